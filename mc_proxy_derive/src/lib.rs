@@ -0,0 +1,708 @@
+// `#[derive(Parsable)]` writes `empty`, `parse_packet`, `serialize` and `get_printable` for a
+// packet struct, going through the fields in order. How a field is sent is picked from its type:
+//
+//   bool, i8 (byte), u8 (unsigned byte), i16, u16, i32 (int), i64 (long), f32, f64,
+//   u128 (UUID), String, Option<T> (bool followed by the value if it's true),
+//   Vec<u8> (the rest of the packet), and anything else goes through `packet::Field`.
+//
+// Attributes change that where the type alone isn't enough:
+//
+//   #[varint] i32, #[varlong] i64, #[angle] u8, #[position] (i64, i64, i64),
+//   #[chat] String, #[identifier] String,
+//   #[length_prefixed] Vec<T> (VarInt length, then the elements),
+//   #[since(version)] / #[before(version)] to only send the field in some protocol versions.
+//
+// For `Vec` and `Option` the type attributes apply to the elements, so
+// `#[length_prefixed] #[varint] Vec<i32>` is a VarInt length followed by VarInts.
+// Fields that are only in some versions are left out of the printable output when they're
+// still at their default value, so they need to implement `PartialEq`.
+//
+// The JSON output comes from `Serialize`, so the struct has to derive that as well. UUIDs,
+// positions, chat and bytes need `#[serde(serialize_with = "json::...")]` to look right, and
+// leaving it out is an error. `to_json` leaves out the fields that aren't in the protocol version.
+//
+// Packets that update the status say so on the struct, with a function that's given the packet
+// and the status, and a printable can be swapped out the same way:
+//
+//   #[parsable(update_status = Self::set_block, get_printable = Self::printable)]
+//
+// Packets with a layout these can't describe implement `Parsable` by hand.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Expr, Fields, GenericArgument, PathArguments, Type,
+};
+
+#[proc_macro_derive(
+    Parsable,
+    attributes(
+        varint,
+        varlong,
+        angle,
+        position,
+        chat,
+        identifier,
+        length_prefixed,
+        since,
+        before,
+        parsable
+    )
+)]
+pub fn derive_parsable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_parsable(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+// `#[derive(Field)]` is the same thing for structs that are part of a packet, like the elements
+// of an array.
+#[proc_macro_derive(
+    Field,
+    attributes(
+        varint,
+        varlong,
+        angle,
+        position,
+        chat,
+        identifier,
+        length_prefixed,
+        since,
+        before
+    )
+)]
+pub fn derive_field(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_field(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+// How a single value is put on the wire.
+enum Kind {
+    Bool,
+    Byte,
+    UByte,
+    Short,
+    UShort,
+    Int,
+    Long,
+    Float,
+    Double,
+    Uuid,
+    String,
+    Chat,
+    Identifier,
+    VarInt,
+    VarLong,
+    Angle,
+    Position,
+    // A byte array, with a VarInt length in front of it or taking up the rest of the packet.
+    Bytes { length_prefixed: bool },
+    Array(Box<Kind>),
+    Optional(Box<Kind>),
+    Custom(Box<Type>),
+}
+
+// The attributes on a field that pick a different encoding.
+#[derive(Default)]
+struct Encoding {
+    varint: bool,
+    varlong: bool,
+    angle: bool,
+    position: bool,
+    chat: bool,
+    identifier: bool,
+    length_prefixed: bool,
+}
+
+struct Field {
+    name: syn::Member,
+    // What it's called while it's being decoded, so it can't clash with the generated code.
+    local: syn::Ident,
+    ty: Type,
+    kind: Kind,
+    since: Option<Expr>,
+    before: Option<Expr>,
+}
+
+fn fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "this can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "this can only be derived for structs",
+            ))
+        }
+    };
+    fields.iter().map(parse_field).collect()
+}
+
+// Statements that read every field into its own variable.
+fn decode_fields(fields: &[Field]) -> TokenStream2 {
+    let statements = fields.iter().map(|field| {
+        let local = &field.local;
+        let decode = decode(&field.kind);
+        match gate_condition(field, quote!(packet.protocol_version())) {
+            Some(condition) => quote! {
+                let #local = if #condition {
+                    #decode
+                } else {
+                    ::std::default::Default::default()
+                };
+            },
+            None => quote!(let #local = #decode;),
+        }
+    });
+    quote!(#(#statements)*)
+}
+
+// Statements that write every field of `self` to `packet`.
+fn encode_fields(fields: &[Field]) -> TokenStream2 {
+    let statements = fields.iter().map(|field| {
+        let name = &field.name;
+        let statement = encode(&field.kind, quote!((&self.#name)));
+        match gate_condition(field, quote!(packet.protocol_version())) {
+            Some(condition) => quote! {
+                if #condition {
+                    #statement
+                }
+            },
+            None => statement,
+        }
+    });
+    quote!(#(#statements)*)
+}
+
+// An expression with all the fields of `self` printed, separated by spaces.
+fn printable_fields(fields: &[Field]) -> TokenStream2 {
+    let parts = fields.iter().map(|field| {
+        let name = &field.name;
+        let ty = &field.ty;
+        let printable = printable(&field.kind, quote!((&self.#name)));
+        if field.since.is_some() || field.before.is_some() {
+            quote! {
+                if self.#name != <#ty as ::std::default::Default>::default() {
+                    parts.push(#printable);
+                }
+            }
+        } else {
+            quote!(parts.push(#printable);)
+        }
+    });
+    quote! {{
+        let mut parts: Vec<String> = Vec::new();
+        #(#parts)*
+        parts.join(" ")
+    }}
+}
+
+// `to_json`, if there are fields that aren't in every version. Those are taken back out of
+// what `Serialize` gives when they aren't in the protocol version.
+fn to_json(fields: &[Field]) -> TokenStream2 {
+    let removals: Vec<_> = fields
+        .iter()
+        .filter_map(|field| {
+            let condition = gate_condition(field, quote!(protocol_version))?;
+            let key = match &field.name {
+                syn::Member::Named(ident) => ident.unraw().to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            };
+            Some(quote! {
+                if !(#condition) {
+                    fields.remove(#key);
+                }
+            })
+        })
+        .collect();
+    if removals.is_empty() {
+        return quote!();
+    }
+    quote! {
+        fn to_json(&self, protocol_version: i32) -> ::serde_json::Value {
+            let mut value = crate::json::ToJson::json_value(self);
+            if let ::serde_json::Value::Object(fields) = &mut value {
+                #(#removals)*
+            }
+            value
+        }
+    }
+}
+
+// The functions in `#[parsable(...)]` on a packet struct.
+#[derive(Default)]
+struct Hooks {
+    update_status: Option<syn::ExprPath>,
+    get_printable: Option<syn::ExprPath>,
+}
+
+fn hooks(input: &DeriveInput) -> syn::Result<Hooks> {
+    let mut hooks = Hooks::default();
+    for attr in &input.attrs {
+        if !attr.path().is_ident("parsable") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            let hook = if meta.path.is_ident("update_status") {
+                &mut hooks.update_status
+            } else if meta.path.is_ident("get_printable") {
+                &mut hooks.get_printable
+            } else {
+                return Err(meta.error("expected `update_status` or `get_printable`"));
+            };
+            *hook = Some(meta.value()?.parse()?);
+            Ok(())
+        })?;
+    }
+    Ok(hooks)
+}
+
+fn expand_parsable(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = fields(&input)?;
+    let hooks = hooks(&input)?;
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let locals: Vec<_> = fields.iter().map(|field| &field.local).collect();
+    let decode = decode_fields(&fields);
+    let encode = encode_fields(&fields);
+    let printable = match &hooks.get_printable {
+        Some(get_printable) => quote!(#get_printable(self)),
+        None => printable_fields(&fields),
+    };
+    let json = to_json(&fields);
+    let update_status = hooks.update_status.map(|update_status| {
+        quote! {
+            fn status_updating(&self) -> bool {
+                true
+            }
+
+            fn update_status(
+                &self,
+                status: &mut crate::types::Status,
+            ) -> ::std::result::Result<(), crate::error::Error> {
+                #update_status(self, status)
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl crate::packet::Parsable for #name {
+            fn empty() -> Self {
+                Self {
+                    #(#names: ::std::default::Default::default(),)*
+                }
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn parse_packet(
+                &mut self,
+                mut packet: crate::packet::Packet,
+            ) -> ::std::result::Result<(), crate::error::Error> {
+                let packet = &mut packet;
+                #decode
+                #(self.#names = #locals;)*
+                Ok(())
+            }
+
+            #[allow(unused_mut)]
+            fn serialize(&self, protocol_version: i32) -> crate::packet::Packet {
+                let mut packet = crate::packet::Packet::with_version(protocol_version);
+                {
+                    let packet = &mut packet;
+                    #encode
+                }
+                packet
+            }
+
+            #[allow(unused_mut)]
+            fn get_printable(&self) -> String {
+                #printable
+            }
+
+            #json
+
+            #update_status
+        }
+    })
+}
+
+fn expand_field(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = fields(&input)?;
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let locals: Vec<_> = fields.iter().map(|field| &field.local).collect();
+    let decode = decode_fields(&fields);
+    let encode = encode_fields(&fields);
+    let printable = printable_fields(&fields);
+
+    Ok(quote! {
+        impl crate::packet::Field for #name {
+            #[allow(unused_variables)]
+            fn decode(
+                packet: &mut crate::packet::Packet,
+            ) -> ::std::result::Result<Self, crate::error::Error> {
+                #decode
+                Ok(Self {
+                    #(#names: #locals,)*
+                })
+            }
+
+            #[allow(unused_variables)]
+            fn encode(&self, packet: &mut crate::packet::Packet) {
+                #encode
+            }
+
+            #[allow(unused_mut)]
+            fn printable(&self) -> String {
+                #printable
+            }
+        }
+    })
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<Field> {
+    let mut encoding = Encoding::default();
+    let mut since = None;
+    let mut before = None;
+    for attr in &field.attrs {
+        let path = attr.path();
+        if path.is_ident("since") {
+            since = Some(attr.parse_args::<Expr>()?);
+        } else if path.is_ident("before") {
+            before = Some(attr.parse_args::<Expr>()?);
+        } else {
+            let flag = if path.is_ident("varint") {
+                &mut encoding.varint
+            } else if path.is_ident("varlong") {
+                &mut encoding.varlong
+            } else if path.is_ident("angle") {
+                &mut encoding.angle
+            } else if path.is_ident("position") {
+                &mut encoding.position
+            } else if path.is_ident("chat") {
+                &mut encoding.chat
+            } else if path.is_ident("identifier") {
+                &mut encoding.identifier
+            } else if path.is_ident("length_prefixed") {
+                &mut encoding.length_prefixed
+            } else {
+                continue;
+            };
+            attr.meta.require_path_only()?;
+            *flag = true;
+        }
+    }
+    let name = match &field.ident {
+        Some(ident) => syn::Member::Named(ident.clone()),
+        None => return Err(syn::Error::new(field.span(), "field needs a name")),
+    };
+    let kind = kind(&field.ty, &mut encoding)?;
+    // Anything that's still set here wasn't used by the type it was put on.
+    let unused = [
+        (encoding.varint, "varint"),
+        (encoding.varlong, "varlong"),
+        (encoding.angle, "angle"),
+        (encoding.position, "position"),
+        (encoding.chat, "chat"),
+        (encoding.identifier, "identifier"),
+        (encoding.length_prefixed, "length_prefixed"),
+    ];
+    if let Some((_, attr)) = unused.iter().find(|(set, _)| *set) {
+        return Err(syn::Error::new(
+            field.ty.span(),
+            format!("#[{}] can't be used on this type", attr),
+        ));
+    }
+    let serialized_with = field.attrs.iter().any(|attr| attr.path().is_ident("serde"));
+    if needs_serialize_with(&kind) && !serialized_with {
+        return Err(syn::Error::new(
+            field.ty.span(),
+            "this needs #[serde(serialize_with = \"json::...\")] for the JSON output",
+        ));
+    }
+    let local = match &field.ident {
+        Some(ident) => quote::format_ident!("field_{}", ident.unraw()),
+        None => unreachable!(),
+    };
+    Ok(Field {
+        name,
+        local,
+        ty: field.ty.clone(),
+        kind,
+        since,
+        before,
+    })
+}
+
+// Works out the kind for a type, clearing the attributes it used.
+fn kind(ty: &Type, encoding: &mut Encoding) -> syn::Result<Kind> {
+    if let Type::Tuple(_) = ty {
+        if encoding.position {
+            encoding.position = false;
+            return Ok(Kind::Position);
+        }
+        return Ok(Kind::Custom(Box::new(ty.clone())));
+    }
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => match path.path.segments.last() {
+            Some(segment) => segment,
+            None => return Ok(Kind::Custom(Box::new(ty.clone()))),
+        },
+        _ => return Ok(Kind::Custom(Box::new(ty.clone()))),
+    };
+    let name = segment.ident.to_string();
+    let kind = match name.as_str() {
+        "bool" => Kind::Bool,
+        "i8" => Kind::Byte,
+        "u8" if encoding.angle => {
+            encoding.angle = false;
+            Kind::Angle
+        }
+        "u8" => Kind::UByte,
+        "i16" => Kind::Short,
+        "u16" => Kind::UShort,
+        "i32" if encoding.varint => {
+            encoding.varint = false;
+            Kind::VarInt
+        }
+        "i32" => Kind::Int,
+        "i64" if encoding.varlong => {
+            encoding.varlong = false;
+            Kind::VarLong
+        }
+        "i64" => Kind::Long,
+        "f32" => Kind::Float,
+        "f64" => Kind::Double,
+        "u128" => Kind::Uuid,
+        "String" if encoding.chat => {
+            encoding.chat = false;
+            Kind::Chat
+        }
+        "String" if encoding.identifier => {
+            encoding.identifier = false;
+            Kind::Identifier
+        }
+        "String" => Kind::String,
+        "Vec" => {
+            let inner = generic_argument(segment)?;
+            let length_prefixed = encoding.length_prefixed;
+            encoding.length_prefixed = false;
+            if is_u8(inner) && !encoding.angle {
+                Kind::Bytes { length_prefixed }
+            } else if length_prefixed {
+                Kind::Array(Box::new(kind(inner, encoding)?))
+            } else {
+                return Err(syn::Error::new(
+                    ty.span(),
+                    "a Vec that isn't of bytes needs #[length_prefixed]",
+                ));
+            }
+        }
+        "Option" => Kind::Optional(Box::new(kind(generic_argument(segment)?, encoding)?)),
+        _ => Kind::Custom(Box::new(ty.clone())),
+    };
+    Ok(kind)
+}
+
+// The kinds that `Serialize` doesn't write the way the JSON output shows them.
+fn needs_serialize_with(kind: &Kind) -> bool {
+    match kind {
+        Kind::Uuid | Kind::Position | Kind::Chat | Kind::Bytes { .. } => true,
+        Kind::Array(inner) | Kind::Optional(inner) => needs_serialize_with(inner),
+        _ => false,
+    }
+}
+
+fn generic_argument(segment: &syn::PathSegment) -> syn::Result<&Type> {
+    if let PathArguments::AngleBracketed(arguments) = &segment.arguments {
+        if let Some(GenericArgument::Type(ty)) = arguments.args.first() {
+            return Ok(ty);
+        }
+    }
+    Err(syn::Error::new(segment.span(), "expected a type argument"))
+}
+
+fn is_u8(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.is_ident("u8"),
+        _ => false,
+    }
+}
+
+// The condition for the protocol versions a field is in, if it isn't in all of them.
+fn gate_condition(field: &Field, version: TokenStream2) -> Option<TokenStream2> {
+    let mut conditions = Vec::new();
+    if let Some(since) = &field.since {
+        conditions.push(quote!(#version >= #since));
+    }
+    if let Some(before) = &field.before {
+        conditions.push(quote!(#version < #before));
+    }
+    if conditions.is_empty() {
+        None
+    } else {
+        Some(quote!(#(#conditions)&&*))
+    }
+}
+
+// An expression that reads a value of this kind from `packet`.
+fn decode(kind: &Kind) -> TokenStream2 {
+    match kind {
+        Kind::Bool => quote!(packet.decode_bool()?),
+        Kind::Byte => quote!(packet.decode_byte()?),
+        Kind::UByte => quote!(packet.decode_ubyte()?),
+        Kind::Short => quote!(packet.decode_short()?),
+        Kind::UShort => quote!(packet.decode_ushort()?),
+        Kind::Int => quote!(packet.decode_int()?),
+        Kind::Long => quote!(packet.decode_long()?),
+        Kind::Float => quote!(packet.decode_float()?),
+        Kind::Double => quote!(packet.decode_double()?),
+        Kind::Uuid => quote!(packet.decode_uuid()?),
+        Kind::String => quote!(packet.decode_string()?),
+        Kind::Chat => quote!(packet.decode_chat()?),
+        Kind::Identifier => quote!(packet.decode_identifier()?),
+        Kind::VarInt => quote!(packet.decode_varint()?),
+        Kind::VarLong => quote!(packet.decode_varlong()?),
+        Kind::Angle => quote!(packet.decode_angle()?),
+        Kind::Position => quote!(packet.decode_position()?),
+        Kind::Bytes {
+            length_prefixed: true,
+        } => quote! {{
+            let length = packet.decode_varint()?;
+            packet.read_array(length)?
+        }},
+        Kind::Bytes {
+            length_prefixed: false,
+        } => quote! {{
+            let length = packet.len();
+            packet.read(length)?
+        }},
+        Kind::Array(inner) => {
+            let inner = decode(inner);
+            quote! {{
+                let count = packet.decode_count()?;
+                let mut values = Vec::with_capacity(count);
+                for _ in 0..count {
+                    values.push(#inner);
+                }
+                values
+            }}
+        }
+        Kind::Optional(inner) => {
+            let inner = decode(inner);
+            quote! {
+                if packet.decode_bool()? {
+                    Some(#inner)
+                } else {
+                    None
+                }
+            }
+        }
+        Kind::Custom(ty) => quote_spanned! {ty.span()=>
+            <#ty as crate::packet::Field>::decode(packet)?
+        },
+    }
+}
+
+// Statements that write `value` (a reference) to `packet`.
+fn encode(kind: &Kind, value: TokenStream2) -> TokenStream2 {
+    match kind {
+        Kind::Bool => quote!(packet.encode_bool(*#value);),
+        Kind::Byte => quote!(packet.encode_byte(*#value);),
+        Kind::UByte => quote!(packet.encode_ubyte(*#value);),
+        Kind::Short => quote!(packet.encode_short(*#value);),
+        Kind::UShort => quote!(packet.encode_ushort(*#value);),
+        Kind::Int => quote!(packet.encode_int(*#value);),
+        Kind::Long => quote!(packet.encode_long(*#value);),
+        Kind::Float => quote!(packet.encode_float(*#value);),
+        Kind::Double => quote!(packet.encode_double(*#value);),
+        Kind::Uuid => quote!(packet.encode_uuid(*#value);),
+        Kind::String => quote!(packet.encode_string(#value);),
+        Kind::Chat => quote!(packet.encode_chat(#value);),
+        Kind::Identifier => quote!(packet.encode_identifier(#value);),
+        Kind::VarInt => quote!(packet.encode_varint(*#value);),
+        Kind::VarLong => quote!(packet.encode_varlong(*#value);),
+        Kind::Angle => quote!(packet.encode_angle(*#value);),
+        Kind::Position => quote!(packet.encode_position(*#value);),
+        Kind::Bytes {
+            length_prefixed: true,
+        } => quote! {
+            packet.encode_varint(#value.len() as i32);
+            packet.push_vec(#value.clone());
+        },
+        Kind::Bytes {
+            length_prefixed: false,
+        } => quote!(packet.push_vec(#value.clone());),
+        Kind::Array(inner) => {
+            let inner = encode(inner, quote!(value));
+            quote! {
+                packet.encode_varint(#value.len() as i32);
+                for value in #value.iter() {
+                    #inner
+                }
+            }
+        }
+        Kind::Optional(inner) => {
+            let inner = encode(inner, quote!(value));
+            quote! {
+                packet.encode_bool(#value.is_some());
+                if let Some(value) = #value {
+                    #inner
+                }
+            }
+        }
+        Kind::Custom(ty) => quote_spanned! {ty.span()=>
+            <#ty as crate::packet::Field>::encode(#value, packet);
+        },
+    }
+}
+
+// A `String` expression with `value` (a reference) the way it should be printed.
+fn printable(kind: &Kind, value: TokenStream2) -> TokenStream2 {
+    match kind {
+        Kind::Uuid => quote!(format!("{:x}", #value)),
+        Kind::Position => quote!(format!("{} {} {}", #value.0, #value.1, #value.2)),
+        Kind::Bytes { .. } => quote!(hex::encode(#value)),
+        Kind::Array(inner) => {
+            let inner = printable(inner, quote!(value));
+            quote! {
+                format!(
+                    "[{}]",
+                    #value
+                        .iter()
+                        .map(|value| #inner)
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+        }
+        Kind::Optional(inner) => {
+            let inner = printable(inner, quote!(value));
+            quote! {
+                match #value {
+                    Some(value) => #inner,
+                    None => "None".to_string(),
+                }
+            }
+        }
+        Kind::Custom(ty) => quote_spanned! {ty.span()=>
+            <#ty as crate::packet::Field>::printable(#value)
+        },
+        _ => quote!(#value.to_string()),
+    }
+}
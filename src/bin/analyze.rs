@@ -0,0 +1,139 @@
+// Runs recorded captures through the parsers and prints them the way the proxy does, with the
+// parsing_packets and printing_packets from settings.toml.
+//
+//   cargo run --bin analyze -- captures/1618000000000-0.mccap [more captures]
+//
+// With --pcapng they're also exported for Wireshark, every capture as a TCP connection with the
+// packets uncompressed and what they were parsed as in the comments.
+//
+//   cargo run --bin analyze -- --pcapng session.pcapng captures/1618000000000-0.mccap
+//
+// --json writes the printed packets as JSON too, the way json_output does in the proxy. Every
+// capture is a connection, and the timestamps are when they were recorded.
+//
+//   cargo run --bin analyze -- --json - captures/*.mccap | jq .packet
+use mc_proxy::capture::Capture;
+use mc_proxy::{functions, json, pcapng, schema, types, utils, versions, Packet};
+
+fn main() {
+    utils::init_logger();
+
+    let mut args = std::env::args().skip(1);
+    let mut paths = Vec::new();
+    let mut export = None;
+    let mut json_path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pcapng" => export = args.next(),
+            "--json" => json_path = args.next(),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: analyze [--pcapng <output>] [--json <output>] <capture>...");
+        std::process::exit(2);
+    }
+    let output = json_path.map(|path| match json::Output::open(&path) {
+        Ok(output) => output,
+        Err(err) => panic!("{}", err),
+    });
+    let mut writer = export.as_ref().map(|_| pcapng::Writer::new());
+    let mut exported = 0;
+
+    let config = match types::ConfigFormat::load("settings") {
+        Ok(config) => config,
+        Err(err) => panic!("Could not parse config file!\n{}", err),
+    };
+    let mut functions = functions::get_functions();
+    if let Some(path) = &config.packet_schema {
+        match schema::Schema::load(path) {
+            Ok(schema) => functions.add_schema(&schema),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    for (connection, path) in paths.into_iter().enumerate() {
+        let capture = match Capture::load(&path) {
+            Ok(capture) => capture,
+            Err(err) => {
+                log::error!("{}", err);
+                continue;
+            }
+        };
+        let handshake = &capture.header.handshake;
+        log::info!(
+            "{}: {}:{}, protocol version {} ({}), {} packets",
+            path,
+            handshake.server_address,
+            handshake.server_port,
+            handshake.protocol_version,
+            versions::name(handshake.protocol_version),
+            capture.entries.len()
+        );
+
+        // Every entry has the state and version it was parsed in live, so there's no need to
+        // keep a status to know how to parse the next one.
+        let mut comments = Vec::with_capacity(capture.entries.len());
+        for entry in &capture.entries {
+            comments.push(None);
+            let protocol_version = versions::parsing_version(entry.protocol_version);
+            let func_name = functions.get_name(
+                &entry.direction,
+                &entry.state,
+                protocol_version,
+                &entry.packet_id,
+            );
+            let (func_name, func) = match (func_name, func_name.and_then(|f| functions.get(f))) {
+                (Some(func_name), Some(func)) if config.parses(&func_name.to_string()) => {
+                    (func_name, func)
+                }
+                _ => continue,
+            };
+            let mut packet = Packet::from(entry.payload.clone());
+            packet.set_protocol_version(protocol_version);
+            let mut parsed_packet = dyn_clone::clone_box(func);
+            match parsed_packet.parse_packet(packet) {
+                Ok(_) => {
+                    *comments.last_mut().unwrap() = Some(format!(
+                        "{} [{}] {}",
+                        entry.direction,
+                        func_name,
+                        parsed_packet.get_printable()
+                    ));
+                    if config.prints(&func_name.to_string()) {
+                        utils::log_packet(
+                            &entry.direction,
+                            &func_name.to_string(),
+                            &parsed_packet.get_printable(),
+                        );
+                        if let Some(output) = &output {
+                            output.write(&json::Record {
+                                connection,
+                                timestamp: capture.header.start.max(0) as u64 + entry.time,
+                                direction: &entry.direction,
+                                state: &entry.state,
+                                packet: &func_name.to_string(),
+                                fields: parsed_packet.to_json(protocol_version),
+                            });
+                        }
+                    }
+                }
+                Err(err) => log::error!(
+                    "Could not parse packet: {}",
+                    err.with_context(&entry.direction, &entry.state, Some(func_name.to_string()))
+                ),
+            }
+        }
+        if let Some(writer) = &mut writer {
+            pcapng::add_capture(writer, &capture, &comments, exported);
+            exported += 1;
+        }
+    }
+
+    if let (Some(path), Some(writer)) = (export, writer) {
+        match std::fs::write(&path, writer.into_bytes()) {
+            Ok(_) => log::info!("Exported {} captures to {}", exported, path),
+            Err(why) => log::error!("Could not write {}: {}", path, why),
+        }
+    }
+}
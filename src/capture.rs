@@ -0,0 +1,483 @@
+use crate::error::Error;
+use crate::packet::{Packet, Parsable};
+use crate::serverbound::handshaking::Handshake;
+use crate::types::{Direction, State, Status};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// A capture file records one connection. Everything is encoded the way packets are:
+//
+//   header: "MCCAPTURE", the format version (ubyte), when it started (long, ms since the epoch),
+//           the compression threshold (int, -1 if it's off) and the handshake (VarInt length + payload)
+//   entry:  the time (VarLong, ms since it started), the direction (ubyte, 0 serverbound and
+//           1 clientbound), the state (ubyte, 0 to 3 from handshaking to play), the protocol
+//           version (VarInt), the packet ID (VarInt) and the payload (VarInt length + bytes)
+//
+// The payloads are decrypted and decompressed, the way the parsers get them. The compression
+// threshold is at a fixed place, so it can be filled in once the server turns compression on.
+pub const MAGIC: &[u8] = b"MCCAPTURE";
+pub const FORMAT_VERSION: u8 = 1;
+const COMPRESSION_OFFSET: u64 = MAGIC.len() as u64 + 1 + 8;
+pub const EXTENSION: &str = "mccap";
+
+// How long entries can sit in the buffer before they're written out anyway.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Every connection gets its own file, even when they start in the same millisecond.
+static CAPTURES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone)]
+pub struct Header {
+    pub start: i64,
+    pub compression: Option<u32>,
+    pub handshake: Handshake,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub time: u64,
+    pub direction: Direction,
+    pub state: State,
+    pub protocol_version: i32,
+    pub packet_id: i32,
+    pub payload: Vec<u8>,
+}
+
+impl Entry {
+    fn encode(&self, packet: &mut Packet) {
+        packet.encode_varlong(self.time as i64);
+        packet.encode_ubyte(match self.direction {
+            Direction::Serverbound => 0,
+            Direction::Clientbound => 1,
+        });
+        packet.encode_ubyte(match self.state {
+            State::Handshaking => 0,
+            State::Status => 1,
+            State::Login => 2,
+            State::Play => 3,
+        });
+        packet.encode_varint(self.protocol_version);
+        packet.encode_varint(self.packet_id);
+        packet.encode_varint(self.payload.len() as i32);
+        packet.push_vec(self.payload.clone());
+    }
+
+    fn decode(packet: &mut Packet) -> Result<Self, String> {
+        let to_string = |err: Error| err.to_string();
+        let time = packet.decode_varlong().map_err(to_string)?;
+        let direction = match packet.decode_ubyte().map_err(to_string)? {
+            0 => Direction::Serverbound,
+            1 => Direction::Clientbound,
+            value => return Err(format!("{} is not a direction", value)),
+        };
+        let state = match packet.decode_ubyte().map_err(to_string)? {
+            0 => State::Handshaking,
+            1 => State::Status,
+            2 => State::Login,
+            3 => State::Play,
+            value => return Err(format!("{} is not a state", value)),
+        };
+        let protocol_version = packet.decode_varint().map_err(to_string)?;
+        let packet_id = packet.decode_varint().map_err(to_string)?;
+        let length = packet.decode_varint().map_err(to_string)?;
+        let payload = packet.read_array(length).map_err(to_string)?;
+        Ok(Self {
+            time: time as u64,
+            direction,
+            state,
+            protocol_version,
+            packet_id,
+            payload,
+        })
+    }
+}
+
+// A capture file that was read back.
+pub struct Capture {
+    pub header: Header,
+    pub entries: Vec<Entry>,
+}
+
+impl Capture {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|why| format!("Could not read {}: {}", path, why))?;
+        Self::from_bytes(data).map_err(|why| format!("Could not load {}: {}", path, why))
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, String> {
+        let mut packet = Packet::from(data);
+        let to_string = |err: Error| err.to_string();
+        if packet.read(MAGIC.len()).ok().as_deref() != Some(MAGIC) {
+            return Err("it's not a capture file".into());
+        }
+        let version = packet.decode_ubyte().map_err(to_string)?;
+        if version != FORMAT_VERSION {
+            return Err(format!("format version {} is not supported", version));
+        }
+        let start = packet.decode_long().map_err(to_string)?;
+        let compression = packet.decode_int().map_err(to_string)?;
+        let length = packet.decode_varint().map_err(to_string)?;
+        let mut handshake = Handshake::empty();
+        handshake
+            .parse_packet(Packet::from(packet.read_array(length).map_err(to_string)?))
+            .map_err(|err| format!("invalid handshake: {}", err))?;
+        let header = Header {
+            start,
+            compression: match compression {
+                -1 => None,
+                threshold => Some(threshold as u32),
+            },
+            handshake,
+        };
+        // A capture that was cut off in the middle of an entry still has the ones before it.
+        let mut entries = Vec::new();
+        while !packet.is_empty() {
+            match Entry::decode(&mut packet) {
+                Ok(entry) => entries.push(entry),
+                Err(why) => {
+                    log::warn!("The capture ends with a broken entry: {}", why);
+                    break;
+                }
+            }
+        }
+        Ok(Self { header, entries })
+    }
+}
+
+// Records a connection. The file is only made once the handshake comes in, that's what the
+// header needs. Server list pings aren't recorded, there's nothing in them worth keeping.
+// Entries are buffered, the file is written when the buffer is full, when compression is turned
+// on, at most a second after an entry comes in and when the connection ends.
+pub struct Recorder {
+    directory: PathBuf,
+    file: Option<BufWriter<File>>,
+    start: Instant,
+    flushed: Instant,
+    compression: Option<u32>,
+    protocol_version: i32,
+}
+
+impl Recorder {
+    pub fn new(directory: &str) -> Self {
+        Self {
+            directory: PathBuf::from(directory),
+            file: None,
+            start: Instant::now(),
+            flushed: Instant::now(),
+            compression: None,
+            protocol_version: 0,
+        }
+    }
+
+    fn create(&mut self, handshake: &Handshake) -> std::io::Result<PathBuf> {
+        fs::create_dir_all(&self.directory)?;
+        let start = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis() as i64)
+            .unwrap_or(0);
+        let path = self.directory.join(format!(
+            "{}-{}.{}",
+            start,
+            CAPTURES.fetch_add(1, Ordering::Relaxed),
+            EXTENSION
+        ));
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let mut header = Packet::new();
+        header.push_vec(MAGIC.to_vec());
+        header.encode_ubyte(FORMAT_VERSION);
+        header.encode_long(start);
+        header.encode_int(-1);
+        let payload = handshake.serialize(handshake.protocol_version).get_vec();
+        header.encode_varint(payload.len() as i32);
+        header.push_vec(payload);
+        file.write_all(header.get_slice())?;
+        self.file = Some(BufWriter::new(file));
+        self.start = Instant::now();
+        self.protocol_version = handshake.protocol_version;
+        Ok(path)
+    }
+
+    fn write(&mut self, entry: &Entry, compression: Option<u32>) -> std::io::Result<()> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        if compression != self.compression {
+            file.seek(SeekFrom::Start(COMPRESSION_OFFSET))?;
+            file.write_all(
+                &compression
+                    .map(|threshold| threshold as i32)
+                    .unwrap_or(-1)
+                    .to_be_bytes(),
+            )?;
+            file.seek(SeekFrom::End(0))?;
+            file.flush()?;
+            self.compression = compression;
+        }
+        let mut packet = Packet::new();
+        entry.encode(&mut packet);
+        file.write_all(packet.get_slice())?;
+        if self.flushed.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.flushed = Instant::now();
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(why) = self.flush() {
+            log::error!("Could not finish recording: {}", why);
+        }
+    }
+}
+
+// Writes out what's still buffered, once the connection is over.
+pub fn flush(status: &mut Status) {
+    if let Some(recorder) = &mut status.capture {
+        if let Err(why) = recorder.flush() {
+            log::error!("Could not finish recording: {}", why);
+        }
+    }
+}
+
+// Records a packet the way it came in, before it's parsed. The first one has to be the
+// handshake, otherwise nothing is recorded.
+pub fn record(status: &mut Status, direction: &Direction, packet_id: i32, payload: &[u8]) {
+    let recorder = match &mut status.capture {
+        Some(recorder) => recorder,
+        None => return,
+    };
+    if recorder.file.is_none() {
+        let mut handshake = Handshake::empty();
+        let is_handshake = status.state == State::Handshaking && packet_id == 0;
+        if !is_handshake
+            || handshake
+                .parse_packet(Packet::from(payload.to_vec()))
+                .is_err()
+        {
+            log::warn!("The connection didn't start with a handshake, it isn't recorded");
+            status.capture = None;
+            return;
+        }
+        if handshake.next_state == State::Status {
+            status.capture = None;
+            return;
+        }
+        match recorder.create(&handshake) {
+            Ok(path) => log::info!("Recording to {}", path.display()),
+            Err(why) => {
+                log::error!("Could not start recording: {}", why);
+                status.capture = None;
+                return;
+            }
+        }
+    }
+    // Before the handshake has been handled the status doesn't know the version yet.
+    let protocol_version = match status.state {
+        State::Handshaking => recorder.protocol_version,
+        _ => status.protocol_version,
+    };
+    let entry = Entry {
+        time: recorder.start.elapsed().as_millis() as u64,
+        direction: direction.clone(),
+        state: status.state.clone(),
+        protocol_version,
+        packet_id,
+        payload: payload.to_vec(),
+    };
+    if let Err(why) = recorder.write(&entry, status.compress) {
+        log::error!("Could not record packet, stopped recording: {}", why);
+        status.capture = None;
+    }
+}
+
+// The capture files in a folder, oldest first.
+pub fn list(directory: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == EXTENSION)
+        })
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+    Ok(paths)
+}
+
+// Captures for the tests, of a 1.16.5 connection to mc:25565 that logs in.
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+
+    pub fn handshake() -> Handshake {
+        let mut handshake = Handshake::empty();
+        handshake.protocol_version = 754;
+        handshake.server_address = "mc".into();
+        handshake.server_port = 25565;
+        handshake.next_state = State::Login;
+        handshake
+    }
+
+    pub fn entry(
+        time: u64,
+        direction: Direction,
+        state: State,
+        packet_id: i32,
+        payload: Vec<u8>,
+    ) -> Entry {
+        Entry {
+            time,
+            direction,
+            state,
+            protocol_version: 754,
+            packet_id,
+            payload,
+        }
+    }
+
+    pub struct Builder {
+        capture: Capture,
+    }
+
+    impl Builder {
+        pub fn new(start: i64, compression: Option<u32>) -> Self {
+            Self {
+                capture: Capture {
+                    header: Header {
+                        start,
+                        compression,
+                        handshake: handshake(),
+                    },
+                    entries: Vec::new(),
+                },
+            }
+        }
+
+        pub fn entry(
+            mut self,
+            time: u64,
+            direction: Direction,
+            state: State,
+            packet_id: i32,
+            payload: Vec<u8>,
+        ) -> Self {
+            self.capture
+                .entries
+                .push(entry(time, direction, state, packet_id, payload));
+            self
+        }
+
+        pub fn build(self) -> Capture {
+            self.capture
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{self, entry};
+    use super::*;
+
+    #[test]
+    fn test_capture() {
+        let directory =
+            std::env::temp_dir().join(format!("mc_proxy_capture_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let mut status = Status::new();
+        status.capture = Some(Recorder::new(directory.to_str().unwrap()));
+
+        let handshake = testing::handshake().serialize(754).get_vec();
+        record(&mut status, &Direction::Serverbound, 0x00, &handshake);
+        status.state = State::Login;
+        status.protocol_version = 754;
+        record(
+            &mut status,
+            &Direction::Serverbound,
+            0x00,
+            &[0x02, b'h', b'i'],
+        );
+        status.compress = Some(256);
+        record(&mut status, &Direction::Clientbound, 0x03, &[0x80, 0x02]);
+        // Turning compression on writes out what came before, the rest once the connection ends.
+        let path = list(&directory).unwrap().pop().unwrap();
+        assert_eq!(
+            Capture::load(path.to_str().unwrap()).unwrap().entries.len(),
+            2
+        );
+        flush(&mut status);
+        assert_eq!(
+            Capture::load(path.to_str().unwrap()).unwrap().entries.len(),
+            3
+        );
+        status.capture = None;
+        // A capture that stops halfway through an entry.
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[0x05, 0x01])
+            .unwrap();
+
+        let capture = Capture::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(capture.header.compression, Some(256));
+        assert_eq!(capture.header.handshake.protocol_version, 754);
+        assert_eq!(capture.header.handshake.server_address, "mc");
+        assert_eq!(capture.header.handshake.server_port, 25565);
+        assert_eq!(capture.header.handshake.next_state, State::Login);
+        assert_eq!(capture.entries.len(), 3);
+        assert_eq!(capture.entries[0].payload, handshake);
+        assert_eq!(capture.entries[0].protocol_version, 754);
+        assert_eq!(capture.entries[0].state, State::Handshaking);
+        assert_eq!(
+            capture.entries[2],
+            entry(
+                capture.entries[2].time,
+                Direction::Clientbound,
+                State::Login,
+                0x03,
+                vec![0x80, 0x02]
+            )
+        );
+        assert!(capture.entries[0].time <= capture.entries[2].time);
+
+        // Neither are server list pings.
+        let mut status = Status::new();
+        status.capture = Some(Recorder::new(directory.to_str().unwrap()));
+        let mut ping = testing::handshake();
+        ping.next_state = State::Status;
+        record(
+            &mut status,
+            &Direction::Serverbound,
+            0x00,
+            &ping.serialize(754).get_vec(),
+        );
+        assert!(status.capture.is_none());
+
+        // Without a handshake nothing is recorded.
+        let mut status = Status::new();
+        status.capture = Some(Recorder::new(directory.to_str().unwrap()));
+        status.state = State::Play;
+        record(&mut status, &Direction::Clientbound, 0x20, &[]);
+        assert!(status.capture.is_none());
+        assert_eq!(list(&directory).unwrap().len(), 1);
+
+        assert!(Capture::from_bytes(b"MCCAPTURE\x02".to_vec()).is_err());
+        assert!(Capture::from_bytes(b"not a capture".to_vec()).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -4,19 +4,41 @@ use cfb8::Cfb8;
 
 type AesCfb8 = Cfb8<Aes128>;
 
+// Cipher holds the stream state for one direction, one for the bytes coming in and one for the bytes going out.
+// They have to be kept apart, because as soon as a packet gets changed the two streams aren't the same anymore.
 pub struct Cipher {
+    decryptor: Option<AesCfb8>,
     encryptor: Option<AesCfb8>,
 }
 
+impl Default for Cipher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Cipher {
     pub fn new() -> Self {
-        Self { encryptor: None }
+        Self {
+            decryptor: None,
+            encryptor: None,
+        }
     }
 
     pub fn decrypt(&mut self, mut data: Vec<u8>) -> Vec<u8> {
+        match &mut self.decryptor {
+            Some(decryptor) => {
+                decryptor.decrypt(data.as_mut_slice());
+                data
+            }
+            None => data,
+        }
+    }
+
+    pub fn encrypt(&mut self, mut data: Vec<u8>) -> Vec<u8> {
         match &mut self.encryptor {
             Some(encryptor) => {
-                encryptor.decrypt(data.as_mut_slice());
+                encryptor.encrypt(data.as_mut_slice());
                 data
             }
             None => data,
@@ -24,11 +46,18 @@ impl Cipher {
     }
 
     pub fn enable(&mut self, key: &[u8]) {
-        let cipher = AesCfb8::new_var(key, key).unwrap();
-        self.encryptor = Some(cipher);
+        self.enable_keys(Some(key), Some(key))
+    }
+
+    // When the proxy does the encryption itself, both sides have their own key (or none at all
+    // if the server is in offline mode).
+    pub fn enable_keys(&mut self, decrypt_key: Option<&[u8]>, encrypt_key: Option<&[u8]>) {
+        self.decryptor = decrypt_key.map(|key| AesCfb8::new_var(key, key).unwrap());
+        self.encryptor = encrypt_key.map(|key| AesCfb8::new_var(key, key).unwrap());
     }
 
     pub fn disable(&mut self) {
-        self.encryptor = None
+        self.decryptor = None;
+        self.encryptor = None;
     }
 }
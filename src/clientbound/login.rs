@@ -1,15 +1,21 @@
+use crate::error::{Error, ErrorKind};
+use crate::json;
 use crate::packet::{Packet, Parsable};
 use crate::types::{State, Status};
 use crate::utils;
+use crate::versions;
 use hex::encode;
+use serde::Serialize;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct EncRequest {
-    server_id: String,
-    public_key_length: i32,
-    public_key: Vec<u8>,
-    verify_token_length: i32,
-    verify_token: Vec<u8>,
+    pub server_id: String,
+    pub public_key_length: i32,
+    #[serde(serialize_with = "json::bytes")]
+    pub public_key: Vec<u8>,
+    pub verify_token_length: i32,
+    #[serde(serialize_with = "json::bytes")]
+    pub verify_token: Vec<u8>,
 }
 
 impl Parsable for EncRequest {
@@ -23,13 +29,23 @@ impl Parsable for EncRequest {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.server_id = packet.decode_string()?;
         self.public_key_length = packet.decode_varint()?;
-        self.public_key = packet.read(self.public_key_length as usize)?;
+        self.public_key = packet.read_array(self.public_key_length)?;
         self.verify_token_length = packet.decode_varint()?;
-        self.verify_token = packet.read(self.verify_token_length as usize)?;
-        return Ok(());
+        self.verify_token = packet.read_array(self.verify_token_length)?;
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_string(&self.server_id);
+        packet.encode_varint(self.public_key_length);
+        packet.push_vec(self.public_key.clone());
+        packet.encode_varint(self.verify_token_length);
+        packet.push_vec(self.verify_token.clone());
+        packet
     }
 
     fn get_printable(&self) -> String {
@@ -42,11 +58,20 @@ impl Parsable for EncRequest {
             utils::make_string_fixed_length(encode(self.verify_token.clone()), 20)
         )
     }
+
+    fn status_updating(&self) -> bool {
+        true
+    }
+
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
+        status.online_mode = true;
+        Ok(())
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct SetCompression {
-    threshold: i32,
+    pub threshold: i32,
 }
 
 impl Parsable for SetCompression {
@@ -54,9 +79,15 @@ impl Parsable for SetCompression {
         Self { threshold: 0 }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.threshold = packet.decode_varint()?;
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.threshold);
+        packet
     }
 
     fn get_printable(&self) -> String {
@@ -67,16 +98,25 @@ impl Parsable for SetCompression {
         true
     }
 
-    fn update_status(&self, status: &mut Status) -> Result<(), ()> {
-        status.compress = self.threshold as u32;
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
+        // A negative threshold means compression is turned off.
+        status.compress = match self.threshold {
+            threshold if threshold < 0 => None,
+            threshold => Some(threshold as u32),
+        };
         Ok(())
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct LoginSuccess {
-    uuid: u128,
-    username: String,
+    #[serde(serialize_with = "json::uuid")]
+    pub uuid: u128,
+    pub username: String,
+    // The UUID as the server wrote it before 1.16, so it's sent on the same even if it had
+    // no dashes or capital letters.
+    #[serde(skip)]
+    pub uuid_string: Option<String>,
 }
 
 impl Parsable for LoginSuccess {
@@ -84,33 +124,80 @@ impl Parsable for LoginSuccess {
         Self {
             uuid: 0,
             username: "".into(),
+            uuid_string: None,
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
-        self.uuid = packet.decode_uuid()?;
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        // Before 1.16 the UUID was sent as a string (with dashes).
+        if packet.protocol_version() < versions::V1_16 {
+            let uuid = packet.decode_string()?;
+            self.uuid = match utils::parse_uuid(&uuid) {
+                Some(uuid) => uuid,
+                None => return Err(packet.error(ErrorKind::BadUuid(uuid))),
+            };
+            self.uuid_string = Some(uuid);
+        } else {
+            self.uuid = packet.decode_uuid()?;
+            self.uuid_string = None;
+        }
         self.username = packet.decode_string()?;
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        if protocol_version < versions::V1_16 {
+            match &self.uuid_string {
+                Some(uuid) => packet.encode_string(uuid),
+                None => packet.encode_string(&utils::format_uuid(self.uuid)),
+            }
+        } else {
+            packet.encode_uuid(self.uuid);
+        }
+        packet.encode_string(&self.username);
+        packet
     }
 
     fn get_printable(&self) -> String {
-        format!("{:x} {}", self.uuid, self.username,)
+        format!("{} {}", utils::format_uuid(self.uuid), self.username)
     }
 
     fn status_updating(&self) -> bool {
         true
     }
 
-    fn update_status(&self, status: &mut Status) -> Result<(), ()> {
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
         status.state = State::Play;
+        status.uuid = Some(self.uuid);
         log::debug!("State updated to {}", status.state);
+        // Without encryption the server is in offline mode, so it should have used the offline UUID.
+        // If it didn't something else (like BungeeCord's IP forwarding) gave it the UUID.
+        if !status.online_mode {
+            let expected = utils::offline_uuid(&self.username);
+            if self.uuid == expected {
+                log::info!(
+                    "{} logged in to an offline-mode server with UUID {}",
+                    self.username,
+                    utils::format_uuid(self.uuid)
+                );
+            } else {
+                log::warn!(
+                    "{} logged in to an offline-mode server with UUID {}, but their offline UUID is {}",
+                    self.username,
+                    utils::format_uuid(self.uuid),
+                    utils::format_uuid(expected)
+                );
+            }
+        }
         Ok(())
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct Disconnect {
-    reason: String,
+    #[serde(serialize_with = "json::chat")]
+    pub reason: String,
 }
 
 impl Parsable for Disconnect {
@@ -118,31 +205,38 @@ impl Parsable for Disconnect {
         Self { reason: "".into() }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.reason = packet.decode_string()?;
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_chat(&self.reason);
+        packet
     }
 
     fn get_printable(&self) -> String {
-        format!("{}", self.reason)
+        self.reason.to_string()
     }
 
     fn status_updating(&self) -> bool {
         true
     }
 
-    fn update_status(&self, status: &mut Status) -> Result<(), ()> {
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
         status.state = State::Handshaking;
         log::debug!("State updated to {}", status.state);
         Ok(())
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct PluginRequest {
-    message_id: i32,
-    channel: String,
-    data: Vec<u8>,
+    pub message_id: i32,
+    pub channel: String,
+    #[serde(serialize_with = "json::bytes")]
+    pub data: Vec<u8>,
 }
 
 impl Parsable for PluginRequest {
@@ -154,11 +248,19 @@ impl Parsable for PluginRequest {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.message_id = packet.decode_varint()?;
         self.channel = packet.decode_string()?;
         self.data = packet.get_vec();
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.message_id);
+        packet.encode_identifier(&self.channel);
+        packet.push_vec(self.data.clone());
+        packet
     }
 
     fn get_printable(&self) -> String {
@@ -1,20 +1,32 @@
-use crate::packet::{Packet, Parsable};
+use crate::download;
+use crate::error::{Error, ErrorKind};
+use crate::json;
+use crate::metadata::{Metadata, ParticleData};
+use crate::nbt::{self, Nbt};
+use crate::packet::{Field, Packet, Parsable};
+use crate::slot::Slot;
+use crate::types::Status;
+use crate::versions;
+use crate::world::ChunkSection;
+use mc_proxy_derive::{Field, Parsable};
+use serde::Serialize;
 
 // 0x00
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct SpawnEntity {
-    entity_id: i32,
-    object_uuid: u128,
-    r#type: i32,
-    x: f64,
-    y: f64,
-    z: f64,
-    pitch: u8,
-    yaw: u8,
-    data: i32,
-    velocity_x: i16,
-    velocity_y: i16,
-    velocity_z: i16,
+    pub entity_id: i32,
+    #[serde(serialize_with = "json::uuid")]
+    pub object_uuid: u128,
+    pub r#type: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub pitch: u8,
+    pub yaw: u8,
+    pub data: i32,
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
 }
 
 impl Parsable for SpawnEntity {
@@ -35,10 +47,15 @@ impl Parsable for SpawnEntity {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.entity_id = packet.decode_varint()?;
         self.object_uuid = packet.decode_uuid()?;
-        self.r#type = packet.decode_varint()?;
+        // Before 1.14 there were less kinds of objects, so the type was a byte.
+        self.r#type = if packet.protocol_version() < versions::V1_14 {
+            packet.decode_byte()? as i32
+        } else {
+            packet.decode_varint()?
+        };
         self.x = packet.decode_double()?;
         self.y = packet.decode_double()?;
         self.z = packet.decode_double()?;
@@ -48,7 +65,28 @@ impl Parsable for SpawnEntity {
         self.velocity_x = packet.decode_short()?;
         self.velocity_y = packet.decode_short()?;
         self.velocity_z = packet.decode_short()?;
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.entity_id);
+        packet.encode_uuid(self.object_uuid);
+        if protocol_version < versions::V1_14 {
+            packet.encode_byte(self.r#type as i8);
+        } else {
+            packet.encode_varint(self.r#type);
+        }
+        packet.encode_double(self.x);
+        packet.encode_double(self.y);
+        packet.encode_double(self.z);
+        packet.encode_angle(self.pitch);
+        packet.encode_angle(self.yaw);
+        packet.encode_int(self.data);
+        packet.encode_short(self.velocity_x);
+        packet.encode_short(self.velocity_y);
+        packet.encode_short(self.velocity_z);
+        packet
     }
 
     fn get_printable(&self) -> String {
@@ -71,130 +109,62 @@ impl Parsable for SpawnEntity {
 }
 
 //0x01
-#[derive(Clone)]
+#[derive(Clone, Parsable, Serialize)]
 pub struct SpawnXpOrb {
-    entity_id: i32,
-    x: f64,
-    y: f64,
-    z: f64,
-    count: i16,
-}
-
-impl Parsable for SpawnXpOrb {
-    fn empty() -> Self {
-        Self {
-            entity_id: 0,
-            x: 0f64,
-            y: 0f64,
-            z: 0f64,
-            count: 0,
-        }
-    }
-
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
-        self.entity_id = packet.decode_varint()?;
-        self.x = packet.decode_double()?;
-        self.y = packet.decode_double()?;
-        self.z = packet.decode_double()?;
-        self.count = packet.decode_short()?;
-        return Ok(());
-    }
-
-    fn get_printable(&self) -> String {
-        format!(
-            "{} {} {} {} {}",
-            self.entity_id, self.x, self.y, self.z, self.count
-        )
-    }
+    #[varint]
+    pub entity_id: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub count: i16,
 }
 
 //0x02
-#[derive(Clone)]
+#[derive(Clone, Parsable, Serialize)]
 pub struct SpawnLivingEntity {
-    entity_id: i32,
-    object_uuid: u128,
-    r#type: i32,
-    x: f64,
-    y: f64,
-    z: f64,
-    yaw: u8,
-    pitch: u8,
-    head_pitch: u8,
-    velocity_x: i16,
-    velocity_y: i16,
-    velocity_z: i16,
-}
-
-impl Parsable for SpawnLivingEntity {
-    fn empty() -> Self {
-        Self {
-            entity_id: 0,
-            object_uuid: 0,
-            r#type: 0,
-            x: 0f64,
-            y: 0f64,
-            z: 0f64,
-            yaw: 0,
-            pitch: 0,
-            head_pitch: 0,
-            velocity_x: 0,
-            velocity_y: 0,
-            velocity_z: 0,
-        }
-    }
-
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
-        self.entity_id = packet.decode_varint()?;
-        self.object_uuid = packet.decode_uuid()?;
-        self.r#type = packet.decode_varint()?;
-        self.x = packet.decode_double()?;
-        self.y = packet.decode_double()?;
-        self.z = packet.decode_double()?;
-        self.yaw = packet.read(1)?[0];
-        self.pitch = packet.read(1)?[0];
-        self.head_pitch = packet.read(1)?[0];
-        self.velocity_x = packet.decode_short()?;
-        self.velocity_y = packet.decode_short()?;
-        self.velocity_z = packet.decode_short()?;
-        return Ok(());
-    }
-
-    fn get_printable(&self) -> String {
-        format!(
-            "{} {:x} {} {} {} {} {} {} {} {} {} {}",
-            self.entity_id,
-            self.object_uuid,
-            self.r#type,
-            self.x,
-            self.y,
-            self.z,
-            self.yaw,
-            self.pitch,
-            self.head_pitch,
-            self.velocity_x,
-            self.velocity_y,
-            self.velocity_z
-        )
-    }
+    #[varint]
+    pub entity_id: i32,
+    #[serde(serialize_with = "json::uuid")]
+    pub object_uuid: u128,
+    #[varint]
+    pub r#type: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    #[angle]
+    pub yaw: u8,
+    #[angle]
+    pub pitch: u8,
+    #[angle]
+    pub head_pitch: u8,
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
+    // Before 1.15 the entity metadata was sent with it.
+    #[before(versions::V1_15)]
+    pub metadata: Metadata,
 }
 
 //0x03
-#[derive(Clone, Debug)]
-enum FacingDirection {
+#[derive(Clone, Debug, Serialize)]
+pub enum FacingDirection {
     North,
     South,
     East,
     West,
 }
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct SpawnPainting {
-    entity_id: i32,
-    object_uuid: u128,
-    motive: i32,
-    x: i64,
-    y: i64,
-    z: i64,
-    direction: FacingDirection,
+    pub entity_id: i32,
+    #[serde(serialize_with = "json::uuid")]
+    pub object_uuid: u128,
+    pub motive: i32,
+    // Before 1.13 the motive was sent by name.
+    pub title: String,
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+    pub direction: FacingDirection,
 }
 
 impl Parsable for SpawnPainting {
@@ -203,6 +173,7 @@ impl Parsable for SpawnPainting {
             entity_id: 0,
             object_uuid: 0,
             motive: 0,
+            title: "".into(),
             x: 0,
             y: 0,
             z: 0,
@@ -210,10 +181,14 @@ impl Parsable for SpawnPainting {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.entity_id = packet.decode_varint()?;
         self.object_uuid = packet.decode_uuid()?;
-        self.motive = packet.decode_varint()?;
+        if packet.protocol_version() < versions::V1_13 {
+            self.title = packet.decode_string()?;
+        } else {
+            self.motive = packet.decode_varint()?;
+        }
         let position = packet.decode_position()?;
         self.x = position.0;
         self.y = position.1;
@@ -223,112 +198,2805 @@ impl Parsable for SpawnPainting {
             0x01 => self.direction = FacingDirection::West,
             0x02 => self.direction = FacingDirection::North,
             0x03 => self.direction = FacingDirection::East,
-            _ => return Err(()),
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "FacingDirection",
+                    value: value as i64,
+                }))
+            }
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.entity_id);
+        packet.encode_uuid(self.object_uuid);
+        if protocol_version < versions::V1_13 {
+            packet.encode_string(&self.title);
+        } else {
+            packet.encode_varint(self.motive);
         }
-        return Ok(());
+        packet.encode_position((self.x, self.y, self.z));
+        packet.encode_ubyte(match self.direction {
+            FacingDirection::South => 0x00,
+            FacingDirection::West => 0x01,
+            FacingDirection::North => 0x02,
+            FacingDirection::East => 0x03,
+        });
+        packet
     }
 
     fn get_printable(&self) -> String {
+        let motive = match self.title.is_empty() {
+            true => self.motive.to_string(),
+            false => self.title.clone(),
+        };
         format!(
             "{} {:x} {} {} {} {} {:?}",
-            self.entity_id, self.object_uuid, self.motive, self.x, self.y, self.z, self.direction,
+            self.entity_id, self.object_uuid, motive, self.x, self.y, self.z, self.direction,
         )
     }
 }
 
 //0x04
-#[derive(Clone)]
+#[derive(Clone, Parsable, Serialize)]
 pub struct SpawnPlayer {
-    entity_id: i32,
-    player_uuid: u128,
-    x: f64,
-    y: f64,
-    z: f64,
-    yaw: u8,
-    pitch: u8,
+    #[varint]
+    pub entity_id: i32,
+    #[serde(serialize_with = "json::uuid")]
+    pub player_uuid: u128,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    #[angle]
+    pub yaw: u8,
+    #[angle]
+    pub pitch: u8,
+    // Before 1.15 the entity metadata was sent with it.
+    #[before(versions::V1_15)]
+    pub metadata: Metadata,
+}
+
+//0x05
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityAnimation {
+    #[varint]
+    pub entity_id: i32,
+    pub animation: u8,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct Statistic {
+    #[varint]
+    pub category_id: i32,
+    #[varint]
+    pub statistic_id: i32,
+    #[varint]
+    pub value: i32,
+}
+
+//0x06
+#[derive(Clone, Parsable, Serialize)]
+pub struct Statistics {
+    #[length_prefixed]
+    pub statistics: Vec<Statistic>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum DiggingStatus {
+    #[default]
+    Started,
+    Cancelled,
+    Finished,
+}
+
+impl Field for DiggingStatus {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        match packet.decode_varint()? {
+            0x00 => Ok(Self::Started),
+            0x01 => Ok(Self::Cancelled),
+            0x02 => Ok(Self::Finished),
+            value => Err(packet.error(ErrorKind::UnknownEnumValue {
+                name: "DiggingStatus",
+                value: value as i64,
+            })),
+        }
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        packet.encode_varint(match self {
+            Self::Started => 0x00,
+            Self::Cancelled => 0x01,
+            Self::Finished => 0x02,
+        });
+    }
+
+    fn printable(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+//0x07
+#[derive(Clone, Parsable, Serialize)]
+pub struct AckPlayerDigging {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    #[varint]
+    pub block: i32,
+    pub status: DiggingStatus,
+    pub successful: bool,
+}
+//0x08
+#[derive(Clone, Parsable, Serialize)]
+pub struct BlockBreakAnimation {
+    #[varint]
+    pub entity_id: i32,
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    pub destroy_stage: i8,
+}
+
+//0x09
+#[derive(Clone, Parsable, Serialize)]
+#[parsable(update_status = Self::set_block_entity)]
+pub struct BlockEntityData {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    pub action: u8,
+    pub data: Nbt,
+}
+
+impl BlockEntityData {
+    // Without any NBT the block entity is removed.
+    fn set_block_entity(&self, status: &mut Status) -> Result<(), Error> {
+        let mut tag = self.data.tag.clone();
+        if let Some(tag) = &mut tag {
+            if tag.get("id").is_none() {
+                if let Some(id) = download::block_entity_id(self.action) {
+                    tag.insert("id", nbt::Tag::String(id.into()));
+                }
+            }
+        }
+        status.world.set_block_entity(self.location, tag);
+        Ok(())
+    }
+}
+
+//0x0a
+#[derive(Clone, Parsable, Serialize)]
+pub struct BlockAction {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    pub action_id: u8,
+    pub action_param: u8,
+    #[varint]
+    pub block_type: i32,
+}
+
+//0x0b
+#[derive(Clone, Parsable, Serialize)]
+#[parsable(update_status = Self::set_block)]
+pub struct BlockChange {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    #[varint]
+    pub block_id: i32,
+}
+
+impl BlockChange {
+    fn set_block(&self, status: &mut Status) -> Result<(), Error> {
+        status.world.set_block(self.location, self.block_id);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum BossBarAction {
+    Add {
+        #[serde(serialize_with = "json::chat")]
+        title: String,
+        health: f32,
+        color: i32,
+        division: i32,
+        flags: u8,
+    },
+    #[default]
+    Remove,
+    UpdateHealth(f32),
+    UpdateTitle(#[serde(serialize_with = "json::chat")] String),
+    UpdateStyle {
+        color: i32,
+        division: i32,
+    },
+    UpdateFlags(u8),
+}
+
+impl Field for BossBarAction {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_varint()? {
+            0 => Self::Add {
+                title: packet.decode_chat()?,
+                health: packet.decode_float()?,
+                color: packet.decode_varint()?,
+                division: packet.decode_varint()?,
+                flags: packet.decode_ubyte()?,
+            },
+            1 => Self::Remove,
+            2 => Self::UpdateHealth(packet.decode_float()?),
+            3 => Self::UpdateTitle(packet.decode_chat()?),
+            4 => Self::UpdateStyle {
+                color: packet.decode_varint()?,
+                division: packet.decode_varint()?,
+            },
+            5 => Self::UpdateFlags(packet.decode_ubyte()?),
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "BossBarAction",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::Add {
+                title,
+                health,
+                color,
+                division,
+                flags,
+            } => {
+                packet.encode_varint(0);
+                packet.encode_chat(title);
+                packet.encode_float(*health);
+                packet.encode_varint(*color);
+                packet.encode_varint(*division);
+                packet.encode_ubyte(*flags);
+            }
+            Self::Remove => packet.encode_varint(1),
+            Self::UpdateHealth(health) => {
+                packet.encode_varint(2);
+                packet.encode_float(*health);
+            }
+            Self::UpdateTitle(title) => {
+                packet.encode_varint(3);
+                packet.encode_chat(title);
+            }
+            Self::UpdateStyle { color, division } => {
+                packet.encode_varint(4);
+                packet.encode_varint(*color);
+                packet.encode_varint(*division);
+            }
+            Self::UpdateFlags(flags) => {
+                packet.encode_varint(5);
+                packet.encode_ubyte(*flags);
+            }
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::Add {
+                title,
+                health,
+                color,
+                division,
+                flags,
+            } => format!("Add {} {} {} {} {}", title, health, color, division, flags),
+            Self::Remove => "Remove".into(),
+            Self::UpdateHealth(health) => format!("UpdateHealth {}", health),
+            Self::UpdateTitle(title) => format!("UpdateTitle {}", title),
+            Self::UpdateStyle { color, division } => {
+                format!("UpdateStyle {} {}", color, division)
+            }
+            Self::UpdateFlags(flags) => format!("UpdateFlags {}", flags),
+        }
+    }
+}
+
+//0x0c
+#[derive(Clone, Parsable, Serialize)]
+pub struct BossBar {
+    #[serde(serialize_with = "json::uuid")]
+    pub uuid: u128,
+    pub action: BossBarAction,
+}
+
+//0x0d
+#[derive(Clone, Parsable, Serialize)]
+pub struct ServerDifficulty {
+    pub difficulty: u8,
+    pub locked: bool,
+}
+
+//0x0e
+#[derive(Clone, Parsable, Serialize)]
+pub struct ChatMessageClientbound {
+    #[chat]
+    #[serde(serialize_with = "json::chat")]
+    pub message: String,
+    pub position: i8,
+    // Before 1.16 the client didn't get told who sent it.
+    #[since(versions::V1_16)]
+    #[serde(serialize_with = "json::uuid")]
+    pub sender: u128,
 }
 
-impl Parsable for SpawnPlayer {
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct TabCompleteMatch {
+    pub text: String,
+    #[chat]
+    #[serde(serialize_with = "json::optional_chat")]
+    pub tooltip: Option<String>,
+}
+
+//0x0f
+#[derive(Clone, Parsable, Serialize)]
+pub struct TabCompleteClientbound {
+    #[varint]
+    pub id: i32,
+    #[varint]
+    pub start: i32,
+    #[varint]
+    pub length: i32,
+    #[length_prefixed]
+    pub matches: Vec<TabCompleteMatch>,
+}
+
+// One node of the command tree. The parser properties are kept as they were sent.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CommandNode {
+    pub flags: i8,
+    pub children: Vec<i32>,
+    pub redirect_node: Option<i32>,
+    pub name: Option<String>,
+    pub parser: Option<String>,
+    #[serde(serialize_with = "json::bytes")]
+    pub properties: Vec<u8>,
+    pub suggestions_type: Option<String>,
+}
+
+impl CommandNode {
+    // 0 is the root, 1 a literal and 2 an argument.
+    pub fn node_type(&self) -> i8 {
+        self.flags & 0x03
+    }
+
+    // The properties are different for every parser, this reads the ones that have any.
+    fn decode_properties(parser: &str, packet: &mut Packet) -> Result<Vec<u8>, Error> {
+        let length = match parser {
+            "brigadier:double" | "brigadier:float" | "brigadier:integer" | "brigadier:long" => {
+                let flags = *packet.get_slice().first().unwrap_or(&0);
+                let size = match parser {
+                    "brigadier:double" | "brigadier:long" => 8,
+                    _ => 4,
+                };
+                1 + (flags & 0x01) as usize * size + ((flags & 0x02) >> 1) as usize * size
+            }
+            "brigadier:string" => {
+                let mut properties = Packet::new();
+                properties.encode_varint(packet.decode_varint()?);
+                return Ok(properties.get_vec());
+            }
+            "minecraft:entity" | "minecraft:score_holder" | "minecraft:range" => 1,
+            _ => 0,
+        };
+        packet.read(length)
+    }
+}
+
+impl Field for CommandNode {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        let mut node = Self {
+            flags: packet.decode_byte()?,
+            ..Default::default()
+        };
+        let count = packet.decode_count()?;
+        for _ in 0..count {
+            node.children.push(packet.decode_varint()?);
+        }
+        if node.flags & 0x08 != 0 {
+            node.redirect_node = Some(packet.decode_varint()?);
+        }
+        if node.node_type() == 1 || node.node_type() == 2 {
+            node.name = Some(packet.decode_string()?);
+        }
+        if node.node_type() == 2 {
+            let parser = packet.decode_identifier()?;
+            node.properties = Self::decode_properties(&parser, packet)?;
+            node.parser = Some(parser);
+        }
+        if node.flags & 0x10 != 0 {
+            node.suggestions_type = Some(packet.decode_identifier()?);
+        }
+        Ok(node)
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        packet.encode_byte(self.flags);
+        packet.encode_varint(self.children.len() as i32);
+        for child in &self.children {
+            packet.encode_varint(*child);
+        }
+        if let Some(redirect_node) = self.redirect_node {
+            packet.encode_varint(redirect_node);
+        }
+        if let Some(name) = &self.name {
+            packet.encode_string(name);
+        }
+        if let Some(parser) = &self.parser {
+            packet.encode_identifier(parser);
+            packet.push_vec(self.properties.clone());
+        }
+        if let Some(suggestions_type) = &self.suggestions_type {
+            packet.encode_identifier(suggestions_type);
+        }
+    }
+
+    fn printable(&self) -> String {
+        let mut printable = match (&self.name, &self.parser) {
+            (Some(name), Some(parser)) => format!("<{}: {}>", name, parser),
+            (Some(name), None) => name.clone(),
+            _ => "root".into(),
+        };
+        if !self.children.is_empty() {
+            printable.push_str(&format!(" -> {:?}", self.children));
+        }
+        if let Some(redirect_node) = self.redirect_node {
+            printable.push_str(&format!(" => {}", redirect_node));
+        }
+        printable
+    }
+}
+
+//0x10
+#[derive(Clone, Parsable, Serialize)]
+pub struct DeclareCommands {
+    #[length_prefixed]
+    pub nodes: Vec<CommandNode>,
+    #[varint]
+    pub root_index: i32,
+}
+
+//0x11
+#[derive(Clone, Parsable, Serialize)]
+pub struct WindowConfirmationClientbound {
+    pub window_id: i8,
+    pub action_number: i16,
+    pub accepted: bool,
+}
+
+//0x12
+#[derive(Clone, Parsable, Serialize)]
+pub struct CloseWindowClientbound {
+    pub window_id: u8,
+}
+
+//0x13
+#[derive(Clone, Serialize)]
+pub struct WindowItems {
+    pub window_id: u8,
+    pub slot_data: Vec<Slot>,
+}
+
+impl Parsable for WindowItems {
     fn empty() -> Self {
         Self {
-            entity_id: 0,
-            player_uuid: 0,
-            x: 0f64,
-            y: 0f64,
-            z: 0f64,
-            yaw: 0,
-            pitch: 0,
+            window_id: 0,
+            slot_data: Vec::new(),
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
-        self.entity_id = packet.decode_varint()?;
-        self.player_uuid = packet.decode_uuid()?;
-        self.x = packet.decode_double()?;
-        self.y = packet.decode_double()?;
-        self.z = packet.decode_double()?;
-        self.yaw = packet.read(1)?[0];
-        self.pitch = packet.read(1)?[0];
-        return Ok(());
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.window_id = packet.decode_ubyte()?;
+        // The count is a Short here.
+        let count = packet.decode_short()?;
+        let count = packet.check_count(count as i32)?;
+        self.slot_data = Vec::with_capacity(count);
+        for _ in 0..count {
+            self.slot_data.push(packet.decode_slot()?);
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_ubyte(self.window_id);
+        packet.encode_short(self.slot_data.len() as i16);
+        for slot in &self.slot_data {
+            packet.encode_slot(slot);
+        }
+        packet
     }
 
+    // Windows have a lot of empty slots, only the ones with something in them are shown.
     fn get_printable(&self) -> String {
+        let items = self
+            .slot_data
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.present)
+            .map(|(index, slot)| format!("{}: {}", index, slot.printable()))
+            .collect::<Vec<String>>();
         format!(
-            "{} {:x} {} {} {} {} {}",
-            self.entity_id, self.player_uuid, self.x, self.y, self.z, self.yaw, self.pitch,
+            "{} {} slots [{}]",
+            self.window_id,
+            self.slot_data.len(),
+            items.join(", ")
         )
     }
 }
 
-#[derive(Clone, Debug)]
-enum DiggingStatus {
-    Started,
-    Cancelled,
-    Finished,
+//0x14
+#[derive(Clone, Parsable, Serialize)]
+pub struct WindowProperty {
+    pub window_id: u8,
+    pub property: i16,
+    pub value: i16,
 }
-//0x07
-#[derive(Clone)]
-pub struct AckPlayerDigging {
-    x: i64,
-    y: i64,
-    z: i64,
-    block: i32,
-    status: DiggingStatus,
-    successful: bool,
+
+//0x15
+#[derive(Clone, Parsable, Serialize)]
+pub struct SetSlot {
+    pub window_id: i8,
+    pub slot: i16,
+    pub slot_data: Slot,
+}
+
+//0x16
+#[derive(Clone, Parsable, Serialize)]
+pub struct SetCooldown {
+    #[varint]
+    pub item_id: i32,
+    #[varint]
+    pub cooldown_ticks: i32,
+}
+
+//0x17
+#[derive(Clone, Parsable, Serialize)]
+pub struct PluginMessageClientbound {
+    #[identifier]
+    pub channel: String,
+    #[serde(serialize_with = "json::bytes")]
+    pub data: Vec<u8>,
+}
+
+//0x18
+#[derive(Clone, Parsable, Serialize)]
+pub struct NamedSoundEffect {
+    #[identifier]
+    pub sound_name: String,
+    #[varint]
+    pub sound_category: i32,
+    // These are fixed point numbers, multiplied by 8.
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub volume: f32,
+    pub pitch: f32,
+}
+
+//0x19
+#[derive(Clone, Parsable, Serialize)]
+pub struct DisconnectPlay {
+    #[chat]
+    #[serde(serialize_with = "json::chat")]
+    pub reason: String,
+}
+
+//0x1a
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityStatus {
+    pub entity_id: i32,
+    pub entity_status: i8,
+}
+
+//0x1b
+#[derive(Clone, Serialize)]
+pub struct Explosion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub strength: f32,
+    // The offsets of the blocks that got destroyed.
+    pub records: Vec<(i8, i8, i8)>,
+    pub motion_x: f32,
+    pub motion_y: f32,
+    pub motion_z: f32,
 }
 
-impl Parsable for AckPlayerDigging {
+impl Parsable for Explosion {
     fn empty() -> Self {
         Self {
-            x: 0,
-            y: 0,
-            z: 0,
-            block: 0,
-            status: DiggingStatus::Started,
-            successful: false,
+            x: 0f32,
+            y: 0f32,
+            z: 0f32,
+            strength: 0f32,
+            records: Vec::new(),
+            motion_x: 0f32,
+            motion_y: 0f32,
+            motion_z: 0f32,
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
-        let position = packet.decode_position()?;
-        self.x = position.0;
-        self.y = position.1;
-        self.z = position.2;
-        self.block = packet.decode_varint()?;
-        self.status = match packet.decode_varint()? {
-            0x00 => DiggingStatus::Started,
-            0x01 => DiggingStatus::Cancelled,
-            0x02 => DiggingStatus::Finished,
-            _ => return Err(()),
-        };
-        self.successful = packet.decode_bool()?;
-        return Ok(());
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.x = packet.decode_float()?;
+        self.y = packet.decode_float()?;
+        self.z = packet.decode_float()?;
+        self.strength = packet.decode_float()?;
+        // Unlike most arrays, this one has an Int as the length.
+        let count = packet.decode_int()?;
+        let count = packet.check_count(count)?;
+        self.records = Vec::with_capacity(count);
+        for _ in 0..count {
+            self.records.push((
+                packet.decode_byte()?,
+                packet.decode_byte()?,
+                packet.decode_byte()?,
+            ));
+        }
+        self.motion_x = packet.decode_float()?;
+        self.motion_y = packet.decode_float()?;
+        self.motion_z = packet.decode_float()?;
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_float(self.x);
+        packet.encode_float(self.y);
+        packet.encode_float(self.z);
+        packet.encode_float(self.strength);
+        packet.encode_int(self.records.len() as i32);
+        for (x, y, z) in &self.records {
+            packet.encode_byte(*x);
+            packet.encode_byte(*y);
+            packet.encode_byte(*z);
+        }
+        packet.encode_float(self.motion_x);
+        packet.encode_float(self.motion_y);
+        packet.encode_float(self.motion_z);
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        format!(
+            "{} {} {} {} {:?} {} {} {}",
+            self.x,
+            self.y,
+            self.z,
+            self.strength,
+            self.records,
+            self.motion_x,
+            self.motion_y,
+            self.motion_z
+        )
+    }
+}
+
+//0x1c
+#[derive(Clone, Parsable, Serialize)]
+#[parsable(update_status = Self::unload)]
+pub struct UnloadChunk {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+}
+
+impl UnloadChunk {
+    fn unload(&self, status: &mut Status) -> Result<(), Error> {
+        let chunk = (self.chunk_x, self.chunk_z);
+        if let Some(download) = &status.download {
+            download.save_chunk(status, chunk);
+        }
+        status.world.unload_chunk(chunk);
+        Ok(())
+    }
+}
+
+//0x1d
+#[derive(Clone, Parsable, Serialize)]
+pub struct ChangeGameState {
+    pub reason: u8,
+    pub value: f32,
+}
+
+//0x1e
+#[derive(Clone, Parsable, Serialize)]
+pub struct OpenHorseWindow {
+    pub window_id: u8,
+    #[varint]
+    pub number_of_slots: i32,
+    pub entity_id: i32,
+}
+
+//0x1f
+#[derive(Clone, Parsable, Serialize)]
+pub struct KeepAliveClientbound {
+    pub keep_alive_id: i64,
+}
+
+//0x20
+#[derive(Clone, Serialize)]
+pub struct ChunkData {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub full_chunk: bool,
+    pub primary_bit_mask: i32,
+    pub heightmaps: Nbt,
+    // Only sent with full chunks.
+    pub biomes: Vec<i32>,
+    // One section for every bit set in the mask, from the lowest bit up.
+    pub sections: Vec<ChunkSection>,
+    pub block_entities: Vec<Nbt>,
+}
+
+impl Parsable for ChunkData {
+    fn empty() -> Self {
+        Self {
+            chunk_x: 0,
+            chunk_z: 0,
+            full_chunk: false,
+            primary_bit_mask: 0,
+            heightmaps: Nbt::default(),
+            biomes: Vec::new(),
+            sections: Vec::new(),
+            block_entities: Vec::new(),
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.chunk_x = packet.decode_int()?;
+        self.chunk_z = packet.decode_int()?;
+        self.full_chunk = packet.decode_bool()?;
+        self.primary_bit_mask = packet.decode_varint()?;
+        self.heightmaps = packet.decode_nbt()?;
+        self.biomes = Vec::new();
+        if self.full_chunk {
+            let count = packet.decode_count()?;
+            for _ in 0..count {
+                self.biomes.push(packet.decode_varint()?);
+            }
+        }
+        // The sections are sent as a byte array, they have to fill it exactly.
+        let length = packet.decode_varint()?;
+        let start = packet.len();
+        self.sections = Vec::new();
+        for _ in 0..self.primary_bit_mask.count_ones() {
+            self.sections.push(ChunkSection::decode(&mut packet)?);
+        }
+        let read = start - packet.len();
+        if length < 0 || length as usize != read {
+            return Err(packet.error(ErrorKind::InvalidLength {
+                length: length as i64,
+                max: read,
+            }));
+        }
+        self.block_entities = packet.decode_array()?;
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_int(self.chunk_x);
+        packet.encode_int(self.chunk_z);
+        packet.encode_bool(self.full_chunk);
+        packet.encode_varint(self.primary_bit_mask);
+        packet.encode_nbt(&self.heightmaps);
+        if self.full_chunk {
+            packet.encode_varint(self.biomes.len() as i32);
+            for biome in &self.biomes {
+                packet.encode_varint(*biome);
+            }
+        }
+        let mut sections = Packet::with_version(protocol_version);
+        for section in &self.sections {
+            section.encode(&mut sections);
+        }
+        packet.encode_varint(sections.len() as i32);
+        packet.push_vec(sections.get_vec());
+        packet.encode_array(&self.block_entities);
+        packet
     }
 
     fn get_printable(&self) -> String {
         format!(
-            "{} {} {} {} {:?} {}",
-            self.x, self.y, self.z, self.block, self.status, self.successful,
+            "{} {} {} {:b} {} sections, {} block entities",
+            self.chunk_x,
+            self.chunk_z,
+            self.full_chunk,
+            self.primary_bit_mask,
+            self.sections.len(),
+            self.block_entities.len()
         )
     }
+
+    fn status_updating(&self) -> bool {
+        true
+    }
+
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
+        let chunk = (self.chunk_x, self.chunk_z);
+        status.world.load_chunk(
+            chunk,
+            self.full_chunk,
+            self.primary_bit_mask,
+            &self.sections,
+        );
+        if self.full_chunk {
+            status.world.biomes.insert(chunk, self.biomes.clone());
+        }
+        // The block entities have their position in them.
+        for block_entity in &self.block_entities {
+            let tag = match &block_entity.tag {
+                Some(tag) => tag,
+                None => continue,
+            };
+            let coordinate = |name| tag.get(name).and_then(nbt::Tag::as_i64);
+            if let (Some(x), Some(y), Some(z)) = (coordinate("x"), coordinate("y"), coordinate("z"))
+            {
+                status.world.set_block_entity((x, y, z), Some(tag.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+//0x21
+#[derive(Clone, Parsable, Serialize)]
+pub struct Effect {
+    pub effect_id: i32,
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    pub data: i32,
+    pub disable_relative_volume: bool,
+}
+
+//0x22
+#[derive(Clone, Serialize)]
+pub struct Particle {
+    // An int here, unlike everywhere else particles are sent.
+    pub particle_id: i32,
+    pub long_distance: bool,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub offset_z: f32,
+    pub particle_data: f32,
+    pub particle_count: i32,
+    // What's in it depends on the particle, it comes after everything else.
+    pub data: ParticleData,
+}
+
+impl Parsable for Particle {
+    fn empty() -> Self {
+        Self {
+            particle_id: 0,
+            long_distance: false,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            offset_z: 0.0,
+            particle_data: 0.0,
+            particle_count: 0,
+            data: ParticleData::None,
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.particle_id = packet.decode_int()?;
+        self.long_distance = packet.decode_bool()?;
+        self.x = packet.decode_double()?;
+        self.y = packet.decode_double()?;
+        self.z = packet.decode_double()?;
+        self.offset_x = packet.decode_float()?;
+        self.offset_y = packet.decode_float()?;
+        self.offset_z = packet.decode_float()?;
+        self.particle_data = packet.decode_float()?;
+        self.particle_count = packet.decode_int()?;
+        self.data = ParticleData::decode(&mut packet, self.particle_id)?;
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_int(self.particle_id);
+        packet.encode_bool(self.long_distance);
+        packet.encode_double(self.x);
+        packet.encode_double(self.y);
+        packet.encode_double(self.z);
+        packet.encode_float(self.offset_x);
+        packet.encode_float(self.offset_y);
+        packet.encode_float(self.offset_z);
+        packet.encode_float(self.particle_data);
+        packet.encode_int(self.particle_count);
+        self.data.encode(&mut packet);
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        format!(
+            "{} {} {} {} {} {} {} {} {} {} {}",
+            self.particle_id,
+            self.long_distance,
+            self.x,
+            self.y,
+            self.z,
+            self.offset_x,
+            self.offset_y,
+            self.offset_z,
+            self.particle_data,
+            self.particle_count,
+            self.data.printable()
+        )
+    }
+}
+
+//0x23
+#[derive(Clone, Serialize)]
+pub struct UpdateLight {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub trust_edges: bool,
+    pub sky_light_mask: i32,
+    pub block_light_mask: i32,
+    pub empty_sky_light_mask: i32,
+    pub empty_block_light_mask: i32,
+    // One array for every bit set in the masks, from the lowest bit up.
+    #[serde(serialize_with = "json::byte_arrays")]
+    pub sky_light: Vec<Vec<u8>>,
+    #[serde(serialize_with = "json::byte_arrays")]
+    pub block_light: Vec<Vec<u8>>,
+}
+
+impl UpdateLight {
+    fn decode_arrays(packet: &mut Packet, mask: i32) -> Result<Vec<Vec<u8>>, Error> {
+        let mut arrays = Vec::new();
+        for _ in 0..mask.count_ones() {
+            let length = packet.decode_varint()?;
+            arrays.push(packet.read_array(length)?);
+        }
+        Ok(arrays)
+    }
+
+    fn encode_arrays(packet: &mut Packet, arrays: &[Vec<u8>]) {
+        for array in arrays {
+            packet.encode_varint(array.len() as i32);
+            packet.push_vec(array.clone());
+        }
+    }
+}
+
+impl Parsable for UpdateLight {
+    fn empty() -> Self {
+        Self {
+            chunk_x: 0,
+            chunk_z: 0,
+            trust_edges: false,
+            sky_light_mask: 0,
+            block_light_mask: 0,
+            empty_sky_light_mask: 0,
+            empty_block_light_mask: 0,
+            sky_light: Vec::new(),
+            block_light: Vec::new(),
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.chunk_x = packet.decode_varint()?;
+        self.chunk_z = packet.decode_varint()?;
+        self.trust_edges = packet.decode_bool()?;
+        self.sky_light_mask = packet.decode_varint()?;
+        self.block_light_mask = packet.decode_varint()?;
+        self.empty_sky_light_mask = packet.decode_varint()?;
+        self.empty_block_light_mask = packet.decode_varint()?;
+        self.sky_light = Self::decode_arrays(&mut packet, self.sky_light_mask)?;
+        self.block_light = Self::decode_arrays(&mut packet, self.block_light_mask)?;
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.chunk_x);
+        packet.encode_varint(self.chunk_z);
+        packet.encode_bool(self.trust_edges);
+        packet.encode_varint(self.sky_light_mask);
+        packet.encode_varint(self.block_light_mask);
+        packet.encode_varint(self.empty_sky_light_mask);
+        packet.encode_varint(self.empty_block_light_mask);
+        Self::encode_arrays(&mut packet, &self.sky_light);
+        Self::encode_arrays(&mut packet, &self.block_light);
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        format!(
+            "{} {} {} {:b} {:b} {:b} {:b}",
+            self.chunk_x,
+            self.chunk_z,
+            self.trust_edges,
+            self.sky_light_mask,
+            self.block_light_mask,
+            self.empty_sky_light_mask,
+            self.empty_block_light_mask
+        )
+    }
+
+    fn status_updating(&self) -> bool {
+        true
+    }
+
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
+        status.world.update_light(
+            (self.chunk_x, self.chunk_z),
+            (self.sky_light_mask, self.block_light_mask),
+            (self.empty_sky_light_mask, self.empty_block_light_mask),
+            (&self.sky_light, &self.block_light),
+        );
+        Ok(())
+    }
+}
+
+//0x24
+#[derive(Clone, Parsable, Serialize)]
+#[parsable(update_status = Self::change_world, get_printable = Self::printable)]
+pub struct JoinGame {
+    pub entity_id: i32,
+    pub is_hardcore: bool,
+    pub gamemode: u8,
+    pub previous_gamemode: i8,
+    #[length_prefixed]
+    #[identifier]
+    pub world_names: Vec<String>,
+    pub dimension_codec: Nbt,
+    pub dimension: Nbt,
+    #[identifier]
+    pub world_name: String,
+    pub hashed_seed: i64,
+    #[varint]
+    pub max_players: i32,
+    #[varint]
+    pub view_distance: i32,
+    pub reduced_debug_info: bool,
+    pub enable_respawn_screen: bool,
+    pub is_debug: bool,
+    pub is_flat: bool,
+}
+
+impl JoinGame {
+    // The dimension codec is huge, it's left out.
+    fn printable(&self) -> String {
+        format!(
+            "{} {} {} {} [{}] {} {} {} {} {} {} {} {} {}",
+            self.entity_id,
+            self.is_hardcore,
+            self.gamemode,
+            self.previous_gamemode,
+            self.world_names.join(", "),
+            self.dimension,
+            self.world_name,
+            self.hashed_seed,
+            self.max_players,
+            self.view_distance,
+            self.reduced_debug_info,
+            self.enable_respawn_screen,
+            self.is_debug,
+            self.is_flat
+        )
+    }
+
+    // Proxies like Velocity send another one when the player switches servers.
+    fn change_world(&self, status: &mut Status) -> Result<(), Error> {
+        change_world(status, &self.world_name);
+        Ok(())
+    }
+}
+
+// Saves what's loaded of the world the player leaves and starts over with the new one.
+fn change_world(status: &mut Status, world_name: &str) {
+    let world = status.world.take();
+    if let Some(download) = &status.download {
+        download.save_world(world, status.protocol_version);
+    }
+    status.world.dimension = world_name.to_string();
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct MapIcon {
+    #[varint]
+    pub r#type: i32,
+    pub x: i8,
+    pub z: i8,
+    pub direction: i8,
+    #[chat]
+    #[serde(serialize_with = "json::optional_chat")]
+    pub display_name: Option<String>,
+}
+
+// The part of the map that changed, there's none if no columns were updated.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct MapUpdate {
+    pub columns: u8,
+    pub rows: u8,
+    pub x: i8,
+    pub z: i8,
+    #[serde(serialize_with = "json::bytes")]
+    pub data: Vec<u8>,
+}
+
+impl Field for MapUpdate {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        let columns = packet.decode_ubyte()?;
+        if columns == 0 {
+            return Ok(Self::default());
+        }
+        let rows = packet.decode_ubyte()?;
+        let x = packet.decode_byte()?;
+        let z = packet.decode_byte()?;
+        let length = packet.decode_varint()?;
+        Ok(Self {
+            columns,
+            rows,
+            x,
+            z,
+            data: packet.read_array(length)?,
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        packet.encode_ubyte(self.columns);
+        if self.columns == 0 {
+            return;
+        }
+        packet.encode_ubyte(self.rows);
+        packet.encode_byte(self.x);
+        packet.encode_byte(self.z);
+        packet.encode_varint(self.data.len() as i32);
+        packet.push_vec(self.data.clone());
+    }
+
+    fn printable(&self) -> String {
+        match self.columns {
+            0 => "no update".into(),
+            _ => format!("{}x{} at {} {}", self.columns, self.rows, self.x, self.z),
+        }
+    }
+}
+
+//0x25
+#[derive(Clone, Parsable, Serialize)]
+pub struct MapData {
+    #[varint]
+    pub map_id: i32,
+    pub scale: i8,
+    pub tracking_position: bool,
+    pub locked: bool,
+    #[length_prefixed]
+    pub icons: Vec<MapIcon>,
+    pub update: MapUpdate,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct Trade {
+    pub input_item_1: Slot,
+    pub output_item: Slot,
+    pub input_item_2: Option<Slot>,
+    pub trade_disabled: bool,
+    pub uses: i32,
+    pub max_uses: i32,
+    pub xp: i32,
+    pub special_price: i32,
+    pub price_multiplier: f32,
+    pub demand: i32,
+}
+
+//0x26
+#[derive(Clone, Serialize)]
+pub struct TradeList {
+    pub window_id: i32,
+    pub trades: Vec<Trade>,
+    pub villager_level: i32,
+    pub experience: i32,
+    pub is_regular_villager: bool,
+    pub can_restock: bool,
+}
+
+impl Parsable for TradeList {
+    fn empty() -> Self {
+        Self {
+            window_id: 0,
+            trades: Vec::new(),
+            villager_level: 0,
+            experience: 0,
+            is_regular_villager: false,
+            can_restock: false,
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.window_id = packet.decode_varint()?;
+        // The number of trades is a single unsigned byte.
+        let count = packet.decode_ubyte()?;
+        self.trades = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            self.trades.push(Trade::decode(&mut packet)?);
+        }
+        self.villager_level = packet.decode_varint()?;
+        self.experience = packet.decode_varint()?;
+        self.is_regular_villager = packet.decode_bool()?;
+        self.can_restock = packet.decode_bool()?;
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.window_id);
+        packet.encode_ubyte(self.trades.len() as u8);
+        for trade in &self.trades {
+            trade.encode(&mut packet);
+        }
+        packet.encode_varint(self.villager_level);
+        packet.encode_varint(self.experience);
+        packet.encode_bool(self.is_regular_villager);
+        packet.encode_bool(self.can_restock);
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        let trades = self
+            .trades
+            .iter()
+            .map(|trade| match &trade.input_item_2 {
+                Some(second) if second.present => format!(
+                    "{} + {} -> {}",
+                    trade.input_item_1.printable(),
+                    second.printable(),
+                    trade.output_item.printable()
+                ),
+                _ => format!(
+                    "{} -> {}",
+                    trade.input_item_1.printable(),
+                    trade.output_item.printable()
+                ),
+            })
+            .collect::<Vec<String>>();
+        format!(
+            "{} [{}] {} {} {} {}",
+            self.window_id,
+            trades.join(", "),
+            self.villager_level,
+            self.experience,
+            self.is_regular_villager,
+            self.can_restock
+        )
+    }
+}
+
+//0x27
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityPosition {
+    #[varint]
+    pub entity_id: i32,
+    pub delta_x: i16,
+    pub delta_y: i16,
+    pub delta_z: i16,
+    pub on_ground: bool,
+}
+
+//0x28
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityPositionAndRotation {
+    #[varint]
+    pub entity_id: i32,
+    pub delta_x: i16,
+    pub delta_y: i16,
+    pub delta_z: i16,
+    #[angle]
+    pub yaw: u8,
+    #[angle]
+    pub pitch: u8,
+    pub on_ground: bool,
+}
+
+//0x29
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityRotation {
+    #[varint]
+    pub entity_id: i32,
+    #[angle]
+    pub yaw: u8,
+    #[angle]
+    pub pitch: u8,
+    pub on_ground: bool,
+}
+
+//0x2a
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityMovement {
+    #[varint]
+    pub entity_id: i32,
+}
+
+//0x2b
+#[derive(Clone, Parsable, Serialize)]
+pub struct VehicleMoveClientbound {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+//0x2c
+#[derive(Clone, Parsable, Serialize)]
+pub struct OpenBook {
+    #[varint]
+    pub hand: i32,
+}
+
+//0x2d
+#[derive(Clone, Parsable, Serialize)]
+pub struct OpenWindow {
+    #[varint]
+    pub window_id: i32,
+    #[varint]
+    pub window_type: i32,
+    #[chat]
+    #[serde(serialize_with = "json::chat")]
+    pub window_title: String,
+}
+
+//0x2e
+#[derive(Clone, Parsable, Serialize)]
+pub struct OpenSignEditor {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+}
+
+//0x2f
+#[derive(Clone, Parsable, Serialize)]
+pub struct CraftRecipeResponse {
+    pub window_id: i8,
+    #[identifier]
+    pub recipe: String,
+}
+
+//0x30
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerAbilitiesClientbound {
+    pub flags: i8,
+    pub flying_speed: f32,
+    pub field_of_view_modifier: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum CombatEventKind {
+    #[default]
+    EnterCombat,
+    EndCombat {
+        duration: i32,
+        entity_id: i32,
+    },
+    EntityDead {
+        player_id: i32,
+        entity_id: i32,
+        #[serde(serialize_with = "json::chat")]
+        message: String,
+    },
+}
+
+impl Field for CombatEventKind {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_varint()? {
+            0 => Self::EnterCombat,
+            1 => Self::EndCombat {
+                duration: packet.decode_varint()?,
+                entity_id: packet.decode_int()?,
+            },
+            2 => Self::EntityDead {
+                player_id: packet.decode_varint()?,
+                entity_id: packet.decode_int()?,
+                message: packet.decode_chat()?,
+            },
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "CombatEvent",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::EnterCombat => packet.encode_varint(0),
+            Self::EndCombat {
+                duration,
+                entity_id,
+            } => {
+                packet.encode_varint(1);
+                packet.encode_varint(*duration);
+                packet.encode_int(*entity_id);
+            }
+            Self::EntityDead {
+                player_id,
+                entity_id,
+                message,
+            } => {
+                packet.encode_varint(2);
+                packet.encode_varint(*player_id);
+                packet.encode_int(*entity_id);
+                packet.encode_chat(message);
+            }
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::EnterCombat => "EnterCombat".into(),
+            Self::EndCombat {
+                duration,
+                entity_id,
+            } => format!("EndCombat {} {}", duration, entity_id),
+            Self::EntityDead {
+                player_id,
+                entity_id,
+                message,
+            } => format!("EntityDead {} {} {}", player_id, entity_id, message),
+        }
+    }
+}
+
+//0x31
+#[derive(Clone, Parsable, Serialize)]
+pub struct CombatEvent {
+    pub event: CombatEventKind,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct PlayerProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct PlayerInfoAdd {
+    #[serde(serialize_with = "json::uuid")]
+    pub uuid: u128,
+    pub name: String,
+    #[length_prefixed]
+    pub properties: Vec<PlayerProperty>,
+    #[varint]
+    pub gamemode: i32,
+    #[varint]
+    pub ping: i32,
+    #[chat]
+    #[serde(serialize_with = "json::optional_chat")]
+    pub display_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct PlayerInfoGamemode {
+    #[serde(serialize_with = "json::uuid")]
+    pub uuid: u128,
+    #[varint]
+    pub gamemode: i32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct PlayerInfoLatency {
+    #[serde(serialize_with = "json::uuid")]
+    pub uuid: u128,
+    #[varint]
+    pub ping: i32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct PlayerInfoDisplayName {
+    #[serde(serialize_with = "json::uuid")]
+    pub uuid: u128,
+    #[chat]
+    #[serde(serialize_with = "json::optional_chat")]
+    pub display_name: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum PlayerInfoAction {
+    AddPlayer(Vec<PlayerInfoAdd>),
+    UpdateGamemode(Vec<PlayerInfoGamemode>),
+    UpdateLatency(Vec<PlayerInfoLatency>),
+    UpdateDisplayName(Vec<PlayerInfoDisplayName>),
+    RemovePlayer(#[serde(serialize_with = "json::uuids")] Vec<u128>),
+}
+
+impl Default for PlayerInfoAction {
+    fn default() -> Self {
+        Self::RemovePlayer(Vec::new())
+    }
+}
+
+fn printable_list<T: Field>(values: &[T]) -> String {
+    format!(
+        "[{}]",
+        values
+            .iter()
+            .map(|value| value.printable())
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+impl Field for PlayerInfoAction {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_varint()? {
+            0 => Self::AddPlayer(packet.decode_array()?),
+            1 => Self::UpdateGamemode(packet.decode_array()?),
+            2 => Self::UpdateLatency(packet.decode_array()?),
+            3 => Self::UpdateDisplayName(packet.decode_array()?),
+            4 => {
+                let count = packet.decode_count()?;
+                let mut uuids = Vec::with_capacity(count);
+                for _ in 0..count {
+                    uuids.push(packet.decode_uuid()?);
+                }
+                Self::RemovePlayer(uuids)
+            }
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "PlayerInfoAction",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::AddPlayer(players) => {
+                packet.encode_varint(0);
+                packet.encode_array(players);
+            }
+            Self::UpdateGamemode(players) => {
+                packet.encode_varint(1);
+                packet.encode_array(players);
+            }
+            Self::UpdateLatency(players) => {
+                packet.encode_varint(2);
+                packet.encode_array(players);
+            }
+            Self::UpdateDisplayName(players) => {
+                packet.encode_varint(3);
+                packet.encode_array(players);
+            }
+            Self::RemovePlayer(uuids) => {
+                packet.encode_varint(4);
+                packet.encode_varint(uuids.len() as i32);
+                for uuid in uuids {
+                    packet.encode_uuid(*uuid);
+                }
+            }
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::AddPlayer(players) => format!("AddPlayer {}", printable_list(players)),
+            Self::UpdateGamemode(players) => {
+                format!("UpdateGamemode {}", printable_list(players))
+            }
+            Self::UpdateLatency(players) => format!("UpdateLatency {}", printable_list(players)),
+            Self::UpdateDisplayName(players) => {
+                format!("UpdateDisplayName {}", printable_list(players))
+            }
+            Self::RemovePlayer(uuids) => format!(
+                "RemovePlayer [{}]",
+                uuids
+                    .iter()
+                    .map(|uuid| format!("{:x}", uuid))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+//0x32
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerInfo {
+    pub action: PlayerInfoAction,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct FaceEntity {
+    #[varint]
+    pub entity_id: i32,
+    #[varint]
+    pub entity_feet_eyes: i32,
+}
+
+//0x33
+#[derive(Clone, Parsable, Serialize)]
+pub struct FacePlayer {
+    #[varint]
+    pub feet_eyes: i32,
+    pub target_x: f64,
+    pub target_y: f64,
+    pub target_z: f64,
+    pub entity: Option<FaceEntity>,
+}
+
+//0x34
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerPositionAndLook {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    // Which of the values are relative instead of absolute.
+    pub flags: i8,
+    #[varint]
+    pub teleport_id: i32,
+}
+
+//0x35
+#[derive(Clone, Serialize)]
+pub struct UnlockRecipes {
+    pub action: i32,
+    pub crafting_book_open: bool,
+    pub crafting_book_filter_active: bool,
+    pub smelting_book_open: bool,
+    pub smelting_book_filter_active: bool,
+    pub blast_furnace_book_open: bool,
+    pub blast_furnace_book_filter_active: bool,
+    pub smoker_book_open: bool,
+    pub smoker_book_filter_active: bool,
+    pub recipe_ids: Vec<String>,
+    // Only sent when the action is init (0).
+    pub init_recipe_ids: Vec<String>,
+}
+
+impl UnlockRecipes {
+    fn decode_ids(packet: &mut Packet) -> Result<Vec<String>, Error> {
+        let count = packet.decode_count()?;
+        let mut ids = Vec::with_capacity(count);
+        for _ in 0..count {
+            ids.push(packet.decode_identifier()?);
+        }
+        Ok(ids)
+    }
+
+    fn encode_ids(packet: &mut Packet, ids: &[String]) {
+        packet.encode_varint(ids.len() as i32);
+        for id in ids {
+            packet.encode_identifier(id);
+        }
+    }
+}
+
+impl Parsable for UnlockRecipes {
+    fn empty() -> Self {
+        Self {
+            action: 0,
+            crafting_book_open: false,
+            crafting_book_filter_active: false,
+            smelting_book_open: false,
+            smelting_book_filter_active: false,
+            blast_furnace_book_open: false,
+            blast_furnace_book_filter_active: false,
+            smoker_book_open: false,
+            smoker_book_filter_active: false,
+            recipe_ids: Vec::new(),
+            init_recipe_ids: Vec::new(),
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.action = packet.decode_varint()?;
+        self.crafting_book_open = packet.decode_bool()?;
+        self.crafting_book_filter_active = packet.decode_bool()?;
+        self.smelting_book_open = packet.decode_bool()?;
+        self.smelting_book_filter_active = packet.decode_bool()?;
+        self.blast_furnace_book_open = packet.decode_bool()?;
+        self.blast_furnace_book_filter_active = packet.decode_bool()?;
+        self.smoker_book_open = packet.decode_bool()?;
+        self.smoker_book_filter_active = packet.decode_bool()?;
+        self.recipe_ids = Self::decode_ids(&mut packet)?;
+        self.init_recipe_ids = Vec::new();
+        if self.action == 0 {
+            self.init_recipe_ids = Self::decode_ids(&mut packet)?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.action);
+        packet.encode_bool(self.crafting_book_open);
+        packet.encode_bool(self.crafting_book_filter_active);
+        packet.encode_bool(self.smelting_book_open);
+        packet.encode_bool(self.smelting_book_filter_active);
+        packet.encode_bool(self.blast_furnace_book_open);
+        packet.encode_bool(self.blast_furnace_book_filter_active);
+        packet.encode_bool(self.smoker_book_open);
+        packet.encode_bool(self.smoker_book_filter_active);
+        Self::encode_ids(&mut packet, &self.recipe_ids);
+        if self.action == 0 {
+            Self::encode_ids(&mut packet, &self.init_recipe_ids);
+        }
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        let action = match self.action {
+            0 => "Init",
+            1 => "Add",
+            2 => "Remove",
+            _ => "Unknown",
+        };
+        match self.action {
+            0 => format!(
+                "{} {:?} {:?}",
+                action, self.recipe_ids, self.init_recipe_ids
+            ),
+            _ => format!("{} {:?}", action, self.recipe_ids),
+        }
+    }
+}
+
+//0x36
+#[derive(Clone, Parsable, Serialize)]
+pub struct DestroyEntities {
+    #[length_prefixed]
+    #[varint]
+    pub entity_ids: Vec<i32>,
+}
+
+//0x37
+#[derive(Clone, Parsable, Serialize)]
+pub struct RemoveEntityEffect {
+    #[varint]
+    pub entity_id: i32,
+    pub effect_id: i8,
+}
+
+//0x38
+#[derive(Clone, Parsable, Serialize)]
+pub struct ResourcePackSend {
+    pub url: String,
+    pub hash: String,
+}
+
+//0x39
+#[derive(Clone, Parsable, Serialize)]
+#[parsable(update_status = Self::change_world)]
+pub struct Respawn {
+    pub dimension: Nbt,
+    #[identifier]
+    pub world_name: String,
+    pub hashed_seed: i64,
+    pub gamemode: u8,
+    pub previous_gamemode: i8,
+    pub is_debug: bool,
+    pub is_flat: bool,
+    pub copy_metadata: bool,
+}
+
+impl Respawn {
+    // The server sends all the chunks around the player again after this, even in the same world.
+    fn change_world(&self, status: &mut Status) -> Result<(), Error> {
+        change_world(status, &self.world_name);
+        Ok(())
+    }
+}
+
+//0x3a
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityHeadLook {
+    #[varint]
+    pub entity_id: i32,
+    #[angle]
+    pub head_yaw: u8,
+}
+
+//0x3b
+#[derive(Clone, Parsable, Serialize)]
+#[parsable(update_status = Self::set_blocks, get_printable = Self::printable)]
+pub struct MultiBlockChange {
+    // The section's x (22 bits), z (22 bits) and y (20 bits) packed together.
+    pub chunk_section_position: i64,
+    pub trust_edges: bool,
+    // The block state ID, shifted 12 bits to the left, with the position in the section.
+    #[length_prefixed]
+    #[varlong]
+    pub blocks: Vec<i64>,
+}
+
+impl MultiBlockChange {
+    // The section's position, in sections.
+    pub fn section(&self) -> (i64, i64, i64) {
+        let position = self.chunk_section_position;
+        (position >> 42, position << 44 >> 44, position << 22 >> 42)
+    }
+
+    // The positions in the world and the block state IDs they're changed to.
+    pub fn changes(&self) -> Vec<((i64, i64, i64), i32)> {
+        let (section_x, section_y, section_z) = self.section();
+        self.blocks
+            .iter()
+            .map(|block| {
+                let position = (
+                    section_x * 16 + (block >> 8 & 15),
+                    section_y * 16 + (block & 15),
+                    section_z * 16 + (block >> 4 & 15),
+                );
+                (position, (block >> 12) as i32)
+            })
+            .collect()
+    }
+
+    fn printable(&self) -> String {
+        let (x, y, z) = self.section();
+        let changes = self
+            .changes()
+            .iter()
+            .map(|((x, y, z), state)| format!("{} {} {} {}", x, y, z, state))
+            .collect::<Vec<String>>();
+        format!(
+            "{} {} {} {} [{}]",
+            x,
+            y,
+            z,
+            self.trust_edges,
+            changes.join(", ")
+        )
+    }
+
+    fn set_blocks(&self, status: &mut Status) -> Result<(), Error> {
+        for (position, state) in self.changes() {
+            status.world.set_block(position, state);
+        }
+        Ok(())
+    }
+}
+
+//0x3c
+#[derive(Clone, Parsable, Serialize)]
+pub struct SelectAdvancementTab {
+    #[identifier]
+    pub identifier: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum WorldBorderAction {
+    SetSize {
+        diameter: f64,
+    },
+    LerpSize {
+        old_diameter: f64,
+        new_diameter: f64,
+        speed: i64,
+    },
+    SetCenter {
+        x: f64,
+        z: f64,
+    },
+    Initialize {
+        x: f64,
+        z: f64,
+        old_diameter: f64,
+        new_diameter: f64,
+        speed: i64,
+        portal_teleport_boundary: i32,
+        warning_time: i32,
+        warning_blocks: i32,
+    },
+    SetWarningTime {
+        warning_time: i32,
+    },
+    SetWarningBlocks {
+        warning_blocks: i32,
+    },
+}
+
+impl Default for WorldBorderAction {
+    fn default() -> Self {
+        Self::SetSize { diameter: 0f64 }
+    }
+}
+
+impl Field for WorldBorderAction {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_varint()? {
+            0 => Self::SetSize {
+                diameter: packet.decode_double()?,
+            },
+            1 => Self::LerpSize {
+                old_diameter: packet.decode_double()?,
+                new_diameter: packet.decode_double()?,
+                speed: packet.decode_varlong()?,
+            },
+            2 => Self::SetCenter {
+                x: packet.decode_double()?,
+                z: packet.decode_double()?,
+            },
+            3 => Self::Initialize {
+                x: packet.decode_double()?,
+                z: packet.decode_double()?,
+                old_diameter: packet.decode_double()?,
+                new_diameter: packet.decode_double()?,
+                speed: packet.decode_varlong()?,
+                portal_teleport_boundary: packet.decode_varint()?,
+                warning_time: packet.decode_varint()?,
+                warning_blocks: packet.decode_varint()?,
+            },
+            4 => Self::SetWarningTime {
+                warning_time: packet.decode_varint()?,
+            },
+            5 => Self::SetWarningBlocks {
+                warning_blocks: packet.decode_varint()?,
+            },
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "WorldBorderAction",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::SetSize { diameter } => {
+                packet.encode_varint(0);
+                packet.encode_double(*diameter);
+            }
+            Self::LerpSize {
+                old_diameter,
+                new_diameter,
+                speed,
+            } => {
+                packet.encode_varint(1);
+                packet.encode_double(*old_diameter);
+                packet.encode_double(*new_diameter);
+                packet.encode_varlong(*speed);
+            }
+            Self::SetCenter { x, z } => {
+                packet.encode_varint(2);
+                packet.encode_double(*x);
+                packet.encode_double(*z);
+            }
+            Self::Initialize {
+                x,
+                z,
+                old_diameter,
+                new_diameter,
+                speed,
+                portal_teleport_boundary,
+                warning_time,
+                warning_blocks,
+            } => {
+                packet.encode_varint(3);
+                packet.encode_double(*x);
+                packet.encode_double(*z);
+                packet.encode_double(*old_diameter);
+                packet.encode_double(*new_diameter);
+                packet.encode_varlong(*speed);
+                packet.encode_varint(*portal_teleport_boundary);
+                packet.encode_varint(*warning_time);
+                packet.encode_varint(*warning_blocks);
+            }
+            Self::SetWarningTime { warning_time } => {
+                packet.encode_varint(4);
+                packet.encode_varint(*warning_time);
+            }
+            Self::SetWarningBlocks { warning_blocks } => {
+                packet.encode_varint(5);
+                packet.encode_varint(*warning_blocks);
+            }
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::SetSize { diameter } => format!("SetSize {}", diameter),
+            Self::LerpSize {
+                old_diameter,
+                new_diameter,
+                speed,
+            } => format!("LerpSize {} {} {}", old_diameter, new_diameter, speed),
+            Self::SetCenter { x, z } => format!("SetCenter {} {}", x, z),
+            Self::Initialize {
+                x,
+                z,
+                old_diameter,
+                new_diameter,
+                speed,
+                portal_teleport_boundary,
+                warning_time,
+                warning_blocks,
+            } => format!(
+                "Initialize {} {} {} {} {} {} {} {}",
+                x,
+                z,
+                old_diameter,
+                new_diameter,
+                speed,
+                portal_teleport_boundary,
+                warning_time,
+                warning_blocks
+            ),
+            Self::SetWarningTime { warning_time } => format!("SetWarningTime {}", warning_time),
+            Self::SetWarningBlocks { warning_blocks } => {
+                format!("SetWarningBlocks {}", warning_blocks)
+            }
+        }
+    }
+}
+
+//0x3d
+#[derive(Clone, Parsable, Serialize)]
+pub struct WorldBorder {
+    pub action: WorldBorderAction,
+}
+
+//0x3e
+#[derive(Clone, Parsable, Serialize)]
+pub struct Camera {
+    #[varint]
+    pub camera_id: i32,
+}
+
+//0x3f
+#[derive(Clone, Parsable, Serialize)]
+pub struct HeldItemChangeClientbound {
+    pub slot: i8,
+}
+
+//0x40
+#[derive(Clone, Parsable, Serialize)]
+pub struct UpdateViewPosition {
+    #[varint]
+    pub chunk_x: i32,
+    #[varint]
+    pub chunk_z: i32,
+}
+
+//0x41
+#[derive(Clone, Parsable, Serialize)]
+pub struct UpdateViewDistance {
+    #[varint]
+    pub view_distance: i32,
+}
+
+//0x42
+#[derive(Clone, Parsable, Serialize)]
+#[parsable(update_status = Self::set_spawn)]
+pub struct SpawnPosition {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+}
+
+impl SpawnPosition {
+    fn set_spawn(&self, status: &mut Status) -> Result<(), Error> {
+        status.world.spawn = Some(self.location);
+        Ok(())
+    }
+}
+
+//0x43
+#[derive(Clone, Parsable, Serialize)]
+pub struct DisplayScoreboard {
+    pub position: i8,
+    pub score_name: String,
+}
+
+//0x44
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityMetadata {
+    #[varint]
+    pub entity_id: i32,
+    pub metadata: Metadata,
+}
+
+//0x45
+#[derive(Clone, Parsable, Serialize)]
+pub struct AttachEntity {
+    pub attached_entity_id: i32,
+    pub holding_entity_id: i32,
+}
+
+//0x46
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityVelocity {
+    #[varint]
+    pub entity_id: i32,
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Equipment {
+    // 0 is the main hand, 1 the off hand and 2 to 5 the armor, from the boots up.
+    pub position: u8,
+    pub item: Slot,
+}
+
+impl Equipment {
+    fn position_name(&self) -> &'static str {
+        match self.position {
+            0 => "MainHand",
+            1 => "OffHand",
+            2 => "Boots",
+            3 => "Leggings",
+            4 => "Chestplate",
+            5 => "Helmet",
+            _ => "Unknown",
+        }
+    }
+}
+
+//0x47
+#[derive(Clone, Serialize)]
+pub struct EntityEquipment {
+    pub entity_id: i32,
+    pub equipment: Vec<Equipment>,
+}
+
+impl Parsable for EntityEquipment {
+    fn empty() -> Self {
+        Self {
+            entity_id: 0,
+            equipment: Vec::new(),
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.entity_id = packet.decode_varint()?;
+        // There's no length, the top bit of the position is set when another one follows.
+        self.equipment = Vec::new();
+        loop {
+            let position = packet.decode_ubyte()?;
+            self.equipment.push(Equipment {
+                position: position & 0x7f,
+                item: packet.decode_slot()?,
+            });
+            if position & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.entity_id);
+        for (index, equipment) in self.equipment.iter().enumerate() {
+            let more = if index + 1 < self.equipment.len() {
+                0x80
+            } else {
+                0x00
+            };
+            packet.encode_ubyte(equipment.position | more);
+            packet.encode_slot(&equipment.item);
+        }
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        let equipment = self
+            .equipment
+            .iter()
+            .map(|equipment| {
+                format!(
+                    "{}: {}",
+                    equipment.position_name(),
+                    equipment.item.printable()
+                )
+            })
+            .collect::<Vec<String>>();
+        format!("{} [{}]", self.entity_id, equipment.join(", "))
+    }
+}
+
+//0x48
+#[derive(Clone, Parsable, Serialize)]
+pub struct SetExperience {
+    pub experience_bar: f32,
+    #[varint]
+    pub level: i32,
+    #[varint]
+    pub total_experience: i32,
+}
+
+//0x49
+#[derive(Clone, Parsable, Serialize)]
+pub struct UpdateHealth {
+    pub health: f32,
+    #[varint]
+    pub food: i32,
+    pub food_saturation: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum ObjectiveMode {
+    Create {
+        #[serde(serialize_with = "json::chat")]
+        value: String,
+        r#type: i32,
+    },
+    #[default]
+    Remove,
+    Update {
+        #[serde(serialize_with = "json::chat")]
+        value: String,
+        r#type: i32,
+    },
+}
+
+impl Field for ObjectiveMode {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_byte()? {
+            0 => Self::Create {
+                value: packet.decode_chat()?,
+                r#type: packet.decode_varint()?,
+            },
+            1 => Self::Remove,
+            2 => Self::Update {
+                value: packet.decode_chat()?,
+                r#type: packet.decode_varint()?,
+            },
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "ObjectiveMode",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::Create { value, r#type } | Self::Update { value, r#type } => {
+                packet.encode_byte(match self {
+                    Self::Create { .. } => 0,
+                    _ => 2,
+                });
+                packet.encode_chat(value);
+                packet.encode_varint(*r#type);
+            }
+            Self::Remove => packet.encode_byte(1),
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::Create { value, r#type } => format!("Create {} {}", value, r#type),
+            Self::Remove => "Remove".into(),
+            Self::Update { value, r#type } => format!("Update {} {}", value, r#type),
+        }
+    }
+}
+
+//0x4a
+#[derive(Clone, Parsable, Serialize)]
+pub struct ScoreboardObjective {
+    pub objective_name: String,
+    pub mode: ObjectiveMode,
+}
+
+//0x4b
+#[derive(Clone, Parsable, Serialize)]
+pub struct SetPassengers {
+    #[varint]
+    pub entity_id: i32,
+    #[length_prefixed]
+    #[varint]
+    pub passengers: Vec<i32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct TeamInfo {
+    #[chat]
+    #[serde(serialize_with = "json::chat")]
+    pub display_name: String,
+    pub friendly_flags: i8,
+    pub name_tag_visibility: String,
+    pub collision_rule: String,
+    #[varint]
+    pub color: i32,
+    #[chat]
+    #[serde(serialize_with = "json::chat")]
+    pub prefix: String,
+    #[chat]
+    #[serde(serialize_with = "json::chat")]
+    pub suffix: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum TeamsMode {
+    Create(TeamInfo, Vec<String>),
+    #[default]
+    Remove,
+    UpdateInfo(TeamInfo),
+    AddEntities(Vec<String>),
+    RemoveEntities(Vec<String>),
+}
+
+impl TeamsMode {
+    fn decode_entities(packet: &mut Packet) -> Result<Vec<String>, Error> {
+        let count = packet.decode_count()?;
+        let mut entities = Vec::with_capacity(count);
+        for _ in 0..count {
+            entities.push(packet.decode_string()?);
+        }
+        Ok(entities)
+    }
+
+    fn encode_entities(packet: &mut Packet, entities: &[String]) {
+        packet.encode_varint(entities.len() as i32);
+        for entity in entities {
+            packet.encode_string(entity);
+        }
+    }
+}
+
+impl Field for TeamsMode {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_byte()? {
+            0 => Self::Create(TeamInfo::decode(packet)?, Self::decode_entities(packet)?),
+            1 => Self::Remove,
+            2 => Self::UpdateInfo(TeamInfo::decode(packet)?),
+            3 => Self::AddEntities(Self::decode_entities(packet)?),
+            4 => Self::RemoveEntities(Self::decode_entities(packet)?),
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "TeamsMode",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::Create(info, entities) => {
+                packet.encode_byte(0);
+                info.encode(packet);
+                Self::encode_entities(packet, entities);
+            }
+            Self::Remove => packet.encode_byte(1),
+            Self::UpdateInfo(info) => {
+                packet.encode_byte(2);
+                info.encode(packet);
+            }
+            Self::AddEntities(entities) => {
+                packet.encode_byte(3);
+                Self::encode_entities(packet, entities);
+            }
+            Self::RemoveEntities(entities) => {
+                packet.encode_byte(4);
+                Self::encode_entities(packet, entities);
+            }
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::Create(info, entities) => {
+                format!("Create {} {:?}", info.printable(), entities)
+            }
+            Self::Remove => "Remove".into(),
+            Self::UpdateInfo(info) => format!("UpdateInfo {}", info.printable()),
+            Self::AddEntities(entities) => format!("AddEntities {:?}", entities),
+            Self::RemoveEntities(entities) => format!("RemoveEntities {:?}", entities),
+        }
+    }
+}
+
+//0x4c
+#[derive(Clone, Parsable, Serialize)]
+pub struct Teams {
+    pub team_name: String,
+    pub mode: TeamsMode,
+}
+
+//0x4d
+#[derive(Clone, Serialize)]
+pub struct UpdateScore {
+    pub entity_name: String,
+    // 0 to create or update, 1 to remove.
+    pub action: i8,
+    pub objective_name: String,
+    pub value: Option<i32>,
+}
+
+impl Parsable for UpdateScore {
+    fn empty() -> Self {
+        Self {
+            entity_name: "".into(),
+            action: 0,
+            objective_name: "".into(),
+            value: None,
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.entity_name = packet.decode_string()?;
+        self.action = packet.decode_byte()?;
+        self.objective_name = packet.decode_string()?;
+        self.value = match self.action {
+            1 => None,
+            _ => Some(packet.decode_varint()?),
+        };
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_string(&self.entity_name);
+        packet.encode_byte(self.action);
+        packet.encode_string(&self.objective_name);
+        if let Some(value) = self.value {
+            packet.encode_varint(value);
+        }
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        match self.value {
+            Some(value) => format!(
+                "{} {} {} {}",
+                self.entity_name, self.action, self.objective_name, value
+            ),
+            None => format!(
+                "{} {} {}",
+                self.entity_name, self.action, self.objective_name
+            ),
+        }
+    }
+}
+
+//0x4e
+#[derive(Clone, Parsable, Serialize)]
+pub struct TimeUpdate {
+    pub world_age: i64,
+    pub time_of_day: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum TitleAction {
+    SetTitle(#[serde(serialize_with = "json::chat")] String),
+    SetSubtitle(#[serde(serialize_with = "json::chat")] String),
+    SetActionBar(#[serde(serialize_with = "json::chat")] String),
+    SetTimes {
+        fade_in: i32,
+        stay: i32,
+        fade_out: i32,
+    },
+    #[default]
+    Hide,
+    Reset,
+}
+
+impl Field for TitleAction {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_varint()? {
+            0 => Self::SetTitle(packet.decode_chat()?),
+            1 => Self::SetSubtitle(packet.decode_chat()?),
+            2 => Self::SetActionBar(packet.decode_chat()?),
+            3 => Self::SetTimes {
+                fade_in: packet.decode_int()?,
+                stay: packet.decode_int()?,
+                fade_out: packet.decode_int()?,
+            },
+            4 => Self::Hide,
+            5 => Self::Reset,
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "TitleAction",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::SetTitle(text) => {
+                packet.encode_varint(0);
+                packet.encode_chat(text);
+            }
+            Self::SetSubtitle(text) => {
+                packet.encode_varint(1);
+                packet.encode_chat(text);
+            }
+            Self::SetActionBar(text) => {
+                packet.encode_varint(2);
+                packet.encode_chat(text);
+            }
+            Self::SetTimes {
+                fade_in,
+                stay,
+                fade_out,
+            } => {
+                packet.encode_varint(3);
+                packet.encode_int(*fade_in);
+                packet.encode_int(*stay);
+                packet.encode_int(*fade_out);
+            }
+            Self::Hide => packet.encode_varint(4),
+            Self::Reset => packet.encode_varint(5),
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::SetTitle(text) => format!("SetTitle {}", text),
+            Self::SetSubtitle(text) => format!("SetSubtitle {}", text),
+            Self::SetActionBar(text) => format!("SetActionBar {}", text),
+            Self::SetTimes {
+                fade_in,
+                stay,
+                fade_out,
+            } => format!("SetTimes {} {} {}", fade_in, stay, fade_out),
+            Self::Hide => "Hide".into(),
+            Self::Reset => "Reset".into(),
+        }
+    }
+}
+
+//0x4f
+#[derive(Clone, Parsable, Serialize)]
+pub struct Title {
+    pub action: TitleAction,
+}
+
+//0x50
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntitySoundEffect {
+    #[varint]
+    pub sound_id: i32,
+    #[varint]
+    pub sound_category: i32,
+    #[varint]
+    pub entity_id: i32,
+    pub volume: f32,
+    pub pitch: f32,
+}
+
+//0x51
+#[derive(Clone, Parsable, Serialize)]
+pub struct SoundEffect {
+    #[varint]
+    pub sound_id: i32,
+    #[varint]
+    pub sound_category: i32,
+    // These are fixed point numbers, multiplied by 8.
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub volume: f32,
+    pub pitch: f32,
+}
+
+//0x52
+#[derive(Clone, Serialize)]
+pub struct StopSound {
+    pub source: Option<i32>,
+    pub sound: Option<String>,
+}
+
+impl Parsable for StopSound {
+    fn empty() -> Self {
+        Self {
+            source: None,
+            sound: None,
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        // The flags say which of the two are there.
+        let flags = packet.decode_byte()?;
+        self.source = match flags & 0x01 {
+            0 => None,
+            _ => Some(packet.decode_varint()?),
+        };
+        self.sound = match flags & 0x02 {
+            0 => None,
+            _ => Some(packet.decode_identifier()?),
+        };
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_byte(self.source.is_some() as i8 | (self.sound.is_some() as i8) << 1);
+        if let Some(source) = self.source {
+            packet.encode_varint(source);
+        }
+        if let Some(sound) = &self.sound {
+            packet.encode_identifier(sound);
+        }
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        format!(
+            "{} {}",
+            self.source
+                .map_or("None".into(), |source| source.to_string()),
+            self.sound.as_deref().unwrap_or("None")
+        )
+    }
+}
+
+//0x53
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerListHeaderAndFooter {
+    #[chat]
+    #[serde(serialize_with = "json::chat")]
+    pub header: String,
+    #[chat]
+    #[serde(serialize_with = "json::chat")]
+    pub footer: String,
+}
+
+//0x54
+#[derive(Clone, Parsable, Serialize)]
+pub struct NbtQueryResponse {
+    #[varint]
+    pub transaction_id: i32,
+    pub nbt: Nbt,
+}
+
+//0x55
+#[derive(Clone, Parsable, Serialize)]
+pub struct CollectItem {
+    #[varint]
+    pub collected_entity_id: i32,
+    #[varint]
+    pub collector_entity_id: i32,
+    #[varint]
+    pub pickup_item_count: i32,
+}
+
+//0x56
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityTeleport {
+    #[varint]
+    pub entity_id: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    #[angle]
+    pub yaw: u8,
+    #[angle]
+    pub pitch: u8,
+    pub on_ground: bool,
+}
+
+//0x57
+#[derive(Clone, Parsable, Serialize)]
+pub struct Advancements {
+    pub reset: bool,
+    // The advancements have slots in them (for the icons), so they're kept as they are for now.
+    #[serde(serialize_with = "json::bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct AttributeModifier {
+    #[serde(serialize_with = "json::uuid")]
+    pub uuid: u128,
+    pub amount: f64,
+    pub operation: i8,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct EntityProperty {
+    #[identifier]
+    pub key: String,
+    pub value: f64,
+    #[length_prefixed]
+    pub modifiers: Vec<AttributeModifier>,
+}
+
+//0x58
+#[derive(Clone, Serialize)]
+pub struct EntityProperties {
+    pub entity_id: i32,
+    pub properties: Vec<EntityProperty>,
+}
+
+impl Parsable for EntityProperties {
+    fn empty() -> Self {
+        Self {
+            entity_id: 0,
+            properties: Vec::new(),
+        }
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.entity_id = packet.decode_varint()?;
+        // This array has an Int as the length, the modifiers in it a VarInt.
+        let count = packet.decode_int()?;
+        let count = packet.check_count(count)?;
+        self.properties = Vec::with_capacity(count);
+        for _ in 0..count {
+            self.properties.push(EntityProperty::decode(&mut packet)?);
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.entity_id);
+        packet.encode_int(self.properties.len() as i32);
+        for property in &self.properties {
+            property.encode(&mut packet);
+        }
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        format!("{} {}", self.entity_id, printable_list(&self.properties))
+    }
+}
+
+//0x59
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityEffect {
+    #[varint]
+    pub entity_id: i32,
+    pub effect_id: i8,
+    pub amplifier: i8,
+    #[varint]
+    pub duration: i32,
+    pub flags: i8,
+}
+
+//0x5a
+#[derive(Clone, Parsable, Serialize)]
+pub struct DeclareRecipes {
+    // Recipes are made of slots, so they're kept as they are for now.
+    #[serde(serialize_with = "json::bytes")]
+    pub recipes: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Field, Serialize)]
+pub struct Tag {
+    #[identifier]
+    pub name: String,
+    #[length_prefixed]
+    #[varint]
+    pub entries: Vec<i32>,
+}
+
+//0x5b
+#[derive(Clone, Parsable, Serialize)]
+pub struct Tags {
+    #[length_prefixed]
+    pub block_tags: Vec<Tag>,
+    #[length_prefixed]
+    pub item_tags: Vec<Tag>,
+    #[length_prefixed]
+    pub fluid_tags: Vec<Tag>,
+    #[length_prefixed]
+    pub entity_tags: Vec<Tag>,
 }
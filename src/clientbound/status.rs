@@ -1,9 +1,14 @@
+use crate::error::Error;
+use crate::json;
 use crate::packet::{Packet, Parsable};
 use crate::{State, Status};
+use serde::Serialize;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct StatusResponse {
-    json_response: String,
+    // It's JSON like chat is, so it's written out the same way.
+    #[serde(serialize_with = "json::chat")]
+    pub json_response: String,
 }
 
 impl Parsable for StatusResponse {
@@ -13,19 +18,25 @@ impl Parsable for StatusResponse {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.json_response = packet.decode_string()?;
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_string(&self.json_response);
+        packet
     }
 
     fn get_printable(&self) -> String {
-        format!("{}", self.json_response)
+        self.json_response.to_string()
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct StatusPong {
-    payload: i64,
+    pub payload: i64,
 }
 
 impl Parsable for StatusPong {
@@ -33,9 +44,15 @@ impl Parsable for StatusPong {
         Self { payload: 0 }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.payload = packet.decode_long()?;
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_long(self.payload);
+        packet
     }
 
     fn get_printable(&self) -> String {
@@ -46,7 +63,7 @@ impl Parsable for StatusPong {
         true
     }
 
-    fn update_status(&self, status: &mut Status) -> Result<(), ()> {
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
         status.state = State::Handshaking;
         log::debug!("State updated to {}", status.state);
         Ok(())
@@ -0,0 +1,266 @@
+use crate::error::{Error, ErrorKind};
+use crate::packet::{self, Packet};
+use crate::types::{Direction, Status};
+use miniz_oxide::{deflate::compress_to_vec_zlib, inflate::decompress_to_vec_zlib_with_limit};
+
+// The same level vanilla uses.
+const COMPRESSION_LEVEL: u8 = 6;
+
+// Decoder holds the (already decrypted) bytes that came in, and splits them up into frames.
+pub struct Decoder {
+    data: Packet,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            data: Packet::new(),
+        }
+    }
+
+    pub fn push(&mut self, data: Vec<u8>) {
+        self.data.push_vec(data)
+    }
+
+    // This gives back the next whole frame (without its length), or None if not all of it is here yet.
+    // If this fails there is no way to find where the next frame starts, so the stream can't be read anymore.
+    pub fn next_frame(&mut self) -> Result<Option<Packet>, Error> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        // It takes a backup of the data before trying to read anything,
+        // because there is a decent chance that not all of it is here yet and it needs to be restored.
+        let o_data = self.data.get_vec();
+        let frame_length = match self.data.decode_varint() {
+            Ok(frame_length) => frame_length,
+            Err(err) if err.kind == ErrorKind::BadVarInt => return Err(err),
+            Err(_) => {
+                self.data.set(o_data);
+                return Ok(None);
+            }
+        };
+        if frame_length < 0 || frame_length as usize > packet::MAX_PACKET_LENGTH {
+            return Err(self.data.error(ErrorKind::InvalidLength {
+                length: frame_length as i64,
+                max: packet::MAX_PACKET_LENGTH,
+            }));
+        }
+        if self.data.len() < frame_length as usize {
+            self.data.set(o_data);
+            return Ok(None);
+        }
+        let frame = self.data.read(frame_length as usize)?;
+        // The data is reset so the offset doesn't keep on growing.
+        let rest = self.data.get_vec();
+        self.data.set(rest);
+        Ok(Some(Packet::from(frame)))
+    }
+}
+
+// Turns a frame into the packet ID and data. Without compression that's the frame itself,
+// with compression it starts with the length of the packet after decompressing, or 0 if it wasn't compressed.
+pub fn decompress(mut frame: Packet, compression: Option<u32>) -> Result<Packet, Error> {
+    if compression.is_none() {
+        return Ok(frame);
+    }
+    let data_length = frame.decode_varint()?;
+    if data_length < 0 || data_length as usize > packet::MAX_DATA_LENGTH {
+        return Err(frame.error(ErrorKind::InvalidLength {
+            length: data_length as i64,
+            max: packet::MAX_DATA_LENGTH,
+        }));
+    }
+    if data_length == 0 {
+        return Ok(Packet::from(frame.get_vec()));
+    }
+    // miniz only grows its buffer by doubling it, and gives up if that would go over the limit,
+    // so it gets twice the length. Anything longer than the packet claims is still caught below.
+    let decompressed =
+        decompress_to_vec_zlib_with_limit(frame.get_slice(), data_length as usize * 2)
+            .map_err(|why| frame.error(ErrorKind::Decompress(format!("{:?}", why))))?;
+    if decompressed.len() != data_length as usize {
+        return Err(frame.error(ErrorKind::Decompress(format!(
+            "expected {} bytes, got {}",
+            data_length,
+            decompressed.len()
+        ))));
+    }
+    Ok(Packet::from(decompressed))
+}
+
+// The other way around, packets under the threshold are sent as they are with a data length of 0.
+pub fn compress(packet: Packet, compression: Option<u32>) -> Packet {
+    let threshold = match compression {
+        Some(threshold) => threshold as usize,
+        None => return packet,
+    };
+    let mut frame = Packet::new();
+    if packet.len() >= threshold {
+        frame.encode_varint(packet.len() as i32);
+        frame.push_vec(compress_to_vec_zlib(packet.get_slice(), COMPRESSION_LEVEL));
+    } else {
+        frame.encode_varint(0);
+        frame.push_vec(packet.get_vec());
+    }
+    frame
+}
+
+// Puts the length in front of a frame.
+pub fn frame(packet: Packet) -> Vec<u8> {
+    let mut frame = Packet::new();
+    frame.encode_varint(packet.len() as i32);
+    frame.push_vec(packet.get_vec());
+    frame.get_vec()
+}
+
+// Makes a packet out of its ID and data.
+pub fn build(packet_id: i32, data: Vec<u8>) -> Packet {
+    let mut packet = Packet::new();
+    packet.encode_varint(packet_id);
+    packet.push_vec(data);
+    packet
+}
+
+// This turns a packet (ID and data) into the bytes that are sent in the direction,
+// compressing and encrypting it the way the connection currently is.
+pub fn encode(status: &mut Status, direction: &Direction, packet: Packet) -> Vec<u8> {
+    let compressed = compress(packet, status.compress);
+    encode_frame(status, direction, compressed)
+}
+
+// The same for a frame that's already compressed the way the connection is, like one that
+// couldn't be read and is sent on the way it came in.
+pub fn encode_frame(status: &mut Status, direction: &Direction, frame: Packet) -> Vec<u8> {
+    let frame = self::frame(frame);
+    match direction {
+        Direction::Serverbound => status.server_cipher.encrypt(frame),
+        Direction::Clientbound => status.client_cipher.encrypt(frame),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::Cipher;
+
+    #[test]
+    fn test_compression() {
+        let small = build(0x01, vec![0x07; 10]);
+        let big = build(0x02, (0..1000).map(|i| (i % 7) as u8).collect());
+        for compression in [None, Some(0), Some(64), Some(256)] {
+            for packet in [&small, &big] {
+                let compressed = compress(Packet::from(packet.get_vec()), compression);
+                match compression {
+                    None => assert_eq!(compressed.get_vec(), packet.get_vec()),
+                    Some(threshold) if packet.len() < threshold as usize => {
+                        assert_eq!(compressed.get_slice()[0], 0x00);
+                        assert_eq!(compressed.get_slice()[1..], packet.get_slice()[..]);
+                    }
+                    Some(_) => assert!(compressed.len() < packet.len() + 3),
+                }
+                let decompressed = decompress(compressed, compression).unwrap();
+                assert_eq!(decompressed.get_vec(), packet.get_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn test_decompress_errors() {
+        let mut frame = Packet::new();
+        frame.encode_varint(-1);
+        assert!(decompress(frame, Some(0)).is_err());
+
+        // It says it's longer than it is
+        let mut frame = compress(build(0x02, vec![0x01; 100]), Some(0));
+        let mut wrong = Packet::new();
+        frame.decode_varint().unwrap();
+        wrong.encode_varint(200);
+        wrong.push_vec(frame.get_vec());
+        assert!(decompress(wrong, Some(0)).is_err());
+
+        // And shorter than it is
+        let mut frame = compress(build(0x02, vec![0x01; 100]), Some(0));
+        let mut wrong = Packet::new();
+        frame.decode_varint().unwrap();
+        wrong.encode_varint(50);
+        wrong.push_vec(frame.get_vec());
+        assert!(decompress(wrong, Some(0)).is_err());
+
+        let mut frame = Packet::new();
+        frame.encode_varint(10);
+        frame.push_vec(vec![0xff; 10]);
+        assert!(decompress(frame, Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_decoder() {
+        let mut bytes = frame(build(0x00, vec![0x01, 0x02]));
+        bytes.append(&mut frame(build(0x01, vec![0x03; 300])));
+        let mut decoder = Decoder::new();
+        let mut frames = Vec::new();
+        // It gets the bytes one at a time, like it could over the network.
+        for byte in bytes {
+            decoder.push(vec![byte]);
+            while let Some(frame) = decoder.next_frame().unwrap() {
+                frames.push(frame.get_vec());
+            }
+        }
+        assert_eq!(
+            frames,
+            vec![
+                build(0x00, vec![0x01, 0x02]).get_vec(),
+                build(0x01, vec![0x03; 300]).get_vec()
+            ]
+        );
+
+        let mut decoder = Decoder::new();
+        decoder.push(vec![0xff, 0xff, 0xff, 0x7f]);
+        assert!(decoder.next_frame().is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        let key = [0x42; 16];
+        let mut status = Status::new();
+        status.compress = Some(16);
+        status.client_cipher.enable(&key);
+        let packets = vec![build(0x00, vec![0x01; 4]), build(0x20, vec![0x02; 400])];
+
+        let mut bytes = Vec::new();
+        for packet in &packets {
+            bytes.append(&mut encode(
+                &mut status,
+                &Direction::Clientbound,
+                Packet::from(packet.get_vec()),
+            ));
+        }
+        // The client has its own cipher with the same key, it has to be able to read everything.
+        let mut client = Cipher::new();
+        client.enable(&key);
+        let mut decoder = Decoder::new();
+        decoder.push(client.decrypt(bytes));
+        for packet in packets {
+            let frame = decoder.next_frame().unwrap().unwrap();
+            assert_eq!(
+                decompress(frame, Some(16)).unwrap().get_vec(),
+                packet.get_vec()
+            );
+        }
+
+        // A frame that's sent on as it is only gets the length and the encryption.
+        let broken = vec![0x05, 0xff, 0xff];
+        let bytes = encode_frame(
+            &mut status,
+            &Direction::Clientbound,
+            Packet::from(broken.clone()),
+        );
+        decoder.push(client.decrypt(bytes));
+        assert_eq!(decoder.next_frame().unwrap().unwrap().get_vec(), broken);
+    }
+}
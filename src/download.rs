@@ -0,0 +1,779 @@
+use crate::nbt::{Nbt, Tag};
+use crate::packet::{Field, Packet};
+use crate::types::Status;
+use crate::world::{self, ChunkSection, World, SECTION_BLOCKS};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Region files are split into sectors of 4 KiB, the first two hold where the chunks are and
+// when they were saved. A chunk can't take more than 255 sectors.
+const SECTOR: usize = 4096;
+const MAX_SECTORS: usize = 255;
+// The region file format, and the compression type for zlib.
+const ANVIL_VERSION: i32 = 19133;
+const ZLIB: u8 = 2;
+// Block entity data packets don't always have the id of the block entity, these are the actions.
+const BLOCK_ENTITY_ACTIONS: [(u8, &str); 13] = [
+    (1, "minecraft:mob_spawner"),
+    (2, "minecraft:command_block"),
+    (3, "minecraft:beacon"),
+    (4, "minecraft:skull"),
+    (5, "minecraft:conduit"),
+    (6, "minecraft:banner"),
+    (7, "minecraft:structure_block"),
+    (8, "minecraft:end_gateway"),
+    (9, "minecraft:sign"),
+    (11, "minecraft:bed"),
+    (12, "minecraft:jigsaw"),
+    (13, "minecraft:campfire"),
+    (14, "minecraft:beehive"),
+];
+
+// What the game's block report (`java -cp server.jar net.minecraft.data.Main --reports`) has
+// for every block, only the states are needed.
+#[derive(Deserialize)]
+struct ReportBlock {
+    states: Vec<ReportState>,
+}
+
+#[derive(Deserialize)]
+struct ReportState {
+    id: i32,
+    #[serde(default)]
+    properties: BTreeMap<String, String>,
+}
+
+// Block state IDs only mean something for one version, region files have the names and
+// properties of the blocks instead. This is what every ID is in a palette.
+pub struct BlockRegistry {
+    states: HashMap<i32, Tag>,
+}
+
+impl BlockRegistry {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|why| format!("Could not read {}: {}", path, why))?;
+        Self::from_json(&text).map_err(|why| format!("Could not load {}: {}", path, why))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let blocks: HashMap<String, ReportBlock> =
+            serde_json::from_str(text).map_err(|why| why.to_string())?;
+        let mut states = HashMap::new();
+        for (name, block) in blocks {
+            for state in block.states {
+                let mut entries = vec![("Name".to_string(), Tag::String(name.clone()))];
+                if !state.properties.is_empty() {
+                    let properties = state
+                        .properties
+                        .into_iter()
+                        .map(|(key, value)| (key, Tag::String(value)))
+                        .collect();
+                    entries.push(("Properties".into(), Tag::Compound(properties)));
+                }
+                states.insert(state.id, Tag::Compound(entries));
+            }
+        }
+        if !states.contains_key(&0) {
+            return Err("there's no block state 0, air".into());
+        }
+        Ok(Self { states })
+    }
+
+    pub fn get(&self, state: i32) -> Option<&Tag> {
+        self.states.get(&state)
+    }
+}
+
+// Everything needed to save a chunk, copied out of the world so it can be written without
+// holding up the connection.
+struct ChunkSnapshot {
+    chunk: (i32, i32),
+    dimension: String,
+    protocol_version: i32,
+    sections: Vec<Option<ChunkSection>>,
+    biomes: Option<Vec<i32>>,
+    block_entities: Vec<Tag>,
+}
+
+impl ChunkSnapshot {
+    fn new(status: &Status, chunk: (i32, i32)) -> Option<Self> {
+        let world = &status.world;
+        Some(Self {
+            chunk,
+            dimension: world.dimension.clone(),
+            protocol_version: status.protocol_version,
+            sections: world.chunks.get(&chunk)?.clone(),
+            biomes: world.biomes.get(&chunk).cloned(),
+            block_entities: world
+                .block_entities
+                .get(&chunk)
+                .map(|block_entities| block_entities.values().cloned().collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+enum Job {
+    Chunk(ChunkSnapshot),
+    // The level.dat, after the chunks of a world.
+    Level {
+        dimension: String,
+        protocol_version: i32,
+        spawn: Option<(i64, i64, i64)>,
+    },
+    Flush(Sender<()>),
+}
+
+// Writes the chunks the client gets to a world that the game can open. Every connection
+// shares one, the compressing and writing happens on a thread of its own, one chunk at a time.
+pub struct Downloader {
+    sender: Option<Sender<Job>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl Downloader {
+    pub fn new(directory: &str, blocks: BlockRegistry) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let writer = Writer {
+            directory: PathBuf::from(directory),
+            blocks,
+        };
+        let writer = thread::Builder::new()
+            .name("download".into())
+            .spawn(move || writer.run(receiver))
+            .expect("Could not start the download thread");
+        Self {
+            sender: Some(sender),
+            writer: Some(writer),
+        }
+    }
+
+    fn send(&self, job: Job) {
+        if let Some(sender) = &self.sender {
+            if sender.send(job).is_err() {
+                log::error!("The download thread stopped, the world isn't saved anymore");
+            }
+        }
+    }
+
+    // Saves a chunk before the world forgets about it. Going wrong is only logged, the proxy
+    // should keep working.
+    pub fn save_chunk(&self, status: &Status, chunk: (i32, i32)) {
+        if let Some(snapshot) = ChunkSnapshot::new(status, chunk) {
+            self.send(Job::Chunk(snapshot));
+        }
+    }
+
+    // Saves all the chunks of a world and the level.dat, if there are any chunks. It's given the
+    // world, so the chunks are moved to the writer instead of copied.
+    pub fn save_world(&self, mut world: World, protocol_version: i32) {
+        if world.chunks.is_empty() {
+            return;
+        }
+        for (chunk, sections) in world.chunks.drain() {
+            self.send(Job::Chunk(ChunkSnapshot {
+                chunk,
+                dimension: world.dimension.clone(),
+                protocol_version,
+                sections,
+                biomes: world.biomes.remove(&chunk),
+                block_entities: world
+                    .block_entities
+                    .remove(&chunk)
+                    .map(|block_entities| block_entities.into_values().collect())
+                    .unwrap_or_default(),
+            }));
+        }
+        self.send(Job::Level {
+            dimension: world.dimension,
+            protocol_version,
+            spawn: world.spawn,
+        });
+    }
+
+    // Waits until everything that was sent before is written.
+    pub fn flush(&self) {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Job::Flush(sender));
+        let _ = receiver.recv();
+    }
+}
+
+// What's still queued is written before the proxy stops.
+impl Drop for Downloader {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+struct Writer {
+    directory: PathBuf,
+    blocks: BlockRegistry,
+}
+
+impl Writer {
+    // The chunks saved since the last level.dat are counted for the log.
+    fn run(self, receiver: Receiver<Job>) {
+        let mut saved = 0;
+        for job in receiver {
+            match job {
+                Job::Chunk(snapshot) => match self.write_chunk(&snapshot) {
+                    Ok(()) => saved += 1,
+                    Err(why) => log::error!(
+                        "Could not save chunk {} {}: {}",
+                        snapshot.chunk.0,
+                        snapshot.chunk.1,
+                        why
+                    ),
+                },
+                Job::Level {
+                    dimension,
+                    protocol_version,
+                    spawn,
+                } => {
+                    if let Err(why) = self.write_level(&dimension, protocol_version, spawn) {
+                        log::error!("Could not save level.dat: {}", why);
+                    }
+                    log::info!(
+                        "Saved {} chunks of {} to {}",
+                        saved,
+                        dimension,
+                        self.directory.display()
+                    );
+                    saved = 0;
+                }
+                Job::Flush(done) => {
+                    let _ = done.send(());
+                }
+            }
+        }
+    }
+
+    // Where the region files of a dimension go, the way the game has it.
+    fn region_directory(&self, dimension: &str) -> PathBuf {
+        match dimension {
+            "minecraft:overworld" | "" => self.directory.join("region"),
+            "minecraft:the_nether" => self.directory.join("DIM-1").join("region"),
+            "minecraft:the_end" => self.directory.join("DIM1").join("region"),
+            _ => {
+                let (namespace, path) = dimension
+                    .split_once(':')
+                    .unwrap_or(("minecraft", dimension));
+                self.directory
+                    .join("dimensions")
+                    .join(namespace)
+                    .join(path)
+                    .join("region")
+            }
+        }
+    }
+
+    fn write_chunk(&self, snapshot: &ChunkSnapshot) -> Result<(), String> {
+        let tag = chunk_tag(snapshot, &self.blocks)?;
+        let directory = self.region_directory(&snapshot.dimension);
+        fs::create_dir_all(&directory).map_err(|why| why.to_string())?;
+        let (x, z) = snapshot.chunk;
+        let path = directory.join(format!("r.{}.{}.mca", x >> 5, z >> 5));
+        let mut packet = Packet::new();
+        Nbt {
+            name: String::new(),
+            tag: Some(tag),
+        }
+        .encode(&mut packet);
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(&packet.get_vec(), 6);
+        write_region_chunk(&path, snapshot.chunk, &data)
+            .map_err(|why| format!("{}: {}", path.display(), why))
+    }
+
+    // The spawn is only known in the overworld, other dimensions keep what's there.
+    fn write_level(
+        &self,
+        dimension: &str,
+        protocol_version: i32,
+        spawn: Option<(i64, i64, i64)>,
+    ) -> Result<(), String> {
+        let path = self.directory.join("level.dat");
+        let overworld = matches!(dimension, "minecraft:overworld" | "");
+        if path.exists() && !overworld {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.directory).map_err(|why| why.to_string())?;
+        let name = self
+            .directory
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Downloaded".into());
+        let mut packet = Packet::new();
+        Nbt {
+            name: String::new(),
+            tag: Some(level_tag(
+                &name,
+                spawn.unwrap_or((0, 64, 0)),
+                data_version(protocol_version),
+            )),
+        }
+        .encode(&mut packet);
+        fs::write(&path, gzip(&packet.get_vec()))
+            .map_err(|why| format!("{}: {}", path.display(), why))
+    }
+}
+
+// The data version chunks and the level.dat are saved with, the game upgrades older ones when
+// it loads them.
+fn data_version(protocol_version: i32) -> i32 {
+    match protocol_version {
+        ..=752 => 2578,
+        753 => 2580,
+        _ => 2584,
+    }
+}
+
+// The id of a block entity from a block entity data packet, if its NBT doesn't have it.
+pub fn block_entity_id(action: u8) -> Option<&'static str> {
+    BLOCK_ENTITY_ACTIONS
+        .iter()
+        .find(|(id, _)| *id == action)
+        .map(|(_, name)| *name)
+}
+
+// A chunk the way 1.16 saves it. The light isn't saved, the game works it out again.
+fn chunk_tag(snapshot: &ChunkSnapshot, blocks: &BlockRegistry) -> Result<Tag, String> {
+    let mut sections = Vec::new();
+    for (y, section) in snapshot.sections.iter().enumerate() {
+        let section = match section {
+            Some(section) => section,
+            None => continue,
+        };
+        // The palette has the blocks in the order they show up in, like the game does it.
+        let mut states = Vec::new();
+        let mut values = Vec::with_capacity(SECTION_BLOCKS);
+        for index in 0..SECTION_BLOCKS {
+            let state = section.get(index & 15, index >> 8, index >> 4 & 15);
+            let value = match states.iter().position(|other| *other == state) {
+                Some(value) => value,
+                None => {
+                    states.push(state);
+                    states.len() - 1
+                }
+            };
+            values.push(value as u64);
+        }
+        if states == [0] {
+            continue;
+        }
+        let mut palette = Vec::with_capacity(states.len());
+        for state in &states {
+            match blocks.get(*state) {
+                Some(block) => palette.push(block.clone()),
+                None => return Err(format!("block state {} isn't in the block report", state)),
+            }
+        }
+        sections.push(Tag::Compound(vec![
+            ("Y".into(), Tag::Byte(y as i8)),
+            ("Palette".into(), Tag::List(10, palette)),
+            (
+                "BlockStates".into(),
+                Tag::LongArray(pack(&values, states.len())),
+            ),
+        ]));
+    }
+
+    let (x, z) = snapshot.chunk;
+    let mut level = vec![
+        ("xPos".to_string(), Tag::Int(x)),
+        ("zPos".into(), Tag::Int(z)),
+        ("LastUpdate".into(), Tag::Long(0)),
+        ("InhabitedTime".into(), Tag::Long(0)),
+        ("Status".into(), Tag::String("full".into())),
+        ("isLightOn".into(), Tag::Byte(0)),
+        ("Sections".into(), Tag::List(10, sections)),
+    ];
+    if let Some(biomes) = &snapshot.biomes {
+        level.push(("Biomes".into(), Tag::IntArray(biomes.clone())));
+    }
+    level.push((
+        "TileEntities".into(),
+        Tag::List(10, snapshot.block_entities.clone()),
+    ));
+    level.push(("Entities".into(), Tag::List(0, Vec::new())));
+    level.push((
+        "Structures".into(),
+        Tag::Compound(vec![
+            ("References".into(), Tag::Compound(Vec::new())),
+            ("Starts".into(), Tag::Compound(Vec::new())),
+        ]),
+    ));
+    Ok(Tag::Compound(vec![
+        (
+            "DataVersion".into(),
+            Tag::Int(data_version(snapshot.protocol_version)),
+        ),
+        ("Level".into(), Tag::Compound(level)),
+    ]))
+}
+
+// Packs palette indexes into longs like the sections the client gets, region files always
+// have a palette though, with at least 4 bits.
+fn pack(values: &[u64], palette_length: usize) -> Vec<i64> {
+    let bits = (usize::BITS - (palette_length - 1).leading_zeros()).max(4);
+    world::pack(values, bits as u8)
+}
+
+// The least level.dat the game opens, in creative mode with cheats so the world can be looked around in.
+// Since 1.16.2 it has to say how every dimension is generated, these are the default ones.
+fn level_tag(name: &str, (x, y, z): (i64, i64, i64), data_version: i32) -> Tag {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as i64)
+        .unwrap_or(0);
+    let dimension = |name: &str, settings: &str, biome_source: Vec<(String, Tag)>| {
+        let mut biome_source = biome_source;
+        biome_source.push(("seed".into(), Tag::Long(0)));
+        (
+            format!("minecraft:{}", name),
+            Tag::Compound(vec![
+                ("type".into(), Tag::String(format!("minecraft:{}", name))),
+                (
+                    "generator".into(),
+                    Tag::Compound(vec![
+                        ("type".into(), Tag::String("minecraft:noise".into())),
+                        ("seed".into(), Tag::Long(0)),
+                        ("settings".into(), Tag::String(settings.into())),
+                        ("biome_source".into(), Tag::Compound(biome_source)),
+                    ]),
+                ),
+            ]),
+        )
+    };
+    let dimensions = vec![
+        dimension(
+            "overworld",
+            "minecraft:overworld",
+            vec![
+                (
+                    "type".into(),
+                    Tag::String("minecraft:vanilla_layered".into()),
+                ),
+                ("large_biomes".into(), Tag::Byte(0)),
+            ],
+        ),
+        dimension(
+            "the_nether",
+            "minecraft:nether",
+            vec![
+                ("type".into(), Tag::String("minecraft:multi_noise".into())),
+                ("preset".into(), Tag::String("minecraft:nether".into())),
+            ],
+        ),
+        dimension(
+            "the_end",
+            "minecraft:end",
+            vec![("type".into(), Tag::String("minecraft:the_end".into()))],
+        ),
+    ];
+    Tag::Compound(vec![(
+        "Data".into(),
+        Tag::Compound(vec![
+            ("version".into(), Tag::Int(ANVIL_VERSION)),
+            ("DataVersion".into(), Tag::Int(data_version)),
+            ("LevelName".into(), Tag::String(name.into())),
+            ("GameType".into(), Tag::Int(1)),
+            ("allowCommands".into(), Tag::Byte(1)),
+            ("hardcore".into(), Tag::Byte(0)),
+            ("initialized".into(), Tag::Byte(1)),
+            (
+                "WorldGenSettings".into(),
+                Tag::Compound(vec![
+                    ("seed".into(), Tag::Long(0)),
+                    ("generate_features".into(), Tag::Byte(0)),
+                    ("bonus_chest".into(), Tag::Byte(0)),
+                    ("dimensions".into(), Tag::Compound(dimensions)),
+                ]),
+            ),
+            ("SpawnX".into(), Tag::Int(x as i32)),
+            ("SpawnY".into(), Tag::Int(y as i32)),
+            ("SpawnZ".into(), Tag::Int(z as i32)),
+            ("Time".into(), Tag::Long(0)),
+            ("DayTime".into(), Tag::Long(6000)),
+            ("LastPlayed".into(), Tag::Long(now)),
+        ]),
+    )])
+}
+
+// Puts a compressed chunk in a region file. It goes where the chunk was if it still fits,
+// otherwise at the end of the file.
+fn write_region_chunk(path: &Path, (x, z): (i32, i32), data: &[u8]) -> std::io::Result<()> {
+    let sectors = (data.len() + 5).div_ceil(SECTOR);
+    if sectors > MAX_SECTORS {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("the chunk takes {} sectors", sectors),
+        ));
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    let mut header = vec![0; SECTOR * 2];
+    if file.metadata()?.len() >= header.len() as u64 {
+        file.read_exact(&mut header)?;
+    }
+    let index = ((x & 31) + (z & 31) * 32) as usize * 4;
+    let location = u32::from_be_bytes([
+        header[index],
+        header[index + 1],
+        header[index + 2],
+        header[index + 3],
+    ]);
+    let offset = match (location >> 8) as usize {
+        offset if offset >= 2 && (location & 0xff) as usize >= sectors => offset,
+        _ => (file.metadata()?.len() as usize).div_ceil(SECTOR).max(2),
+    };
+
+    let mut chunk = Vec::with_capacity(sectors * SECTOR);
+    chunk.extend_from_slice(&(data.len() as u32 + 1).to_be_bytes());
+    chunk.push(ZLIB);
+    chunk.extend_from_slice(data);
+    chunk.resize(sectors * SECTOR, 0);
+    file.seek(SeekFrom::Start((offset * SECTOR) as u64))?;
+    file.write_all(&chunk)?;
+
+    let location = (offset as u32) << 8 | sectors as u32;
+    header[index..index + 4].copy_from_slice(&location.to_be_bytes());
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs() as u32)
+        .unwrap_or(0);
+    header[SECTOR + index..SECTOR + index + 4].copy_from_slice(&now.to_be_bytes());
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&header)
+}
+
+// level.dat is gzipped instead of zlib compressed, miniz only does the deflating.
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut gzipped = vec![0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0xff];
+    gzipped.extend(miniz_oxide::deflate::compress_to_vec(data, 6));
+    gzipped.extend_from_slice(&crc32(data).to_le_bytes());
+    gzipped.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    gzipped
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                crc >> 1 ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCKS: &str = r#"{
+        "minecraft:air": {"states": [{"id": 0, "default": true}]},
+        "minecraft:stone": {"states": [{"id": 1, "default": true}]},
+        "minecraft:oak_log": {
+            "properties": {"axis": ["x", "y", "z"]},
+            "states": [
+                {"id": 73, "properties": {"axis": "x"}},
+                {"id": 74, "properties": {"axis": "y"}, "default": true},
+                {"id": 75, "properties": {"axis": "z"}}
+            ]
+        }
+    }"#;
+
+    fn read_nbt(data: &[u8]) -> Tag {
+        let mut packet = Packet::new();
+        packet.push_vec(data.to_vec());
+        packet.decode_nbt().unwrap().tag.unwrap()
+    }
+
+    #[test]
+    fn test_registry() {
+        let blocks = BlockRegistry::from_json(BLOCKS).unwrap();
+        assert_eq!(
+            blocks.get(74).unwrap().to_string(),
+            r#"{Name: "minecraft:oak_log", Properties: {axis: "y"}}"#
+        );
+        assert_eq!(
+            blocks.get(1).unwrap().to_string(),
+            r#"{Name: "minecraft:stone"}"#
+        );
+        assert!(blocks.get(2).is_none());
+        assert!(
+            BlockRegistry::from_json(r#"{"minecraft:stone": {"states": [{"id": 1}]}}"#).is_err()
+        );
+        assert_eq!(block_entity_id(9), Some("minecraft:sign"));
+        assert_eq!(block_entity_id(10), None);
+    }
+
+    #[test]
+    fn test_pack() {
+        assert_eq!(pack(&[1, 0, 1], 2), vec![0x101]);
+        assert_eq!(pack(&[1; 4096], 2).len(), 256);
+        // 5 bits fit 12 times in a long, the rest is left empty.
+        let data = pack(&[31; 13], 17);
+        assert_eq!(data, vec![0x0fff_ffff_ffff_ffff, 31]);
+    }
+
+    #[test]
+    fn test_gzip() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        let gzipped = gzip(b"level.dat");
+        assert_eq!(gzipped[..2], [0x1f, 0x8b]);
+        let inflated = miniz_oxide::inflate::decompress_to_vec(&gzipped[10..gzipped.len() - 8]);
+        assert_eq!(inflated.unwrap(), b"level.dat");
+    }
+
+    #[test]
+    fn test_download() {
+        let directory =
+            std::env::temp_dir().join(format!("mc_proxy_download_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let downloader = Downloader::new(
+            directory.to_str().unwrap(),
+            BlockRegistry::from_json(BLOCKS).unwrap(),
+        );
+        let mut status = Status::new();
+        status.protocol_version = 754;
+        status.world.dimension = "minecraft:overworld".into();
+        status.world.spawn = Some((-20, 70, 40));
+        let mut section = ChunkSection::default();
+        section.set(1, 2, 3, 74);
+        section.set(0, 0, 0, 1);
+        status
+            .world
+            .load_chunk((-1, 33), true, 0b1001, &[section, ChunkSection::default()]);
+        status.world.biomes.insert((-1, 33), vec![1; 1024]);
+        status.world.set_block_entity(
+            (-2, 5, 530),
+            Some(Tag::Compound(vec![(
+                "id".into(),
+                Tag::String("minecraft:sign".into()),
+            )])),
+        );
+        downloader.save_world(status.world.clone(), status.protocol_version);
+        downloader.flush();
+
+        let region = fs::read(directory.join("region").join("r.-1.1.mca")).unwrap();
+        // The chunk is the last one in the second row of the region.
+        let index = (31 + 32) * 4;
+        assert_eq!(region[index..index + 4], [0, 0, 2, 1]);
+        assert_eq!(region.len(), SECTOR * 3);
+        let length = u32::from_be_bytes([region[8192], region[8193], region[8194], region[8195]]);
+        assert_eq!(region[8196], ZLIB);
+        let data = &region[8197..8196 + length as usize];
+        let chunk = read_nbt(&miniz_oxide::inflate::decompress_to_vec_zlib(data).unwrap());
+        assert_eq!(chunk.get("DataVersion"), Some(&Tag::Int(2584)));
+        let level = chunk.get("Level").unwrap();
+        assert_eq!(level.get("xPos"), Some(&Tag::Int(-1)));
+        assert_eq!(level.get("zPos"), Some(&Tag::Int(33)));
+        assert_eq!(level.get("Biomes"), Some(&Tag::IntArray(vec![1; 1024])));
+        // The empty section at the top isn't saved.
+        let sections = match level.get("Sections") {
+            Some(Tag::List(10, sections)) => sections,
+            _ => panic!("no sections"),
+        };
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].get("Y"), Some(&Tag::Byte(0)));
+        assert_eq!(
+            sections[0].get("Palette").unwrap().to_string(),
+            r#"[{Name: "minecraft:stone"}, {Name: "minecraft:air"}, {Name: "minecraft:oak_log", Properties: {axis: "y"}}]"#
+        );
+        let states = match sections[0].get("BlockStates") {
+            Some(Tag::LongArray(states)) => states,
+            _ => panic!("no block states"),
+        };
+        assert_eq!(states.len(), 256);
+        assert_eq!(states[0], 0x1111_1111_1111_1110);
+        assert_eq!(
+            level.get("TileEntities").unwrap().to_string(),
+            r#"[{id: "minecraft:sign", x: -2, y: 5, z: 530}]"#
+        );
+
+        // Saving it again puts it in the same place, a chunk that doesn't fit goes at the end.
+        downloader.save_chunk(&status, (-1, 33));
+        downloader.flush();
+        assert_eq!(
+            fs::read(directory.join("region").join("r.-1.1.mca"))
+                .unwrap()
+                .len(),
+            SECTOR * 3
+        );
+        write_region_chunk(
+            &directory.join("region").join("r.-1.1.mca"),
+            (-1, 33),
+            &[0; 5000],
+        )
+        .unwrap();
+        let region = fs::read(directory.join("region").join("r.-1.1.mca")).unwrap();
+        assert_eq!(region[index..index + 4], [0, 0, 3, 2]);
+        assert_eq!(region.len(), SECTOR * 5);
+
+        let level = fs::read(directory.join("level.dat")).unwrap();
+        let level = read_nbt(
+            &miniz_oxide::inflate::decompress_to_vec(&level[10..level.len() - 8]).unwrap(),
+        );
+        let data = level.get("Data").unwrap();
+        assert_eq!(data.get("SpawnX"), Some(&Tag::Int(-20)));
+        assert_eq!(data.get("DataVersion"), Some(&Tag::Int(2584)));
+        let dimensions = data
+            .get("WorldGenSettings")
+            .and_then(|settings| settings.get("dimensions"))
+            .unwrap();
+        assert!(dimensions.get("minecraft:the_nether").is_some());
+        assert_eq!(
+            data.get("LevelName"),
+            Some(&Tag::String(
+                directory
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            ))
+        );
+
+        // Other dimensions go in their own folder.
+        status.world.dimension = "minecraft:the_nether".into();
+        downloader.save_chunk(&status, (-1, 33));
+        drop(downloader);
+        assert!(directory
+            .join("DIM-1")
+            .join("region")
+            .join("r.-1.1.mca")
+            .exists());
+        let writer = Writer {
+            directory: directory.clone(),
+            blocks: BlockRegistry::from_json(BLOCKS).unwrap(),
+        };
+        assert_eq!(
+            writer.region_directory("example:mining"),
+            directory
+                .join("dimensions")
+                .join("example")
+                .join("mining")
+                .join("region")
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use crate::types::{Direction, State};
+use std::fmt;
+
+// All the different ways parsing a packet can go wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    NotEnoughBytes { needed: usize, available: usize },
+    BadVarInt,
+    BadUtf8,
+    BadUuid(String),
+    InvalidLength { length: i64, max: usize },
+    UnknownEnumValue { name: &'static str, value: i64 },
+    Decompress(String),
+    MissingSecret(String),
+    Schema(String),
+    Nbt(String),
+    LeftoverBytes(usize),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotEnoughBytes { needed, available } => write!(
+                f,
+                "not enough bytes (needed {}, {} available)",
+                needed, available
+            ),
+            Self::BadVarInt => write!(f, "VarInt or VarLong is too long"),
+            Self::BadUtf8 => write!(f, "string is not valid UTF-8"),
+            Self::BadUuid(uuid) => write!(f, "{:?} is not a UUID", uuid),
+            Self::InvalidLength { length, max } => {
+                write!(f, "length {} is not between 0 and {}", length, max)
+            }
+            Self::UnknownEnumValue { name, value } => {
+                write!(f, "unknown value {} for {}", value, name)
+            }
+            Self::Decompress(why) => write!(f, "could not decompress packet ({})", why),
+            Self::MissingSecret(why) => write!(f, "could not get the shared secret ({})", why),
+            Self::Schema(why) => write!(f, "packet doesn't fit the schema ({})", why),
+            Self::Nbt(why) => write!(f, "invalid NBT ({})", why),
+            Self::LeftoverBytes(count) => write!(f, "{} bytes left over after parsing", count),
+        }
+    }
+}
+
+// Error holds what went wrong, at which byte of the packet it went wrong and,
+// once it reaches `packet_parser`, in which packet that was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub offset: usize,
+    pub packet: Option<String>,
+    pub direction: Option<Direction>,
+    pub state: Option<State>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, offset: usize) -> Self {
+        Self {
+            kind,
+            offset,
+            packet: None,
+            direction: None,
+            state: None,
+        }
+    }
+
+    pub fn with_context(
+        mut self,
+        direction: &Direction,
+        state: &State,
+        packet: Option<String>,
+    ) -> Self {
+        self.direction = Some(direction.clone());
+        self.state = Some(state.clone());
+        self.packet = packet;
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(packet) = &self.packet {
+            write!(f, "{} ", packet)?;
+        }
+        if let (Some(direction), Some(state)) = (&self.direction, &self.state) {
+            write!(f, "({} {}) ", direction, state)?;
+        }
+        write!(f, "at byte {}: {}", self.offset, self.kind)
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,13 +1,19 @@
 use crate::{
     clientbound,
     packet::Parsable,
+    schema::{Schema, SchemaPacket},
     serverbound,
     types::{Direction, State},
+    versions,
 };
 use maplit::hashmap;
-use std::collections::HashMap;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    sync::Arc,
+};
 
-#[derive(Hash, Eq, PartialEq, Debug)]
+#[derive(Hash, Eq, PartialEq, Debug, Clone)]
 pub enum Fid {
     Handshake,
     StatusResponse,
@@ -27,66 +33,406 @@ pub enum Fid {
     SpawnLivingEntity,
     SpawnPainting,
     SpawnPlayer,
+    EntityAnimation,
+    Statistics,
     AckPlayerDigging,
+    BlockBreakAnimation,
+    BlockEntityData,
+    BlockAction,
+    BlockChange,
+    BossBar,
+    ServerDifficulty,
+    ChatMessageClientbound,
+    TabCompleteClientbound,
+    DeclareCommands,
+    WindowConfirmationClientbound,
+    CloseWindowClientbound,
+    WindowItems,
+    WindowProperty,
+    SetSlot,
+    SetCooldown,
+    PluginMessageClientbound,
+    NamedSoundEffect,
+    DisconnectPlay,
+    EntityStatus,
+    Explosion,
+    UnloadChunk,
+    ChangeGameState,
+    OpenHorseWindow,
+    KeepAliveClientbound,
+    ChunkData,
+    Effect,
+    Particle,
+    UpdateLight,
+    JoinGame,
+    MapData,
+    TradeList,
+    EntityPosition,
+    EntityPositionAndRotation,
+    EntityRotation,
+    EntityMovement,
+    VehicleMoveClientbound,
+    OpenBook,
+    OpenWindow,
+    OpenSignEditor,
+    CraftRecipeResponse,
+    PlayerAbilitiesClientbound,
+    CombatEvent,
+    PlayerInfo,
+    FacePlayer,
+    PlayerPositionAndLook,
+    UnlockRecipes,
+    DestroyEntities,
+    RemoveEntityEffect,
+    ResourcePackSend,
+    Respawn,
+    EntityHeadLook,
+    MultiBlockChange,
+    SelectAdvancementTab,
+    WorldBorder,
+    Camera,
+    HeldItemChangeClientbound,
+    UpdateViewPosition,
+    UpdateViewDistance,
+    SpawnPosition,
+    DisplayScoreboard,
+    EntityMetadata,
+    AttachEntity,
+    EntityVelocity,
+    EntityEquipment,
+    SetExperience,
+    UpdateHealth,
+    ScoreboardObjective,
+    SetPassengers,
+    Teams,
+    UpdateScore,
+    TimeUpdate,
+    Title,
+    EntitySoundEffect,
+    SoundEffect,
+    StopSound,
+    PlayerListHeaderAndFooter,
+    NbtQueryResponse,
+    CollectItem,
+    EntityTeleport,
+    Advancements,
+    EntityProperties,
+    EntityEffect,
+    DeclareRecipes,
+    Tags,
+    TeleportConfirm,
+    QueryBlockNbt,
+    SetDifficulty,
+    ChatMessageServerbound,
+    ClientStatus,
+    ClientSettings,
+    TabCompleteServerbound,
+    WindowConfirmationServerbound,
+    ClickWindowButton,
+    ClickWindow,
+    CloseWindowServerbound,
+    PluginMessageServerbound,
+    EditBook,
+    QueryEntityNbt,
+    InteractEntity,
+    GenerateStructure,
+    KeepAliveServerbound,
+    LockDifficulty,
+    PlayerPosition,
+    PlayerPositionAndRotation,
+    PlayerRotation,
+    PlayerMovement,
+    VehicleMoveServerbound,
+    SteerBoat,
+    PickItem,
+    CraftRecipeRequest,
+    PlayerAbilitiesServerbound,
+    PlayerDigging,
+    EntityAction,
+    SteerVehicle,
+    SetRecipeBookState,
+    SetDisplayedRecipe,
+    NameItem,
+    ResourcePackStatus,
+    AdvancementTab,
+    SelectTrade,
+    SetBeaconEffect,
+    HeldItemChangeServerbound,
+    UpdateCommandBlock,
+    UpdateCommandBlockMinecart,
+    CreativeInventoryAction,
+    UpdateJigsawBlock,
+    UpdateStructureBlock,
+    UpdateSign,
+    Animation,
+    Spectate,
+    PlayerBlockPlacement,
+    UseItem,
+    // A packet from the schema file, by its name.
+    Schema(String),
 }
 
-impl Fid {
-    pub fn to_string(&self) -> String {
-        format!("{:?}", self)
+impl fmt::Display for Fid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Schema(name) => write!(f, "{}", name),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
+type IdMap = HashMap<Direction, HashMap<State, HashMap<i32, Fid>>>;
+
 pub struct Functions {
-    map: HashMap<Direction, HashMap<State, HashMap<i32, Fid>>>,
+    // The IDs for every group of versions, newest first. The number is the first version they're for.
+    maps: Vec<(i32, IdMap)>,
     list: HashMap<Fid, Box<dyn Parsable + Send>>,
 }
 
+// Everything before Play is the same for all supported versions, only the Play IDs are different.
+fn id_map(clientbound_play: HashMap<i32, Fid>, serverbound_play: HashMap<i32, Fid>) -> IdMap {
+    hashmap! {
+        Direction::Clientbound => hashmap! {
+            State::Handshaking => hashmap! {},
+            State::Status => hashmap! {
+                0x00 => Fid::StatusResponse,
+                0x01 => Fid::StatusPong
+            },
+            State::Login => hashmap! {
+                0x00 => Fid::Disconnect,
+                0x01 => Fid::EncRequest,
+                0x02 => Fid::LoginSuccess,
+                0x03 => Fid::SetCompression,
+                0x04 => Fid::PluginRequest
+            },
+            State::Play => clientbound_play,
+        },
+        Direction::Serverbound => hashmap! {
+            State::Handshaking => hashmap! {
+                0x00 => Fid::Handshake,
+            },
+            State::Status => hashmap! {
+                0x00 => Fid::StatusRequest,
+                0x01 => Fid::StatusPing,
+            },
+            State::Login => hashmap! {
+                0x00 => Fid::LoginStart,
+                0x01 => Fid::EncResponse,
+                0x02 => Fid::PluginResponse,
+            },
+            State::Play => serverbound_play,
+        },
+    }
+}
+
 impl Functions {
     fn new() -> Self {
+        // The whole Play state is only parsed from 1.16.2 on, older versions only have the
+        // clientbound spawn packets.
+        let play_1_16_2 = hashmap! {
+            0x00 => Fid::SpawnEntity,
+            0x01 => Fid::SpawnXpOrb,
+            0x02 => Fid::SpawnLivingEntity,
+            0x03 => Fid::SpawnPainting,
+            0x04 => Fid::SpawnPlayer,
+            0x05 => Fid::EntityAnimation,
+            0x06 => Fid::Statistics,
+            0x07 => Fid::AckPlayerDigging,
+            0x08 => Fid::BlockBreakAnimation,
+            0x09 => Fid::BlockEntityData,
+            0x0a => Fid::BlockAction,
+            0x0b => Fid::BlockChange,
+            0x0c => Fid::BossBar,
+            0x0d => Fid::ServerDifficulty,
+            0x0e => Fid::ChatMessageClientbound,
+            0x0f => Fid::TabCompleteClientbound,
+            0x10 => Fid::DeclareCommands,
+            0x11 => Fid::WindowConfirmationClientbound,
+            0x12 => Fid::CloseWindowClientbound,
+            0x13 => Fid::WindowItems,
+            0x14 => Fid::WindowProperty,
+            0x15 => Fid::SetSlot,
+            0x16 => Fid::SetCooldown,
+            0x17 => Fid::PluginMessageClientbound,
+            0x18 => Fid::NamedSoundEffect,
+            0x19 => Fid::DisconnectPlay,
+            0x1a => Fid::EntityStatus,
+            0x1b => Fid::Explosion,
+            0x1c => Fid::UnloadChunk,
+            0x1d => Fid::ChangeGameState,
+            0x1e => Fid::OpenHorseWindow,
+            0x1f => Fid::KeepAliveClientbound,
+            0x20 => Fid::ChunkData,
+            0x21 => Fid::Effect,
+            0x22 => Fid::Particle,
+            0x23 => Fid::UpdateLight,
+            0x24 => Fid::JoinGame,
+            0x25 => Fid::MapData,
+            0x26 => Fid::TradeList,
+            0x27 => Fid::EntityPosition,
+            0x28 => Fid::EntityPositionAndRotation,
+            0x29 => Fid::EntityRotation,
+            0x2a => Fid::EntityMovement,
+            0x2b => Fid::VehicleMoveClientbound,
+            0x2c => Fid::OpenBook,
+            0x2d => Fid::OpenWindow,
+            0x2e => Fid::OpenSignEditor,
+            0x2f => Fid::CraftRecipeResponse,
+            0x30 => Fid::PlayerAbilitiesClientbound,
+            0x31 => Fid::CombatEvent,
+            0x32 => Fid::PlayerInfo,
+            0x33 => Fid::FacePlayer,
+            0x34 => Fid::PlayerPositionAndLook,
+            0x35 => Fid::UnlockRecipes,
+            0x36 => Fid::DestroyEntities,
+            0x37 => Fid::RemoveEntityEffect,
+            0x38 => Fid::ResourcePackSend,
+            0x39 => Fid::Respawn,
+            0x3a => Fid::EntityHeadLook,
+            0x3b => Fid::MultiBlockChange,
+            0x3c => Fid::SelectAdvancementTab,
+            0x3d => Fid::WorldBorder,
+            0x3e => Fid::Camera,
+            0x3f => Fid::HeldItemChangeClientbound,
+            0x40 => Fid::UpdateViewPosition,
+            0x41 => Fid::UpdateViewDistance,
+            0x42 => Fid::SpawnPosition,
+            0x43 => Fid::DisplayScoreboard,
+            0x44 => Fid::EntityMetadata,
+            0x45 => Fid::AttachEntity,
+            0x46 => Fid::EntityVelocity,
+            0x47 => Fid::EntityEquipment,
+            0x48 => Fid::SetExperience,
+            0x49 => Fid::UpdateHealth,
+            0x4a => Fid::ScoreboardObjective,
+            0x4b => Fid::SetPassengers,
+            0x4c => Fid::Teams,
+            0x4d => Fid::UpdateScore,
+            0x4e => Fid::TimeUpdate,
+            0x4f => Fid::Title,
+            0x50 => Fid::EntitySoundEffect,
+            0x51 => Fid::SoundEffect,
+            0x52 => Fid::StopSound,
+            0x53 => Fid::PlayerListHeaderAndFooter,
+            0x54 => Fid::NbtQueryResponse,
+            0x55 => Fid::CollectItem,
+            0x56 => Fid::EntityTeleport,
+            0x57 => Fid::Advancements,
+            0x58 => Fid::EntityProperties,
+            0x59 => Fid::EntityEffect,
+            0x5a => Fid::DeclareRecipes,
+            0x5b => Fid::Tags,
+        };
+        let serverbound_play_1_16_2 = hashmap! {
+            0x00 => Fid::TeleportConfirm,
+            0x01 => Fid::QueryBlockNbt,
+            0x02 => Fid::SetDifficulty,
+            0x03 => Fid::ChatMessageServerbound,
+            0x04 => Fid::ClientStatus,
+            0x05 => Fid::ClientSettings,
+            0x06 => Fid::TabCompleteServerbound,
+            0x07 => Fid::WindowConfirmationServerbound,
+            0x08 => Fid::ClickWindowButton,
+            0x09 => Fid::ClickWindow,
+            0x0a => Fid::CloseWindowServerbound,
+            0x0b => Fid::PluginMessageServerbound,
+            0x0c => Fid::EditBook,
+            0x0d => Fid::QueryEntityNbt,
+            0x0e => Fid::InteractEntity,
+            0x0f => Fid::GenerateStructure,
+            0x10 => Fid::KeepAliveServerbound,
+            0x11 => Fid::LockDifficulty,
+            0x12 => Fid::PlayerPosition,
+            0x13 => Fid::PlayerPositionAndRotation,
+            0x14 => Fid::PlayerRotation,
+            0x15 => Fid::PlayerMovement,
+            0x16 => Fid::VehicleMoveServerbound,
+            0x17 => Fid::SteerBoat,
+            0x18 => Fid::PickItem,
+            0x19 => Fid::CraftRecipeRequest,
+            0x1a => Fid::PlayerAbilitiesServerbound,
+            0x1b => Fid::PlayerDigging,
+            0x1c => Fid::EntityAction,
+            0x1d => Fid::SteerVehicle,
+            0x1e => Fid::SetRecipeBookState,
+            0x1f => Fid::SetDisplayedRecipe,
+            0x20 => Fid::NameItem,
+            0x21 => Fid::ResourcePackStatus,
+            0x22 => Fid::AdvancementTab,
+            0x23 => Fid::SelectTrade,
+            0x24 => Fid::SetBeaconEffect,
+            0x25 => Fid::HeldItemChangeServerbound,
+            0x26 => Fid::UpdateCommandBlock,
+            0x27 => Fid::UpdateCommandBlockMinecart,
+            0x28 => Fid::CreativeInventoryAction,
+            0x29 => Fid::UpdateJigsawBlock,
+            0x2a => Fid::UpdateStructureBlock,
+            0x2b => Fid::UpdateSign,
+            0x2c => Fid::Animation,
+            0x2d => Fid::Spectate,
+            0x2e => Fid::PlayerBlockPlacement,
+            0x2f => Fid::UseItem,
+        };
+        let play_1_16 = hashmap! {
+            0x00 => Fid::SpawnEntity,
+            0x01 => Fid::SpawnXpOrb,
+            0x02 => Fid::SpawnLivingEntity,
+            0x03 => Fid::SpawnPainting,
+            0x04 => Fid::SpawnPlayer,
+            0x07 => Fid::AckPlayerDigging,
+        };
+        let play_1_12 = hashmap! {
+            0x00 => Fid::SpawnEntity,
+            0x01 => Fid::SpawnXpOrb,
+
+            0x03 => Fid::SpawnLivingEntity,
+            0x04 => Fid::SpawnPainting,
+            0x05 => Fid::SpawnPlayer,
+        };
         Self {
-            map: hashmap! {
-                Direction::Clientbound => hashmap! {
-                    State::Handshaking => hashmap! {},
-                    State::Status => hashmap! {
-                        0x00 => Fid::StatusResponse,
-                        0x01 => Fid::StatusPong
-                    },
-                    State::Login => hashmap! {
-                        0x00 => Fid::Disconnect,
-                        0x01 => Fid::EncRequest,
-                        0x02 => Fid::LoginSuccess,
-                        0x03 => Fid::SetCompression,
-                        0x04 => Fid::PluginRequest
-                    },
-                    State::Play => hashmap! {
-                        0x00 => Fid::SpawnEntity,
-                        0x01 => Fid::SpawnXpOrb,
-                        0x02 => Fid::SpawnLivingEntity,
-                        0x03 => Fid::SpawnPainting,
-                        0x04 => Fid::SpawnPlayer,
-
-
-
-                        0x07 => Fid::AckPlayerDigging,
-                    },
-                },
-                Direction::Serverbound => hashmap! {
-                    State::Handshaking => hashmap! {
-                        0x00 => Fid::Handshake.into(),
-                    },
-                    State::Status => hashmap! {
-                        0x00 => Fid::StatusRequest.into(),
-                        0x01 => Fid::StatusPing.into(),
-                    },
-                    State::Login => hashmap! {
-                        0x00 => Fid::LoginStart,
-                        0x01 => Fid::EncResponse,
-                        0x02 => Fid::PluginResponse,
-                    },
-                    State::Play => hashmap! {},
-                },
+            maps: vec![
+                (
+                    versions::V1_16_2,
+                    id_map(play_1_16_2, serverbound_play_1_16_2),
+                ),
+                (versions::V1_16, id_map(play_1_16, hashmap! {})),
+                (
+                    versions::V1_15,
+                    id_map(
+                        hashmap! {
+                            0x00 => Fid::SpawnEntity,
+                            0x01 => Fid::SpawnXpOrb,
 
-            },
+                            0x03 => Fid::SpawnLivingEntity,
+                            0x04 => Fid::SpawnPainting,
+                            0x05 => Fid::SpawnPlayer,
+
+
+                            0x08 => Fid::AckPlayerDigging,
+                        },
+                        hashmap! {},
+                    ),
+                ),
+                (
+                    versions::V1_14,
+                    id_map(
+                        hashmap! {
+                            0x00 => Fid::SpawnEntity,
+                            0x01 => Fid::SpawnXpOrb,
+
+                            0x03 => Fid::SpawnLivingEntity,
+                            0x04 => Fid::SpawnPainting,
+                            0x05 => Fid::SpawnPlayer,
+
+                            0x5c => Fid::AckPlayerDigging,
+                        },
+                        hashmap! {},
+                    ),
+                ),
+                // 1.13 only moved packets around that don't have a parser.
+                (versions::V1_13, id_map(play_1_12.clone(), hashmap! {})),
+                (versions::V1_12_2, id_map(play_1_12, hashmap! {})),
+            ],
             list: HashMap::new(),
         }
     }
@@ -95,23 +441,100 @@ impl Functions {
         self.list.insert(id, func);
     }
 
-    pub fn get_name(&self, direction: &Direction, state: &State, pid: &i32) -> Option<&Fid> {
+    // Adds the packets from a schema file. If a packet is also in the code (by name or by ID),
+    // the one in the code is used. A packet the code only has for some versions is still taken
+    // from the schema for the other ones.
+    pub fn add_schema(&mut self, schema: &Schema) {
+        for definition in &schema.packets {
+            let (direction, state) = match (&definition.direction, &definition.state) {
+                (Some(direction), Some(state)) => (direction, state),
+                _ => continue,
+            };
+            let parsed = self
+                .list
+                .keys()
+                .find(|id| id.to_string() == definition.name)
+                .cloned();
+            let id = Fid::Schema(definition.name.clone());
+            let mut used = false;
+            for (version, map) in self.maps.iter_mut() {
+                let pid = match definition.id_for(*version) {
+                    Some(pid) => pid,
+                    None => continue,
+                };
+                if let Some(ids) = map
+                    .get_mut(direction)
+                    .and_then(|states| states.get_mut(state))
+                {
+                    if parsed
+                        .as_ref()
+                        .is_some_and(|parsed| ids.values().any(|fid| fid == parsed))
+                    {
+                        continue;
+                    }
+                    if let Entry::Vacant(entry) = ids.entry(pid) {
+                        entry.insert(id.clone());
+                        used = true;
+                    }
+                }
+            }
+            if parsed.is_some() && !used {
+                log::debug!(
+                    "{} already has a parser, skipping the schema",
+                    definition.name
+                );
+                continue;
+            }
+            self.add(
+                id,
+                Box::new(SchemaPacket::new(Arc::new(definition.clone()))),
+            );
+        }
+    }
+
+    fn map(&self, protocol_version: i32) -> &IdMap {
+        let protocol_version = versions::parsing_version(protocol_version);
         match self
-            .map
-            .get(direction)
-            .unwrap()
-            .get(state)
-            .unwrap()
-            .get(pid)
+            .maps
+            .iter()
+            .find(|(version, _)| *version <= protocol_version)
         {
-            Some(id) => Some(id),
-            None => None,
+            Some((_, map)) => map,
+            None => &self.maps[0].1,
         }
     }
 
-    pub fn get(&self, id: &Fid) -> Option<&Box<dyn Parsable + Send>> {
+    pub fn get_name(
+        &self,
+        direction: &Direction,
+        state: &State,
+        protocol_version: i32,
+        pid: &i32,
+    ) -> Option<&Fid> {
+        self.map(protocol_version)
+            .get(direction)?
+            .get(state)?
+            .get(pid)
+    }
+
+    pub fn get_id(
+        &self,
+        direction: &Direction,
+        state: &State,
+        protocol_version: i32,
+        id: &Fid,
+    ) -> Option<i32> {
+        self.map(protocol_version)
+            .get(direction)?
+            .get(state)?
+            .iter()
+            .find(|(_, fid)| *fid == id)
+            .map(|(pid, _)| *pid)
+    }
+
+    pub fn get(&self, id: &Fid) -> Option<&(dyn Parsable + Send)> {
         match self.list.get(id) {
-            Some(func) => Some(func),
+            Some(func) => Some(func.as_ref()),
             None => None,
         }
     }
@@ -225,5 +648,2142 @@ pub fn get_functions() -> Functions {
         Box::new(clientbound::play::AckPlayerDigging::empty()),
     );
 
-    functions
+    functions.add(
+        Fid::EntityAnimation,
+        Box::new(clientbound::play::EntityAnimation::empty()),
+    );
+
+    functions.add(
+        Fid::Statistics,
+        Box::new(clientbound::play::Statistics::empty()),
+    );
+
+    functions.add(
+        Fid::BlockBreakAnimation,
+        Box::new(clientbound::play::BlockBreakAnimation::empty()),
+    );
+
+    functions.add(
+        Fid::BlockEntityData,
+        Box::new(clientbound::play::BlockEntityData::empty()),
+    );
+
+    functions.add(
+        Fid::BlockAction,
+        Box::new(clientbound::play::BlockAction::empty()),
+    );
+
+    functions.add(
+        Fid::BlockChange,
+        Box::new(clientbound::play::BlockChange::empty()),
+    );
+
+    functions.add(Fid::BossBar, Box::new(clientbound::play::BossBar::empty()));
+
+    functions.add(
+        Fid::ServerDifficulty,
+        Box::new(clientbound::play::ServerDifficulty::empty()),
+    );
+
+    functions.add(
+        Fid::ChatMessageClientbound,
+        Box::new(clientbound::play::ChatMessageClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::TabCompleteClientbound,
+        Box::new(clientbound::play::TabCompleteClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::DeclareCommands,
+        Box::new(clientbound::play::DeclareCommands::empty()),
+    );
+
+    functions.add(
+        Fid::WindowConfirmationClientbound,
+        Box::new(clientbound::play::WindowConfirmationClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::CloseWindowClientbound,
+        Box::new(clientbound::play::CloseWindowClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::WindowItems,
+        Box::new(clientbound::play::WindowItems::empty()),
+    );
+
+    functions.add(
+        Fid::WindowProperty,
+        Box::new(clientbound::play::WindowProperty::empty()),
+    );
+
+    functions.add(Fid::SetSlot, Box::new(clientbound::play::SetSlot::empty()));
+
+    functions.add(
+        Fid::SetCooldown,
+        Box::new(clientbound::play::SetCooldown::empty()),
+    );
+
+    functions.add(
+        Fid::PluginMessageClientbound,
+        Box::new(clientbound::play::PluginMessageClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::NamedSoundEffect,
+        Box::new(clientbound::play::NamedSoundEffect::empty()),
+    );
+
+    functions.add(
+        Fid::DisconnectPlay,
+        Box::new(clientbound::play::DisconnectPlay::empty()),
+    );
+
+    functions.add(
+        Fid::EntityStatus,
+        Box::new(clientbound::play::EntityStatus::empty()),
+    );
+
+    functions.add(
+        Fid::Explosion,
+        Box::new(clientbound::play::Explosion::empty()),
+    );
+
+    functions.add(
+        Fid::UnloadChunk,
+        Box::new(clientbound::play::UnloadChunk::empty()),
+    );
+
+    functions.add(
+        Fid::ChangeGameState,
+        Box::new(clientbound::play::ChangeGameState::empty()),
+    );
+
+    functions.add(
+        Fid::OpenHorseWindow,
+        Box::new(clientbound::play::OpenHorseWindow::empty()),
+    );
+
+    functions.add(
+        Fid::KeepAliveClientbound,
+        Box::new(clientbound::play::KeepAliveClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::ChunkData,
+        Box::new(clientbound::play::ChunkData::empty()),
+    );
+
+    functions.add(Fid::Effect, Box::new(clientbound::play::Effect::empty()));
+
+    functions.add(
+        Fid::Particle,
+        Box::new(clientbound::play::Particle::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateLight,
+        Box::new(clientbound::play::UpdateLight::empty()),
+    );
+
+    functions.add(
+        Fid::JoinGame,
+        Box::new(clientbound::play::JoinGame::empty()),
+    );
+
+    functions.add(Fid::MapData, Box::new(clientbound::play::MapData::empty()));
+
+    functions.add(
+        Fid::TradeList,
+        Box::new(clientbound::play::TradeList::empty()),
+    );
+
+    functions.add(
+        Fid::EntityPosition,
+        Box::new(clientbound::play::EntityPosition::empty()),
+    );
+
+    functions.add(
+        Fid::EntityPositionAndRotation,
+        Box::new(clientbound::play::EntityPositionAndRotation::empty()),
+    );
+
+    functions.add(
+        Fid::EntityRotation,
+        Box::new(clientbound::play::EntityRotation::empty()),
+    );
+
+    functions.add(
+        Fid::EntityMovement,
+        Box::new(clientbound::play::EntityMovement::empty()),
+    );
+
+    functions.add(
+        Fid::VehicleMoveClientbound,
+        Box::new(clientbound::play::VehicleMoveClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::OpenBook,
+        Box::new(clientbound::play::OpenBook::empty()),
+    );
+
+    functions.add(
+        Fid::OpenWindow,
+        Box::new(clientbound::play::OpenWindow::empty()),
+    );
+
+    functions.add(
+        Fid::OpenSignEditor,
+        Box::new(clientbound::play::OpenSignEditor::empty()),
+    );
+
+    functions.add(
+        Fid::CraftRecipeResponse,
+        Box::new(clientbound::play::CraftRecipeResponse::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerAbilitiesClientbound,
+        Box::new(clientbound::play::PlayerAbilitiesClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::CombatEvent,
+        Box::new(clientbound::play::CombatEvent::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerInfo,
+        Box::new(clientbound::play::PlayerInfo::empty()),
+    );
+
+    functions.add(
+        Fid::FacePlayer,
+        Box::new(clientbound::play::FacePlayer::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerPositionAndLook,
+        Box::new(clientbound::play::PlayerPositionAndLook::empty()),
+    );
+
+    functions.add(
+        Fid::UnlockRecipes,
+        Box::new(clientbound::play::UnlockRecipes::empty()),
+    );
+
+    functions.add(
+        Fid::DestroyEntities,
+        Box::new(clientbound::play::DestroyEntities::empty()),
+    );
+
+    functions.add(
+        Fid::RemoveEntityEffect,
+        Box::new(clientbound::play::RemoveEntityEffect::empty()),
+    );
+
+    functions.add(
+        Fid::ResourcePackSend,
+        Box::new(clientbound::play::ResourcePackSend::empty()),
+    );
+
+    functions.add(Fid::Respawn, Box::new(clientbound::play::Respawn::empty()));
+
+    functions.add(
+        Fid::EntityHeadLook,
+        Box::new(clientbound::play::EntityHeadLook::empty()),
+    );
+
+    functions.add(
+        Fid::MultiBlockChange,
+        Box::new(clientbound::play::MultiBlockChange::empty()),
+    );
+
+    functions.add(
+        Fid::SelectAdvancementTab,
+        Box::new(clientbound::play::SelectAdvancementTab::empty()),
+    );
+
+    functions.add(
+        Fid::WorldBorder,
+        Box::new(clientbound::play::WorldBorder::empty()),
+    );
+
+    functions.add(Fid::Camera, Box::new(clientbound::play::Camera::empty()));
+
+    functions.add(
+        Fid::HeldItemChangeClientbound,
+        Box::new(clientbound::play::HeldItemChangeClientbound::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateViewPosition,
+        Box::new(clientbound::play::UpdateViewPosition::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateViewDistance,
+        Box::new(clientbound::play::UpdateViewDistance::empty()),
+    );
+
+    functions.add(
+        Fid::SpawnPosition,
+        Box::new(clientbound::play::SpawnPosition::empty()),
+    );
+
+    functions.add(
+        Fid::DisplayScoreboard,
+        Box::new(clientbound::play::DisplayScoreboard::empty()),
+    );
+
+    functions.add(
+        Fid::EntityMetadata,
+        Box::new(clientbound::play::EntityMetadata::empty()),
+    );
+
+    functions.add(
+        Fid::AttachEntity,
+        Box::new(clientbound::play::AttachEntity::empty()),
+    );
+
+    functions.add(
+        Fid::EntityVelocity,
+        Box::new(clientbound::play::EntityVelocity::empty()),
+    );
+
+    functions.add(
+        Fid::EntityEquipment,
+        Box::new(clientbound::play::EntityEquipment::empty()),
+    );
+
+    functions.add(
+        Fid::SetExperience,
+        Box::new(clientbound::play::SetExperience::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateHealth,
+        Box::new(clientbound::play::UpdateHealth::empty()),
+    );
+
+    functions.add(
+        Fid::ScoreboardObjective,
+        Box::new(clientbound::play::ScoreboardObjective::empty()),
+    );
+
+    functions.add(
+        Fid::SetPassengers,
+        Box::new(clientbound::play::SetPassengers::empty()),
+    );
+
+    functions.add(Fid::Teams, Box::new(clientbound::play::Teams::empty()));
+
+    functions.add(
+        Fid::UpdateScore,
+        Box::new(clientbound::play::UpdateScore::empty()),
+    );
+
+    functions.add(
+        Fid::TimeUpdate,
+        Box::new(clientbound::play::TimeUpdate::empty()),
+    );
+
+    functions.add(Fid::Title, Box::new(clientbound::play::Title::empty()));
+
+    functions.add(
+        Fid::EntitySoundEffect,
+        Box::new(clientbound::play::EntitySoundEffect::empty()),
+    );
+
+    functions.add(
+        Fid::SoundEffect,
+        Box::new(clientbound::play::SoundEffect::empty()),
+    );
+
+    functions.add(
+        Fid::StopSound,
+        Box::new(clientbound::play::StopSound::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerListHeaderAndFooter,
+        Box::new(clientbound::play::PlayerListHeaderAndFooter::empty()),
+    );
+
+    functions.add(
+        Fid::NbtQueryResponse,
+        Box::new(clientbound::play::NbtQueryResponse::empty()),
+    );
+
+    functions.add(
+        Fid::CollectItem,
+        Box::new(clientbound::play::CollectItem::empty()),
+    );
+
+    functions.add(
+        Fid::EntityTeleport,
+        Box::new(clientbound::play::EntityTeleport::empty()),
+    );
+
+    functions.add(
+        Fid::Advancements,
+        Box::new(clientbound::play::Advancements::empty()),
+    );
+
+    functions.add(
+        Fid::EntityProperties,
+        Box::new(clientbound::play::EntityProperties::empty()),
+    );
+
+    functions.add(
+        Fid::EntityEffect,
+        Box::new(clientbound::play::EntityEffect::empty()),
+    );
+
+    functions.add(
+        Fid::DeclareRecipes,
+        Box::new(clientbound::play::DeclareRecipes::empty()),
+    );
+
+    functions.add(Fid::Tags, Box::new(clientbound::play::Tags::empty()));
+
+    // Serverbound
+    functions.add(
+        Fid::TeleportConfirm,
+        Box::new(serverbound::play::TeleportConfirm::empty()),
+    );
+
+    functions.add(
+        Fid::QueryBlockNbt,
+        Box::new(serverbound::play::QueryBlockNbt::empty()),
+    );
+
+    functions.add(
+        Fid::SetDifficulty,
+        Box::new(serverbound::play::SetDifficulty::empty()),
+    );
+
+    functions.add(
+        Fid::ChatMessageServerbound,
+        Box::new(serverbound::play::ChatMessageServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::ClientStatus,
+        Box::new(serverbound::play::ClientStatus::empty()),
+    );
+
+    functions.add(
+        Fid::ClientSettings,
+        Box::new(serverbound::play::ClientSettings::empty()),
+    );
+
+    functions.add(
+        Fid::TabCompleteServerbound,
+        Box::new(serverbound::play::TabCompleteServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::WindowConfirmationServerbound,
+        Box::new(serverbound::play::WindowConfirmationServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::ClickWindowButton,
+        Box::new(serverbound::play::ClickWindowButton::empty()),
+    );
+
+    functions.add(
+        Fid::ClickWindow,
+        Box::new(serverbound::play::ClickWindow::empty()),
+    );
+
+    functions.add(
+        Fid::CloseWindowServerbound,
+        Box::new(serverbound::play::CloseWindowServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::PluginMessageServerbound,
+        Box::new(serverbound::play::PluginMessageServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::EditBook,
+        Box::new(serverbound::play::EditBook::empty()),
+    );
+
+    functions.add(
+        Fid::QueryEntityNbt,
+        Box::new(serverbound::play::QueryEntityNbt::empty()),
+    );
+
+    functions.add(
+        Fid::InteractEntity,
+        Box::new(serverbound::play::InteractEntity::empty()),
+    );
+
+    functions.add(
+        Fid::GenerateStructure,
+        Box::new(serverbound::play::GenerateStructure::empty()),
+    );
+
+    functions.add(
+        Fid::KeepAliveServerbound,
+        Box::new(serverbound::play::KeepAliveServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::LockDifficulty,
+        Box::new(serverbound::play::LockDifficulty::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerPosition,
+        Box::new(serverbound::play::PlayerPosition::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerPositionAndRotation,
+        Box::new(serverbound::play::PlayerPositionAndRotation::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerRotation,
+        Box::new(serverbound::play::PlayerRotation::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerMovement,
+        Box::new(serverbound::play::PlayerMovement::empty()),
+    );
+
+    functions.add(
+        Fid::VehicleMoveServerbound,
+        Box::new(serverbound::play::VehicleMoveServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::SteerBoat,
+        Box::new(serverbound::play::SteerBoat::empty()),
+    );
+
+    functions.add(
+        Fid::PickItem,
+        Box::new(serverbound::play::PickItem::empty()),
+    );
+
+    functions.add(
+        Fid::CraftRecipeRequest,
+        Box::new(serverbound::play::CraftRecipeRequest::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerAbilitiesServerbound,
+        Box::new(serverbound::play::PlayerAbilitiesServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerDigging,
+        Box::new(serverbound::play::PlayerDigging::empty()),
+    );
+
+    functions.add(
+        Fid::EntityAction,
+        Box::new(serverbound::play::EntityAction::empty()),
+    );
+
+    functions.add(
+        Fid::SteerVehicle,
+        Box::new(serverbound::play::SteerVehicle::empty()),
+    );
+
+    functions.add(
+        Fid::SetRecipeBookState,
+        Box::new(serverbound::play::SetRecipeBookState::empty()),
+    );
+
+    functions.add(
+        Fid::SetDisplayedRecipe,
+        Box::new(serverbound::play::SetDisplayedRecipe::empty()),
+    );
+
+    functions.add(
+        Fid::NameItem,
+        Box::new(serverbound::play::NameItem::empty()),
+    );
+
+    functions.add(
+        Fid::ResourcePackStatus,
+        Box::new(serverbound::play::ResourcePackStatus::empty()),
+    );
+
+    functions.add(
+        Fid::AdvancementTab,
+        Box::new(serverbound::play::AdvancementTab::empty()),
+    );
+
+    functions.add(
+        Fid::SelectTrade,
+        Box::new(serverbound::play::SelectTrade::empty()),
+    );
+
+    functions.add(
+        Fid::SetBeaconEffect,
+        Box::new(serverbound::play::SetBeaconEffect::empty()),
+    );
+
+    functions.add(
+        Fid::HeldItemChangeServerbound,
+        Box::new(serverbound::play::HeldItemChangeServerbound::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateCommandBlock,
+        Box::new(serverbound::play::UpdateCommandBlock::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateCommandBlockMinecart,
+        Box::new(serverbound::play::UpdateCommandBlockMinecart::empty()),
+    );
+
+    functions.add(
+        Fid::CreativeInventoryAction,
+        Box::new(serverbound::play::CreativeInventoryAction::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateJigsawBlock,
+        Box::new(serverbound::play::UpdateJigsawBlock::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateStructureBlock,
+        Box::new(serverbound::play::UpdateStructureBlock::empty()),
+    );
+
+    functions.add(
+        Fid::UpdateSign,
+        Box::new(serverbound::play::UpdateSign::empty()),
+    );
+
+    functions.add(
+        Fid::Animation,
+        Box::new(serverbound::play::Animation::empty()),
+    );
+
+    functions.add(
+        Fid::Spectate,
+        Box::new(serverbound::play::Spectate::empty()),
+    );
+
+    functions.add(
+        Fid::PlayerBlockPlacement,
+        Box::new(serverbound::play::PlayerBlockPlacement::empty()),
+    );
+
+    functions.add(Fid::UseItem, Box::new(serverbound::play::UseItem::empty()));
+
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Value;
+    use crate::packet::{Field, Packet};
+    use crate::types::Status;
+    use crate::world::ChunkSection;
+
+    // One valid packet body (without the packet ID) for every registered parser.
+    fn fixtures() -> Vec<(Fid, Packet)> {
+        let mut fixtures = Vec::new();
+
+        let mut packet = Packet::new();
+        packet.encode_varint(754);
+        packet.encode_string("localhost");
+        packet.encode_ushort(25565);
+        packet.encode_varint(2);
+        fixtures.push((Fid::Handshake, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string(r#"{"version":{"name":"1.16.5","protocol":754}}"#);
+        fixtures.push((Fid::StatusResponse, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_long(-1234567890123);
+        fixtures.push((Fid::StatusPong, packet));
+
+        fixtures.push((Fid::StatusRequest, Packet::new()));
+
+        let mut packet = Packet::new();
+        packet.encode_long(1234567890123);
+        fixtures.push((Fid::StatusPing, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_chat(r#"{"text":"Kicked"}"#);
+        fixtures.push((Fid::Disconnect, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("");
+        packet.encode_varint(4);
+        packet.push_vec(vec![0x30, 0x81, 0x9f, 0x30]);
+        packet.encode_varint(4);
+        packet.push_vec(vec![0xde, 0xad, 0xbe, 0xef]);
+        fixtures.push((Fid::EncRequest, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_uuid(0x069a79f444e94726a5befca90e38aaf5);
+        packet.encode_string("Notch");
+        fixtures.push((Fid::LoginSuccess, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(256);
+        fixtures.push((Fid::SetCompression, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(7);
+        packet.encode_identifier("velocity:player_info");
+        packet.push_vec(vec![0x01, 0x02, 0x03]);
+        fixtures.push((Fid::PluginRequest, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("zegevlier");
+        fixtures.push((Fid::LoginStart, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(3);
+        packet.push_vec(vec![0x01, 0x02, 0x03]);
+        packet.encode_varint(2);
+        packet.push_vec(vec![0x04, 0x05]);
+        fixtures.push((Fid::EncResponse, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(7);
+        packet.encode_bool(true);
+        packet.push_vec(vec![0xff]);
+        fixtures.push((Fid::PluginResponse, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(42);
+        packet.encode_uuid(1);
+        packet.encode_varint(2);
+        packet.encode_double(-10.5);
+        packet.encode_double(64.0);
+        packet.encode_double(1e9);
+        packet.encode_angle(12);
+        packet.encode_angle(250);
+        packet.encode_int(-1);
+        packet.encode_short(-800);
+        packet.encode_short(0);
+        packet.encode_short(800);
+        fixtures.push((Fid::SpawnEntity, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(43);
+        packet.encode_double(1.0);
+        packet.encode_double(2.0);
+        packet.encode_double(3.0);
+        packet.encode_short(17);
+        fixtures.push((Fid::SpawnXpOrb, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(44);
+        packet.encode_uuid(u128::MAX);
+        packet.encode_varint(95);
+        packet.encode_double(0.25);
+        packet.encode_double(-0.25);
+        packet.encode_double(f64::MAX);
+        packet.encode_angle(1);
+        packet.encode_angle(2);
+        packet.encode_angle(3);
+        packet.encode_short(i16::MIN);
+        packet.encode_short(i16::MAX);
+        packet.encode_short(5);
+        fixtures.push((Fid::SpawnLivingEntity, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(45);
+        packet.encode_uuid(0xabcdef);
+        packet.encode_varint(3);
+        packet.encode_position((-5, 3, -7));
+        packet.encode_ubyte(0x03);
+        fixtures.push((Fid::SpawnPainting, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_uuid(0x069a79f444e94726a5befca90e38aaf5);
+        packet.encode_double(100.5);
+        packet.encode_double(70.0);
+        packet.encode_double(-100.5);
+        packet.encode_angle(128);
+        packet.encode_angle(64);
+        fixtures.push((Fid::SpawnPlayer, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((-1234567, -60, 7654321));
+        packet.encode_varint(9);
+        packet.encode_varint(0x02);
+        packet.encode_bool(true);
+        fixtures.push((Fid::AckPlayerDigging, packet));
+
+        // A compound with an int in it.
+        let nbt = vec![0x0a, 0x00, 0x00, 0x03, 0x00, 0x01, b'x', 0, 0, 0, 5, 0x00];
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_ubyte(3);
+        fixtures.push((Fid::EntityAnimation, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(2);
+        packet.push_vec(vec![0x00, 0x01, 0x05]);
+        packet.push_vec(vec![0x08, 0x80, 0x01, 0xff, 0x01]);
+        fixtures.push((Fid::Statistics, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_position((10, 64, -10));
+        packet.encode_byte(7);
+        fixtures.push((Fid::BlockBreakAnimation, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((10, 64, -10));
+        packet.encode_ubyte(9);
+        packet.push_vec(nbt.clone());
+        fixtures.push((Fid::BlockEntityData, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((1, 2, 3));
+        packet.encode_ubyte(1);
+        packet.encode_ubyte(2);
+        packet.encode_varint(144);
+        fixtures.push((Fid::BlockAction, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((-1, 255, 1));
+        packet.encode_varint(9);
+        fixtures.push((Fid::BlockChange, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_uuid(0xb055);
+        packet.encode_varint(0);
+        packet.encode_chat(r#"{"text":"Ender Dragon"}"#);
+        packet.encode_float(0.5);
+        packet.encode_varint(5);
+        packet.encode_varint(1);
+        packet.encode_ubyte(0x01);
+        fixtures.push((Fid::BossBar, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_ubyte(2);
+        packet.encode_bool(true);
+        fixtures.push((Fid::ServerDifficulty, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_chat(r#"{"text":"Hello"}"#);
+        packet.encode_byte(0);
+        packet.encode_uuid(0x069a79f444e94726a5befca90e38aaf5);
+        fixtures.push((Fid::ChatMessageClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(3);
+        packet.encode_varint(1);
+        packet.encode_varint(4);
+        packet.encode_varint(2);
+        packet.encode_string("help");
+        packet.encode_bool(false);
+        packet.encode_string("home");
+        packet.encode_bool(true);
+        packet.encode_chat(r#"{"text":"Go home"}"#);
+        fixtures.push((Fid::TabCompleteClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(4);
+        // The root, with the literal as its child.
+        packet.push_vec(vec![0x00, 0x01, 0x01]);
+        // "tp", with an argument as its child.
+        packet.push_vec(vec![0x05, 0x01, 0x02]);
+        packet.encode_string("tp");
+        // An integer argument with a minimum, that can be run.
+        packet.push_vec(vec![0x06, 0x01, 0x03]);
+        packet.encode_string("count");
+        packet.encode_identifier("brigadier:integer");
+        packet.push_vec(vec![0x01, 0x00, 0x00, 0x00, 0x01]);
+        // A string argument with suggestions, that redirects to the root.
+        packet.push_vec(vec![0x1e, 0x00, 0x00]);
+        packet.encode_string("name");
+        packet.encode_identifier("brigadier:string");
+        packet.encode_varint(2);
+        packet.encode_identifier("minecraft:ask_server");
+        packet.encode_varint(0);
+        fixtures.push((Fid::DeclareCommands, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(1);
+        packet.encode_short(12);
+        packet.encode_bool(true);
+        fixtures.push((Fid::WindowConfirmationClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_ubyte(1);
+        fixtures.push((Fid::CloseWindowClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_ubyte(0);
+        packet.encode_short(2);
+        packet.push_vec(vec![0x01, 0x01, 0x40, 0x00, 0x00]);
+        fixtures.push((Fid::WindowItems, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_ubyte(2);
+        packet.encode_short(0);
+        packet.encode_short(200);
+        fixtures.push((Fid::WindowProperty, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(-1);
+        packet.encode_short(-1);
+        packet.push_vec(vec![0x00]);
+        fixtures.push((Fid::SetSlot, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(762);
+        packet.encode_varint(20);
+        fixtures.push((Fid::SetCooldown, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_identifier("minecraft:brand");
+        packet.encode_string("vanilla");
+        fixtures.push((Fid::PluginMessageClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_identifier("minecraft:entity.pig.ambient");
+        packet.encode_varint(5);
+        packet.encode_int(80);
+        packet.encode_int(-512);
+        packet.encode_int(8);
+        packet.encode_float(1.0);
+        packet.encode_float(0.5);
+        fixtures.push((Fid::NamedSoundEffect, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_chat(r#"{"text":"Timed out"}"#);
+        fixtures.push((Fid::DisconnectPlay, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_int(46);
+        packet.encode_byte(24);
+        fixtures.push((Fid::EntityStatus, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_float(10.5);
+        packet.encode_float(64.0);
+        packet.encode_float(-3.5);
+        packet.encode_float(4.0);
+        packet.encode_int(2);
+        packet.push_vec(vec![0x00, 0xff, 0x01, 0x02, 0x00, 0xfe]);
+        packet.encode_float(0.0);
+        packet.encode_float(0.25);
+        packet.encode_float(-0.25);
+        fixtures.push((Fid::Explosion, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_int(-3);
+        packet.encode_int(7);
+        fixtures.push((Fid::UnloadChunk, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_ubyte(3);
+        packet.encode_float(1.0);
+        fixtures.push((Fid::ChangeGameState, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_ubyte(3);
+        packet.encode_varint(17);
+        packet.encode_int(46);
+        fixtures.push((Fid::OpenHorseWindow, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_long(0x1234_5678_9abc);
+        fixtures.push((Fid::KeepAliveClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_int(-3);
+        packet.encode_int(7);
+        packet.encode_bool(true);
+        packet.encode_varint(0b101);
+        packet.push_vec(nbt.clone());
+        packet.encode_varint(3);
+        packet.encode_varint(1);
+        packet.encode_varint(127);
+        packet.encode_varint(200);
+        // A stone block in the corner of the bottom section, and an empty one.
+        let mut sections = Packet::new();
+        let mut section = ChunkSection::default();
+        section.set(0, 0, 0, 1);
+        section.encode(&mut sections);
+        ChunkSection::default().encode(&mut sections);
+        packet.encode_varint(sections.len() as i32);
+        packet.push_vec(sections.get_vec());
+        packet.encode_varint(1);
+        packet.push_vec(nbt.clone());
+        fixtures.push((Fid::ChunkData, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_int(1010);
+        packet.encode_position((5, 60, 5));
+        packet.encode_int(2256);
+        packet.encode_bool(false);
+        fixtures.push((Fid::Effect, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_int(3);
+        packet.encode_bool(true);
+        packet.encode_double(1.0);
+        packet.encode_double(2.0);
+        packet.encode_double(3.0);
+        packet.encode_float(0.1);
+        packet.encode_float(0.2);
+        packet.encode_float(0.3);
+        packet.encode_float(0.0);
+        packet.encode_int(20);
+        packet.encode_varint(1);
+        fixtures.push((Fid::Particle, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(-3);
+        packet.encode_varint(7);
+        packet.encode_bool(true);
+        packet.encode_varint(0b110);
+        packet.encode_varint(0b1);
+        packet.encode_varint(0b1000);
+        packet.encode_varint(0);
+        for fill in &[0x11, 0x22, 0x33] {
+            packet.encode_varint(4);
+            packet.push_vec(vec![*fill; 4]);
+        }
+        fixtures.push((Fid::UpdateLight, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_int(46);
+        packet.encode_bool(false);
+        packet.encode_ubyte(1);
+        packet.encode_byte(-1);
+        packet.encode_varint(2);
+        packet.encode_identifier("minecraft:overworld");
+        packet.encode_identifier("minecraft:the_nether");
+        packet.push_vec(nbt.clone());
+        packet.push_vec(nbt.clone());
+        packet.encode_identifier("minecraft:overworld");
+        packet.encode_long(-1234567);
+        packet.encode_varint(20);
+        packet.encode_varint(10);
+        packet.encode_bool(false);
+        packet.encode_bool(true);
+        packet.encode_bool(false);
+        packet.encode_bool(true);
+        fixtures.push((Fid::JoinGame, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(5);
+        packet.encode_byte(2);
+        packet.encode_bool(true);
+        packet.encode_bool(false);
+        packet.encode_varint(2);
+        packet.push_vec(vec![0x00, 0x10, 0xf0, 0x04, 0x00]);
+        packet.push_vec(vec![0x09, 0x00, 0x00, 0x08, 0x01]);
+        packet.encode_chat(r#"{"text":"Home"}"#);
+        packet.encode_ubyte(2);
+        packet.encode_ubyte(1);
+        packet.encode_byte(64);
+        packet.encode_byte(32);
+        packet.encode_varint(2);
+        packet.push_vec(vec![0x22, 0x23]);
+        fixtures.push((Fid::MapData, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(2);
+        packet.encode_ubyte(1);
+        // Three emeralds for six bread.
+        packet.push_vec(vec![0x01, 0xaf, 0x05, 0x03, 0x00]);
+        packet.push_vec(vec![0x01, 0xb1, 0x04, 0x06, 0x00]);
+        packet.encode_bool(false);
+        packet.encode_bool(false);
+        packet.encode_int(0);
+        packet.encode_int(16);
+        packet.encode_int(2);
+        packet.encode_int(0);
+        packet.encode_float(0.05);
+        packet.encode_int(0);
+        packet.encode_varint(1);
+        packet.encode_varint(0);
+        packet.encode_bool(true);
+        packet.encode_bool(true);
+        fixtures.push((Fid::TradeList, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_short(4096);
+        packet.encode_short(-1);
+        packet.encode_short(0);
+        packet.encode_bool(true);
+        fixtures.push((Fid::EntityPosition, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_short(4096);
+        packet.encode_short(-1);
+        packet.encode_short(0);
+        packet.encode_angle(64);
+        packet.encode_angle(192);
+        packet.encode_bool(false);
+        fixtures.push((Fid::EntityPositionAndRotation, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_angle(64);
+        packet.encode_angle(192);
+        packet.encode_bool(true);
+        fixtures.push((Fid::EntityRotation, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        fixtures.push((Fid::EntityMovement, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_double(1.5);
+        packet.encode_double(63.0);
+        packet.encode_double(-1.5);
+        packet.encode_float(90.0);
+        packet.encode_float(-10.0);
+        fixtures.push((Fid::VehicleMoveClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(1);
+        fixtures.push((Fid::OpenBook, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(3);
+        packet.encode_varint(2);
+        packet.encode_chat(r#"{"translate":"container.chest"}"#);
+        fixtures.push((Fid::OpenWindow, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((100, 70, -100));
+        fixtures.push((Fid::OpenSignEditor, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(1);
+        packet.encode_identifier("minecraft:crafting_table");
+        fixtures.push((Fid::CraftRecipeResponse, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(0x0d);
+        packet.encode_float(0.05);
+        packet.encode_float(0.1);
+        fixtures.push((Fid::PlayerAbilitiesClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(2);
+        packet.encode_varint(46);
+        packet.encode_int(47);
+        packet.encode_chat(r#"{"text":"Fell out of the world"}"#);
+        fixtures.push((Fid::CombatEvent, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        packet.encode_varint(1);
+        packet.encode_uuid(0x069a79f444e94726a5befca90e38aaf5);
+        packet.encode_string("Notch");
+        packet.encode_varint(1);
+        packet.encode_string("textures");
+        packet.encode_string("e30=");
+        packet.encode_bool(true);
+        packet.encode_string("c2lnbmF0dXJl");
+        packet.encode_varint(1);
+        packet.encode_varint(35);
+        packet.encode_bool(false);
+        fixtures.push((Fid::PlayerInfo, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(1);
+        packet.encode_double(0.5);
+        packet.encode_double(65.0);
+        packet.encode_double(0.5);
+        packet.encode_bool(true);
+        packet.encode_varint(46);
+        packet.encode_varint(0);
+        fixtures.push((Fid::FacePlayer, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_double(8.5);
+        packet.encode_double(65.0);
+        packet.encode_double(-8.5);
+        packet.encode_float(0.0);
+        packet.encode_float(0.0);
+        packet.encode_byte(0x18);
+        packet.encode_varint(1);
+        fixtures.push((Fid::PlayerPositionAndLook, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        packet.push_vec(vec![0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01, 0x01]);
+        packet.encode_varint(1);
+        packet.encode_identifier("minecraft:stick");
+        packet.encode_varint(2);
+        packet.encode_identifier("minecraft:stick");
+        packet.encode_identifier("minecraft:torch");
+        fixtures.push((Fid::UnlockRecipes, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(3);
+        packet.encode_varint(46);
+        packet.encode_varint(47);
+        packet.encode_varint(300);
+        fixtures.push((Fid::DestroyEntities, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_byte(1);
+        fixtures.push((Fid::RemoveEntityEffect, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("https://example.com/pack.zip");
+        packet.encode_string("2ef7bde608ce5404e97d5f042f95f89f1c232871");
+        fixtures.push((Fid::ResourcePackSend, packet));
+
+        let mut packet = Packet::new();
+        packet.push_vec(nbt.clone());
+        packet.encode_identifier("minecraft:the_nether");
+        packet.encode_long(987654321);
+        packet.encode_ubyte(0);
+        packet.encode_byte(1);
+        packet.encode_bool(false);
+        packet.encode_bool(false);
+        packet.encode_bool(true);
+        fixtures.push((Fid::Respawn, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_angle(100);
+        fixtures.push((Fid::EntityHeadLook, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_long(0x0000_0400_0010_0004);
+        packet.encode_bool(false);
+        packet.encode_varint(2);
+        packet.encode_varlong(1 << 12 | 0x123);
+        packet.encode_varlong(9 << 12);
+        fixtures.push((Fid::MultiBlockChange, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_bool(true);
+        packet.encode_identifier("minecraft:story/root");
+        fixtures.push((Fid::SelectAdvancementTab, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(3);
+        packet.encode_double(0.0);
+        packet.encode_double(0.0);
+        packet.encode_double(100.0);
+        packet.encode_double(50.0);
+        packet.encode_varlong(60000);
+        packet.encode_varint(29999984);
+        packet.encode_varint(15);
+        packet.encode_varint(5);
+        fixtures.push((Fid::WorldBorder, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        fixtures.push((Fid::Camera, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(4);
+        fixtures.push((Fid::HeldItemChangeClientbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(-3);
+        packet.encode_varint(7);
+        fixtures.push((Fid::UpdateViewPosition, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(12);
+        fixtures.push((Fid::UpdateViewDistance, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((0, 64, 0));
+        fixtures.push((Fid::SpawnPosition, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(1);
+        packet.encode_string("deaths");
+        fixtures.push((Fid::DisplayScoreboard, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        // Crouching, as a flag and as the pose.
+        packet.push_vec(vec![0x00, 0x00, 0x02, 0x06, 0x12, 0x05, 0xff]);
+        fixtures.push((Fid::EntityMetadata, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_int(46);
+        packet.encode_int(-1);
+        fixtures.push((Fid::AttachEntity, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_short(100);
+        packet.encode_short(-100);
+        packet.encode_short(0);
+        fixtures.push((Fid::EntityVelocity, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.push_vec(vec![0x80, 0x01, 0xdb, 0x04, 0x01, 0x00]);
+        packet.push_vec(vec![0x05, 0x00]);
+        fixtures.push((Fid::EntityEquipment, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_float(0.75);
+        packet.encode_varint(30);
+        packet.encode_varint(1395);
+        fixtures.push((Fid::SetExperience, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_float(19.5);
+        packet.encode_varint(18);
+        packet.encode_float(5.0);
+        fixtures.push((Fid::UpdateHealth, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("deaths");
+        packet.encode_byte(0);
+        packet.encode_chat(r#"{"text":"Deaths"}"#);
+        packet.encode_varint(0);
+        fixtures.push((Fid::ScoreboardObjective, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_varint(2);
+        packet.encode_varint(47);
+        packet.encode_varint(48);
+        fixtures.push((Fid::SetPassengers, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("red");
+        packet.encode_byte(0);
+        packet.encode_chat(r#"{"text":"Red"}"#);
+        packet.encode_byte(0x01);
+        packet.encode_string("always");
+        packet.encode_string("pushOtherTeams");
+        packet.encode_varint(12);
+        packet.encode_chat(r#"{"text":"[R] "}"#);
+        packet.encode_chat(r#"{"text":""}"#);
+        packet.encode_varint(2);
+        packet.encode_string("Notch");
+        packet.encode_string("jeb_");
+        fixtures.push((Fid::Teams, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("Notch");
+        packet.encode_byte(0);
+        packet.encode_string("deaths");
+        packet.encode_varint(3);
+        fixtures.push((Fid::UpdateScore, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_long(123456);
+        packet.encode_long(-6000);
+        fixtures.push((Fid::TimeUpdate, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(3);
+        packet.encode_int(10);
+        packet.encode_int(70);
+        packet.encode_int(20);
+        fixtures.push((Fid::Title, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(300);
+        packet.encode_varint(6);
+        packet.encode_varint(46);
+        packet.encode_float(1.0);
+        packet.encode_float(1.0);
+        fixtures.push((Fid::EntitySoundEffect, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(300);
+        packet.encode_varint(0);
+        packet.encode_int(8);
+        packet.encode_int(512);
+        packet.encode_int(-8);
+        packet.encode_float(0.5);
+        packet.encode_float(1.5);
+        fixtures.push((Fid::SoundEffect, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(0x03);
+        packet.encode_varint(2);
+        packet.encode_identifier("minecraft:music.game");
+        fixtures.push((Fid::StopSound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_chat(r#"{"text":"Header"}"#);
+        packet.encode_chat(r#"{"text":"Footer"}"#);
+        fixtures.push((Fid::PlayerListHeaderAndFooter, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(4);
+        packet.push_vec(nbt.clone());
+        fixtures.push((Fid::NbtQueryResponse, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(47);
+        packet.encode_varint(46);
+        packet.encode_varint(3);
+        fixtures.push((Fid::CollectItem, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_double(100.5);
+        packet.encode_double(70.0);
+        packet.encode_double(-100.5);
+        packet.encode_angle(0);
+        packet.encode_angle(255);
+        packet.encode_bool(true);
+        fixtures.push((Fid::EntityTeleport, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_bool(true);
+        packet.push_vec(vec![0x00, 0x00, 0x00]);
+        fixtures.push((Fid::Advancements, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_int(2);
+        packet.encode_identifier("generic.max_health");
+        packet.encode_double(20.0);
+        packet.encode_varint(0);
+        packet.encode_identifier("generic.movement_speed");
+        packet.encode_double(0.1);
+        packet.encode_varint(1);
+        packet.encode_uuid(0x662a6b8dda3e4c1c88186e7ee9290312);
+        packet.encode_double(0.3);
+        packet.encode_byte(2);
+        fixtures.push((Fid::EntityProperties, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_byte(1);
+        packet.encode_byte(0);
+        packet.encode_varint(600);
+        packet.encode_byte(0x02);
+        fixtures.push((Fid::EntityEffect, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        fixtures.push((Fid::DeclareRecipes, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(1);
+        packet.encode_identifier("minecraft:logs");
+        packet.encode_varint(2);
+        packet.encode_varint(35);
+        packet.encode_varint(36);
+        packet.encode_varint(0);
+        packet.encode_varint(1);
+        packet.encode_identifier("minecraft:water");
+        packet.encode_varint(2);
+        packet.encode_varint(1);
+        packet.encode_varint(2);
+        packet.encode_varint(0);
+        fixtures.push((Fid::Tags, packet));
+
+        // Serverbound
+        let mut packet = Packet::new();
+        packet.encode_varint(1);
+        fixtures.push((Fid::TeleportConfirm, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(7);
+        packet.encode_position((10, 64, -10));
+        fixtures.push((Fid::QueryBlockNbt, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(3);
+        fixtures.push((Fid::SetDifficulty, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("Hello");
+        fixtures.push((Fid::ChatMessageServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        fixtures.push((Fid::ClientStatus, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("en_us");
+        packet.encode_byte(12);
+        packet.encode_varint(0);
+        packet.encode_bool(true);
+        packet.encode_ubyte(0x7f);
+        packet.encode_varint(1);
+        fixtures.push((Fid::ClientSettings, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(3);
+        packet.encode_string("/tp ");
+        fixtures.push((Fid::TabCompleteServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(1);
+        packet.encode_short(12);
+        packet.encode_bool(true);
+        fixtures.push((Fid::WindowConfirmationServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(2);
+        packet.encode_byte(1);
+        fixtures.push((Fid::ClickWindowButton, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_ubyte(1);
+        packet.encode_short(36);
+        packet.encode_byte(0);
+        packet.encode_short(5);
+        packet.encode_varint(0);
+        packet.push_vec(vec![0x01, 0x01, 0x40, 0x00]);
+        fixtures.push((Fid::ClickWindow, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_ubyte(1);
+        fixtures.push((Fid::CloseWindowServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_identifier("minecraft:brand");
+        packet.encode_string("vanilla");
+        fixtures.push((Fid::PluginMessageServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.push_vec(vec![0x01, 0x8c, 0x06, 0x01, 0x00, 0x00, 0x00]);
+        fixtures.push((Fid::EditBook, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(8);
+        packet.encode_varint(46);
+        fixtures.push((Fid::QueryEntityNbt, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_varint(2);
+        packet.encode_float(0.5);
+        packet.encode_float(1.0);
+        packet.encode_float(0.25);
+        packet.encode_varint(1);
+        packet.encode_bool(true);
+        fixtures.push((Fid::InteractEntity, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((0, 70, 0));
+        packet.encode_varint(5);
+        packet.encode_bool(false);
+        fixtures.push((Fid::GenerateStructure, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_long(1234);
+        fixtures.push((Fid::KeepAliveServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_bool(true);
+        fixtures.push((Fid::LockDifficulty, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_double(100.5);
+        packet.encode_double(64.0);
+        packet.encode_double(-100.5);
+        packet.encode_bool(true);
+        fixtures.push((Fid::PlayerPosition, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_double(100.5);
+        packet.encode_double(64.0);
+        packet.encode_double(-100.5);
+        packet.encode_float(90.0);
+        packet.encode_float(-45.0);
+        packet.encode_bool(false);
+        fixtures.push((Fid::PlayerPositionAndRotation, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_float(90.0);
+        packet.encode_float(-45.0);
+        packet.encode_bool(true);
+        fixtures.push((Fid::PlayerRotation, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_bool(true);
+        fixtures.push((Fid::PlayerMovement, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_double(10.0);
+        packet.encode_double(62.5);
+        packet.encode_double(-3.0);
+        packet.encode_float(180.0);
+        packet.encode_float(0.0);
+        fixtures.push((Fid::VehicleMoveServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_bool(true);
+        packet.encode_bool(false);
+        fixtures.push((Fid::SteerBoat, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(9);
+        fixtures.push((Fid::PickItem, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(1);
+        packet.encode_identifier("minecraft:oak_planks");
+        packet.encode_bool(true);
+        fixtures.push((Fid::CraftRecipeRequest, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_byte(0x02);
+        fixtures.push((Fid::PlayerAbilitiesServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        packet.encode_position((10, 63, -10));
+        packet.encode_byte(1);
+        fixtures.push((Fid::PlayerDigging, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_varint(3);
+        packet.encode_varint(0);
+        fixtures.push((Fid::EntityAction, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_float(0.0);
+        packet.encode_float(0.98);
+        packet.encode_ubyte(0x01);
+        fixtures.push((Fid::SteerVehicle, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        packet.encode_bool(true);
+        packet.encode_bool(false);
+        fixtures.push((Fid::SetRecipeBookState, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_identifier("minecraft:oak_planks");
+        fixtures.push((Fid::SetDisplayedRecipe, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_string("Sword");
+        fixtures.push((Fid::NameItem, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(3);
+        fixtures.push((Fid::ResourcePackStatus, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        packet.encode_identifier("minecraft:story/root");
+        fixtures.push((Fid::AdvancementTab, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(2);
+        fixtures.push((Fid::SelectTrade, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(1);
+        packet.encode_varint(10);
+        fixtures.push((Fid::SetBeaconEffect, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_short(4);
+        fixtures.push((Fid::HeldItemChangeServerbound, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((1, 2, 3));
+        packet.encode_string("say hi");
+        packet.encode_varint(2);
+        packet.encode_byte(0x04);
+        fixtures.push((Fid::UpdateCommandBlock, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(46);
+        packet.encode_string("say hi");
+        packet.encode_bool(true);
+        fixtures.push((Fid::UpdateCommandBlockMinecart, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_short(36);
+        packet.push_vec(vec![0x01, 0x01, 0x40, 0x00]);
+        fixtures.push((Fid::CreativeInventoryAction, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((1, 2, 3));
+        packet.encode_identifier("minecraft:start");
+        packet.encode_identifier("minecraft:bottom");
+        packet.encode_identifier("minecraft:village/plains/streets");
+        packet.encode_string("minecraft:structure_void");
+        packet.encode_string("rollable");
+        fixtures.push((Fid::UpdateJigsawBlock, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((1, 2, 3));
+        packet.encode_varint(1);
+        packet.encode_varint(0);
+        packet.encode_string("minecraft:house");
+        packet.encode_byte(0);
+        packet.encode_byte(1);
+        packet.encode_byte(0);
+        packet.encode_byte(5);
+        packet.encode_byte(4);
+        packet.encode_byte(5);
+        packet.encode_varint(0);
+        packet.encode_varint(1);
+        packet.encode_string("");
+        packet.encode_float(1.0);
+        packet.encode_varlong(-1);
+        packet.encode_byte(0x04);
+        fixtures.push((Fid::UpdateStructureBlock, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_position((1, 2, 3));
+        packet.encode_string("Welcome");
+        packet.encode_string("to");
+        packet.encode_string("the");
+        packet.encode_string("server");
+        fixtures.push((Fid::UpdateSign, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        fixtures.push((Fid::Animation, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_uuid(0x069a79f444e94726a5befca90e38aaf5);
+        fixtures.push((Fid::Spectate, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(0);
+        packet.encode_position((10, 63, -10));
+        packet.encode_varint(1);
+        packet.encode_float(0.5);
+        packet.encode_float(1.0);
+        packet.encode_float(0.5);
+        packet.encode_bool(false);
+        fixtures.push((Fid::PlayerBlockPlacement, packet));
+
+        let mut packet = Packet::new();
+        packet.encode_varint(1);
+        fixtures.push((Fid::UseItem, packet));
+
+        fixtures
+    }
+
+    #[test]
+    fn test_parse_serialize_roundtrip() {
+        let functions = get_functions();
+        let fixtures = fixtures();
+        for id in functions.list.keys() {
+            assert!(
+                fixtures.iter().any(|(fid, _)| fid == id),
+                "No fixture for {}",
+                id
+            );
+        }
+        for (id, packet) in fixtures {
+            let expected = packet.get_vec();
+            let mut parser = dyn_clone::clone_box(functions.get(&id).unwrap());
+            parser.parse_packet(packet).unwrap();
+            assert_eq!(
+                parser.serialize(versions::V1_16_5).get_vec(),
+                expected,
+                "{}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_play_printables() {
+        let functions = get_functions();
+        let expected = vec![
+            (Fid::Statistics, "[0 1 5, 8 128 255]"),
+            (
+                Fid::DeclareCommands,
+                "[root -> [1], tp -> [2], <count: brigadier:integer> -> [3], \
+                 <name: brigadier:string> => 0] 0",
+            ),
+            (
+                Fid::PlayerInfo,
+                "AddPlayer [69a79f444e94726a5befca90e38aaf5 Notch [textures e30= c2lnbmF0dXJl] 1 35 None]",
+            ),
+            (
+                Fid::MapData,
+                r#"5 2 true false [0 16 -16 4 None, 9 0 0 8 {"text":"Home"}] 2x1 at 64 32"#,
+            ),
+            (
+                Fid::WorldBorder,
+                "Initialize 0 0 100 50 60000 29999984 15 5",
+            ),
+            (Fid::StopSound, "2 minecraft:music.game"),
+            (Fid::NbtQueryResponse, "4 {x: 5}"),
+            (Fid::WindowItems, "0 2 slots [0: 64x #1]"),
+            (Fid::EntityMetadata, "46 {0: 2, 6: sneaking}"),
+            (Fid::ChunkData, "-3 7 true 101 2 sections, 1 block entities"),
+            (
+                Fid::MultiBlockChange,
+                "1 4 1 false [17 67 18 1, 16 64 16 9]",
+            ),
+            (Fid::TradeList, "2 [3x #687 -> 6x #561] 1 0 true true"),
+            (Fid::EntityEquipment, "46 [MainHand: 1x #603, Helmet: Empty]"),
+            (Fid::ClickWindow, "1 36 0 5 0 64x #1"),
+            (Fid::Tags, "[minecraft:logs [35, 36]] [] [minecraft:water [1, 2]] []"),
+            (Fid::InteractEntity, "46 InteractAt 0.5 1 0.25 1 true"),
+            (Fid::AdvancementTab, "OpenedTab minecraft:story/root"),
+            (Fid::PlayerDigging, "0 10 63 -10 1"),
+            (Fid::Particle, "3 true 1 2 3 0.1 0.2 0.3 0 20 block 1"),
+        ];
+        let mut fixtures = fixtures();
+        for (id, printable) in expected {
+            let index = fixtures.iter().position(|(fid, _)| *fid == id).unwrap();
+            let (_, packet) = fixtures.remove(index);
+            let mut parser = dyn_clone::clone_box(functions.get(&id).unwrap());
+            parser.parse_packet(packet).unwrap();
+            assert_eq!(parser.get_printable(), printable, "{}", id);
+        }
+    }
+
+    #[test]
+    fn test_play_json() {
+        let functions = get_functions();
+        for (id, packet) in fixtures() {
+            let mut parser = dyn_clone::clone_box(functions.get(&id).unwrap());
+            parser.parse_packet(packet).unwrap();
+            let json = parser.to_json(versions::V1_16_5);
+            assert!(json.is_object(), "{}: {}", id, json);
+            if id == Fid::EntityMetadata {
+                assert_eq!(
+                    json["metadata"]["entries"],
+                    serde_json::json!([[0, {"Byte": 2}], [6, {"Pose": 5}]])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_world_updates() {
+        let functions = get_functions();
+        let mut status = Status::new();
+        let fixtures = fixtures();
+        let apply = |status: &mut Status, id: Fid, packet: Packet| {
+            let mut parser = dyn_clone::clone_box(functions.get(&id).unwrap());
+            parser.parse_packet(packet).unwrap();
+            assert!(parser.status_updating());
+            parser.update_status(status).unwrap();
+        };
+        let fixture = |id: Fid| {
+            let (_, packet) = fixtures.iter().find(|(fid, _)| *fid == id).unwrap();
+            let mut copy = Packet::new();
+            copy.push_vec(packet.get_vec());
+            copy
+        };
+
+        apply(&mut status, Fid::UpdateLight, fixture(Fid::UpdateLight));
+        apply(&mut status, Fid::ChunkData, fixture(Fid::ChunkData));
+        assert_eq!(status.world.get_block((-48, 0, 112)), Some(1));
+        assert_eq!(status.world.get_block((-47, 0, 112)), Some(0));
+        assert_eq!(status.world.get_block((-48, 16, 112)), Some(0));
+        assert_eq!(status.world.get_block((0, 0, 0)), None);
+        assert_eq!(status.world.get_light((-48, 0, 112)), (Some(1), None));
+
+        let mut packet = Packet::new();
+        packet.encode_position((-48, 0, 112));
+        packet.encode_varint(9);
+        apply(&mut status, Fid::BlockChange, packet);
+        assert_eq!(status.world.get_block((-48, 0, 112)), Some(9));
+
+        // The sign gets its id from the action, and the position it's at.
+        let mut packet = Packet::new();
+        packet.encode_position((-40, 3, 115));
+        packet.encode_ubyte(9);
+        packet.push_vec(vec![
+            0x0a, 0x00, 0x00, 0x03, 0x00, 0x01, b'x', 0, 0, 0, 5, 0x00,
+        ]);
+        apply(&mut status, Fid::BlockEntityData, packet);
+        assert_eq!(
+            status.world.block_entities[&(-3, 7)][&(-40, 3, 115)].to_string(),
+            r#"{x: -40, id: "minecraft:sign", y: 3, z: 115}"#
+        );
+
+        // The section at -3 2 7, x 22 bits, z 22 bits and y 20 bits.
+        let mut packet = Packet::new();
+        packet.encode_long((-3i64 & 0x3fffff) << 42 | 7 << 20 | 2);
+        packet.encode_bool(false);
+        packet.encode_varint(2);
+        packet.encode_varlong(5 << 12 | 0x0f0);
+        packet.encode_varlong(6 << 12 | 0xf0f);
+        apply(&mut status, Fid::MultiBlockChange, packet);
+        assert_eq!(status.world.get_block((-48, 32, 127)), Some(5));
+        assert_eq!(status.world.get_block((-33, 47, 112)), Some(6));
+
+        let mut packet = Packet::new();
+        packet.encode_int(-3);
+        packet.encode_int(7);
+        apply(&mut status, Fid::UnloadChunk, packet);
+        assert_eq!(status.world.get_block((-48, 0, 112)), None);
+        assert_eq!(status.world.get_light((-48, 0, 112)), (None, None));
+
+        apply(&mut status, Fid::ChunkData, fixture(Fid::ChunkData));
+        apply(&mut status, Fid::Respawn, fixture(Fid::Respawn));
+        assert!(status.world.chunks.is_empty());
+        assert_eq!(status.world.dimension, "minecraft:the_nether");
+    }
+
+    // Fixtures for the packets that look different in older versions.
+    fn old_fixtures() -> Vec<(Fid, Packet)> {
+        let mut fixtures = Vec::new();
+
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        packet.encode_string("069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        packet.encode_string("Notch");
+        fixtures.push((Fid::LoginSuccess, packet));
+
+        let mut packet = Packet::with_version(versions::V1_13);
+        packet.encode_varint(42);
+        packet.encode_uuid(1);
+        packet.encode_byte(60);
+        packet.encode_double(-10.5);
+        packet.encode_double(64.0);
+        packet.encode_double(1e9);
+        packet.encode_angle(12);
+        packet.encode_angle(250);
+        packet.encode_int(-1);
+        packet.encode_short(-800);
+        packet.encode_short(0);
+        packet.encode_short(800);
+        fixtures.push((Fid::SpawnEntity, packet));
+
+        let mut packet = Packet::with_version(versions::V1_14);
+        packet.encode_varint(44);
+        packet.encode_uuid(u128::MAX);
+        packet.encode_varint(95);
+        packet.encode_double(0.25);
+        packet.encode_double(-0.25);
+        packet.encode_double(1.0);
+        packet.encode_angle(1);
+        packet.encode_angle(2);
+        packet.encode_angle(3);
+        packet.encode_short(i16::MIN);
+        packet.encode_short(i16::MAX);
+        packet.encode_short(5);
+        packet.push_vec(vec![0x00, 0x00, 0x00, 0xff]);
+        fixtures.push((Fid::SpawnLivingEntity, packet));
+
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        packet.encode_varint(45);
+        packet.encode_uuid(0xabcdef);
+        packet.encode_string("Kebab");
+        packet.encode_position((-5, 3, -7));
+        packet.encode_ubyte(0x03);
+        fixtures.push((Fid::SpawnPainting, packet));
+
+        let mut packet = Packet::with_version(versions::V1_14);
+        packet.encode_varint(46);
+        packet.encode_uuid(0x069a79f444e94726a5befca90e38aaf5);
+        packet.encode_double(100.5);
+        packet.encode_double(70.0);
+        packet.encode_double(-100.5);
+        packet.encode_angle(128);
+        packet.encode_angle(64);
+        packet.push_vec(vec![0x00, 0x00, 0x00, 0xff]);
+        fixtures.push((Fid::SpawnPlayer, packet));
+
+        fixtures
+    }
+
+    #[test]
+    fn test_old_versions() {
+        let functions = get_functions();
+        for (id, packet) in old_fixtures() {
+            let version = packet.protocol_version();
+            let expected = packet.get_vec();
+            let mut parser = dyn_clone::clone_box(functions.get(&id).unwrap());
+            parser.parse_packet(packet).unwrap();
+            assert_eq!(parser.serialize(version).get_vec(), expected, "{}", id);
+        }
+
+        let mut parser = clientbound::play::SpawnPlayer::empty();
+        let (_, packet) = old_fixtures().pop().unwrap();
+        parser.parse_packet(packet).unwrap();
+        assert_eq!(parser.metadata.entries, vec![(0, Value::Byte(0))]);
+        assert_eq!(parser.pitch, 64);
+
+        let mut parser = clientbound::login::LoginSuccess::empty();
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        packet.encode_string("069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        packet.encode_string("Notch");
+        parser.parse_packet(packet).unwrap();
+        assert_eq!(parser.uuid, 0x069a79f444e94726a5befca90e38aaf5);
+        // Without dashes it's still sent on the way it came.
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        packet.encode_string("069A79F444E94726A5BEFCA90E38AAF5");
+        packet.encode_string("Notch");
+        let expected = packet.get_vec();
+        parser.parse_packet(packet).unwrap();
+        assert_eq!(parser.uuid, 0x069a79f444e94726a5befca90e38aaf5);
+        assert_eq!(parser.serialize(versions::V1_12_2).get_vec(), expected);
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        packet.encode_string("Notch");
+        packet.encode_string("Notch");
+        assert!(parser.parse_packet(packet).is_err());
+    }
+
+    #[test]
+    fn test_schema() {
+        let mut functions = get_functions();
+        functions.add_schema(&Schema::from_toml(include_str!("../packets.toml")).unwrap());
+        let fid = functions.get_name(&Direction::Serverbound, &State::Play, 404, &0x0e);
+        assert_eq!(fid, Some(&Fid::Schema("KeepAliveServerbound".into())));
+        // 1.16.2 and newer parse it in the code.
+        let fid = functions.get_name(&Direction::Serverbound, &State::Play, 754, &0x10);
+        assert_eq!(fid, Some(&Fid::KeepAliveServerbound));
+        let fid = functions.get_name(&Direction::Clientbound, &State::Play, 404, &0x21);
+        assert_eq!(fid, Some(&Fid::Schema("KeepAliveClientbound".into())));
+        assert_eq!(fid.unwrap().to_string(), "KeepAliveClientbound");
+
+        {
+            let mut packet = Packet::new();
+            packet.encode_long(1234);
+            let mut parser = dyn_clone::clone_box(functions.get(fid.unwrap()).unwrap());
+            parser.parse_packet(packet).unwrap();
+            assert_eq!(parser.get_printable(), "1234");
+        }
+
+        // The parsers in the code always win.
+        let schema = Schema::from_toml(
+            r#"
+            [[packets]]
+            name = "SpawnPlayer"
+            direction = "clientbound"
+            state = "play"
+            id = 0x70
+
+            [[packets]]
+            name = "NotSpawnPlayer"
+            direction = "clientbound"
+            state = "play"
+            id = 0x04
+            "#,
+        )
+        .unwrap();
+        functions.add_schema(&schema);
+        let fid = functions.get_name(&Direction::Clientbound, &State::Play, 754, &0x04);
+        assert_eq!(fid, Some(&Fid::SpawnPlayer));
+        let fid = functions.get_name(&Direction::Clientbound, &State::Play, 754, &0x70);
+        assert_eq!(fid, None);
+    }
+
+    #[test]
+    fn test_version_ids() {
+        let functions = get_functions();
+        let values = vec![
+            (versions::V1_12_2, 0x05, Some(Fid::SpawnPlayer)),
+            (versions::V1_12_2, 0x5c, None),
+            (404, 0x03, Some(Fid::SpawnLivingEntity)),
+            (498, 0x5c, Some(Fid::AckPlayerDigging)),
+            (578, 0x08, Some(Fid::AckPlayerDigging)),
+            (versions::V1_16, 0x07, Some(Fid::AckPlayerDigging)),
+            (versions::V1_16_5, 0x04, Some(Fid::SpawnPlayer)),
+            (versions::V1_16_2, 0x24, Some(Fid::JoinGame)),
+            (versions::V1_16_5, 0x5b, Some(Fid::Tags)),
+            (versions::V1_16, 0x24, None),
+            // Unsupported versions are parsed like the newest one.
+            (47, 0x04, Some(Fid::SpawnPlayer)),
+            (10000, 0x04, Some(Fid::SpawnPlayer)),
+        ];
+        for (version, pid, fid) in values {
+            assert_eq!(
+                functions.get_name(&Direction::Clientbound, &State::Play, version, &pid),
+                fid.as_ref(),
+                "{} {:#x}",
+                version,
+                pid
+            );
+        }
+        // Every ID has to map back to itself.
+        for (version, map) in &functions.maps {
+            for (direction, states) in map {
+                for (state, ids) in states {
+                    for (pid, fid) in ids {
+                        assert_eq!(
+                            functions.get_id(direction, state, *version, fid),
+                            Some(*pid)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Feeds every parser random and mangled packets. Parsing is allowed to fail, but never to panic,
+    // and whatever did parse has to be printable and serializable again.
+    #[test]
+    fn test_fuzz_parsers() {
+        let functions = get_functions();
+        let mut rng = crate::utils::Rng::new(0xc0ffee);
+        let versions = [
+            versions::V1_12_2,
+            versions::V1_13,
+            versions::V1_14,
+            versions::V1_15,
+            versions::V1_16,
+            versions::V1_16_5,
+        ];
+        for (id, packet) in fixtures().into_iter().chain(old_fixtures()) {
+            let fixture = packet.get_vec();
+            for _ in 0..2000 {
+                let mut data = fixture.clone();
+                match rng.below(4) {
+                    0 => {
+                        let length = rng.below(64);
+                        data = rng.bytes(length);
+                    }
+                    1 => data.truncate(rng.below(data.len() + 1)),
+                    2 => {
+                        for _ in 0..=rng.below(4) {
+                            if !data.is_empty() {
+                                let index = rng.below(data.len());
+                                data[index] = rng.next_u64() as u8;
+                            }
+                        }
+                    }
+                    _ => {
+                        let index = rng.below(data.len() + 1);
+                        let length = rng.below(8);
+                        let extra = rng.bytes(length);
+                        data.splice(index..index, extra);
+                    }
+                }
+                let version = versions[rng.below(versions.len())];
+                let mut packet = Packet::from(data);
+                packet.set_protocol_version(version);
+                let mut parser = dyn_clone::clone_box(functions.get(&id).unwrap());
+                if parser.parse_packet(packet).is_ok() {
+                    parser.get_printable();
+                    parser.serialize(version);
+                    parser.to_json(version);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,236 @@
+use crate::{
+    functions::Fid,
+    mitm::{Keys, MitmHandler},
+    packet::Parsable,
+    types::{ConfigFormat, Direction, State},
+};
+use std::sync::Arc;
+
+// What should happen to a packet after a handler has seen it.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    // Send it on (with whatever changes the handler made).
+    Pass,
+    // Don't send it on, and don't show it to the handlers after this one.
+    Drop,
+}
+
+// Work that blocks, like asking the session server. It gets the context to inject packets and
+// turn on encryption with, and can still drop the packet.
+pub type Blocking = Box<dyn FnOnce(&mut Context) -> Action + Send>;
+
+// Context is given to every handler, it holds what the handler might need to know about
+// the connection and the packets it wants to send on top of the one it's handling.
+pub struct Context {
+    pub state: State,
+    pub protocol_version: i32,
+    injected: Vec<(Direction, Fid, Box<dyn Parsable + Send>)>,
+    encryption: Option<(Vec<u8>, Option<Vec<u8>>)>,
+    blocking: Vec<Blocking>,
+}
+
+impl Context {
+    pub fn new(state: State, protocol_version: i32) -> Self {
+        Self {
+            state,
+            protocol_version,
+            injected: Vec::new(),
+            encryption: None,
+            blocking: Vec::new(),
+        }
+    }
+
+    // Queues a new packet to be sent in the given direction, after the packet that's being handled.
+    pub fn inject(&mut self, direction: Direction, id: Fid, packet: Box<dyn Parsable + Send>) {
+        self.injected.push((direction, id, packet))
+    }
+
+    pub fn take_injected(&mut self) -> Vec<(Direction, Fid, Box<dyn Parsable + Send>)> {
+        std::mem::take(&mut self.injected)
+    }
+
+    // Turns on encryption with a different key for the client and the server (None if the
+    // server isn't encrypted), right after the packet that's being handled is sent.
+    pub fn enable_encryption(&mut self, client_secret: Vec<u8>, server_secret: Option<Vec<u8>>) {
+        self.encryption = Some((client_secret, server_secret))
+    }
+
+    pub fn take_encryption(&mut self) -> Option<(Vec<u8>, Option<Vec<u8>>)> {
+        self.encryption.take()
+    }
+
+    // Queues work that blocks, it's done after all the handlers had the packet, without them
+    // locked and on a thread that's allowed to block.
+    pub fn block(&mut self, work: Blocking) {
+        self.blocking.push(work)
+    }
+
+    pub fn has_blocking(&self) -> bool {
+        !self.blocking.is_empty()
+    }
+
+    // Does the queued work, the packet is dropped if any of it drops it.
+    pub fn run_blocking(&mut self) -> Action {
+        let mut action = Action::Pass;
+        for work in std::mem::take(&mut self.blocking) {
+            if work(self) == Action::Drop {
+                action = Action::Drop;
+            }
+        }
+        action
+    }
+}
+
+// A handler gets to see (and change) every parsed packet when the proxy is running in active mode.
+// To change a packet, get the actual packet back with `packet.as_any_mut().downcast_mut::<T>()`.
+pub trait Handler: Send {
+    fn handle(
+        &mut self,
+        direction: &Direction,
+        id: &Fid,
+        packet: &mut dyn Parsable,
+        context: &mut Context,
+    ) -> Action;
+}
+
+// The handlers are run one after another, until one of them drops the packet.
+pub struct Chain {
+    handlers: Vec<Box<dyn Handler>>,
+}
+
+impl Chain {
+    pub fn new() -> Self {
+        Self {
+            handlers: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, handler: Box<dyn Handler>) {
+        self.handlers.push(handler)
+    }
+
+    pub fn handle(
+        &mut self,
+        direction: &Direction,
+        id: &Fid,
+        packet: &mut dyn Parsable,
+        context: &mut Context,
+    ) -> Action {
+        for handler in self.handlers.iter_mut() {
+            if handler.handle(direction, id, packet, context) == Action::Drop {
+                return Action::Drop;
+            }
+        }
+        Action::Pass
+    }
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Drops every packet that's in `dropping_packets` in the config file.
+pub struct DropHandler {
+    packets: Vec<String>,
+}
+
+impl Handler for DropHandler {
+    fn handle(
+        &mut self,
+        direction: &Direction,
+        id: &Fid,
+        _packet: &mut dyn Parsable,
+        _context: &mut Context,
+    ) -> Action {
+        if self.packets.contains(&id.to_string()) {
+            log::debug!("{} Dropped {}", direction, id);
+            Action::Drop
+        } else {
+            Action::Pass
+        }
+    }
+}
+
+// Makes the handler chain for a new connection.
+pub fn get_chain(config: &ConfigFormat, keys: Option<Arc<Keys>>) -> Chain {
+    let mut chain = Chain::new();
+    if let (Some(mitm), Some(keys)) = (&config.mitm, keys) {
+        chain.add(Box::new(MitmHandler::new(keys, mitm.clone())));
+    }
+    if !config.dropping_packets.is_empty() {
+        chain.add(Box::new(DropHandler {
+            packets: config.dropping_packets.clone(),
+        }));
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clientbound::play::SpawnXpOrb;
+    use crate::functions::get_functions;
+    use crate::packet::Packet;
+
+    struct DoubleXp;
+
+    impl Handler for DoubleXp {
+        fn handle(
+            &mut self,
+            _direction: &Direction,
+            _id: &Fid,
+            packet: &mut dyn Parsable,
+            context: &mut Context,
+        ) -> Action {
+            if let Some(orb) = packet.as_any_mut().downcast_mut::<SpawnXpOrb>() {
+                orb.count *= 2;
+                let mut copy = orb.clone();
+                copy.entity_id += 1;
+                context.inject(Direction::Clientbound, Fid::SpawnXpOrb, Box::new(copy));
+            }
+            Action::Pass
+        }
+    }
+
+    #[test]
+    fn test_chain() {
+        let functions = get_functions();
+        let mut packet = Packet::new();
+        packet.encode_varint(1);
+        packet.encode_double(0.0);
+        packet.encode_double(64.0);
+        packet.encode_double(0.0);
+        packet.encode_short(5);
+        let mut parsed = dyn_clone::clone_box(functions.get(&Fid::SpawnXpOrb).unwrap());
+        parsed.parse_packet(packet).unwrap();
+
+        let mut chain = Chain::new();
+        chain.add(Box::new(DoubleXp));
+        let mut context = Context::new(State::Play, 754);
+        let action = chain.handle(
+            &Direction::Clientbound,
+            &Fid::SpawnXpOrb,
+            parsed.as_mut(),
+            &mut context,
+        );
+        assert_eq!(action, Action::Pass);
+        let orb = parsed.as_any().downcast_ref::<SpawnXpOrb>().unwrap();
+        assert_eq!(orb.count, 10);
+        let injected = context.take_injected();
+        assert_eq!(injected.len(), 1);
+        assert_eq!(injected[0].1, Fid::SpawnXpOrb);
+
+        chain.add(Box::new(DropHandler {
+            packets: vec!["SpawnXpOrb".into()],
+        }));
+        let action = chain.handle(
+            &Direction::Clientbound,
+            &Fid::SpawnXpOrb,
+            parsed.as_mut(),
+            &mut context,
+        );
+        assert_eq!(action, Action::Drop);
+    }
+}
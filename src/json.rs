@@ -0,0 +1,251 @@
+use crate::types::{Direction, State};
+use crate::utils;
+use parking_lot::Mutex;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The JSON output has a line for every printed packet, like:
+//
+//   {"connection":0,"timestamp":1618000000000,"direction":"clientbound","state":"play",
+//    "packet":"ChatMessageClientbound","fields":{"message":{"text":"hi"},"position":0,...}}
+//
+// The fields are what `Parsable::to_json` gives. It's JSON Lines, so `jq` can read it as it comes.
+#[derive(Serialize)]
+pub struct Record<'a> {
+    pub connection: usize,
+    // When the proxy got it, in ms since the epoch.
+    pub timestamp: u64,
+    pub direction: &'a Direction,
+    pub state: &'a State,
+    pub packet: &'a str,
+    pub fields: Value,
+}
+
+// Where the records go, all connections share it so the lines don't get mixed up.
+pub struct Output {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Output {
+    // `-` is stdout, anything else is a file that's appended to. The log goes to stderr,
+    // so it doesn't get in the way.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let writer: Box<dyn Write + Send> = if path == "-" {
+            Box::new(io::stdout())
+        } else {
+            Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|why| format!("Could not open {}: {}", path, why))?,
+            )
+        };
+        Ok(Self::new(writer))
+    }
+
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    // Every line is flushed right away, so whatever reads it doesn't have to wait.
+    pub fn write(&self, record: &Record) {
+        let mut writer = self.writer.lock();
+        let result = serde_json::to_writer(&mut *writer, record)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush());
+        if let Err(why) = result {
+            log::error!("Could not write JSON output: {}", why);
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Anything that's `Serialize` can be turned into JSON, `Parsable::to_json` uses this by default.
+// It's a trait of its own because `Parsable` has to be a trait object, and `Serialize` can't be one.
+pub trait ToJson {
+    fn json_value(&self) -> Value;
+}
+
+impl<T: Serialize> ToJson for T {
+    fn json_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_else(|why| {
+            log::error!("Could not turn a packet into JSON: {}", why);
+            Value::Null
+        })
+    }
+}
+
+// These are for the types the protocol has that JSON doesn't, fields use them with
+// `#[serde(serialize_with = "json::...")]`.
+
+pub fn uuid<S: Serializer>(uuid: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&utils::format_uuid(*uuid))
+}
+
+pub fn optional_uuid<S: Serializer>(uuid: &Option<u128>, serializer: S) -> Result<S::Ok, S::Error> {
+    match uuid {
+        Some(uuid) => serializer.serialize_some(&utils::format_uuid(*uuid)),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn uuids<S: Serializer>(uuids: &[u128], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(uuids.iter().map(|uuid| utils::format_uuid(*uuid)))
+}
+
+#[derive(Serialize)]
+struct Position {
+    x: i64,
+    y: i64,
+    z: i64,
+}
+
+pub fn position<S: Serializer>(
+    position: &(i64, i64, i64),
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let (x, y, z) = *position;
+    Position { x, y, z }.serialize(serializer)
+}
+
+pub fn optional_position<S: Serializer>(
+    position: &Option<(i64, i64, i64)>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match *position {
+        Some((x, y, z)) => serializer.serialize_some(&Position { x, y, z }),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+pub fn byte_arrays<S: Serializer>(arrays: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(arrays.iter().map(hex::encode))
+}
+
+// Chat is JSON already, it's only kept as a string if it isn't valid.
+pub fn chat<S: Serializer>(chat: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    match serde_json::from_str::<Value>(chat) {
+        Ok(chat) => chat.serialize(serializer),
+        Err(_) => serializer.serialize_str(chat),
+    }
+}
+
+pub fn optional_chat<S: Serializer>(
+    chat: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match chat {
+        Some(chat) => self::chat(chat, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::{self, Fid};
+    use crate::packet::Packet;
+    use std::sync::Arc;
+
+    // Collects what's written, so the test can read it back.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn parse(fid: Fid, protocol_version: i32, payload: Vec<u8>) -> Value {
+        let functions = functions::get_functions();
+        let mut parsed = dyn_clone::clone_box(functions.get(&fid).unwrap());
+        let mut packet = Packet::from(payload);
+        packet.set_protocol_version(protocol_version);
+        parsed.parse_packet(packet).unwrap();
+        parsed.to_json(protocol_version)
+    }
+
+    #[test]
+    fn test_fields() {
+        // A derived packet, with a position and a VarInt.
+        assert_eq!(
+            parse(
+                Fid::BlockBreakAnimation,
+                754,
+                vec![0x05, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x10, 0x02, 0x07]
+            ),
+            serde_json::json!({
+                "entity_id": 5,
+                "location": {"x": 1, "y": 2, "z": 1},
+                "destroy_stage": 7,
+            })
+        );
+        // A hand-written one, with a UUID.
+        let mut payload = vec![0x00; 15];
+        payload.push(0x2a);
+        payload.extend(vec![0x03, b'j', b'e', b'b']);
+        assert_eq!(
+            parse(Fid::LoginSuccess, 754, payload),
+            serde_json::json!({
+                "uuid": "00000000-0000-0000-0000-00000000002a",
+                "username": "jeb",
+            })
+        );
+        let chat = |text: &str| chat(text, serde_json::value::Serializer).unwrap();
+        assert_eq!(chat("{\"text\":\"hi\"}"), serde_json::json!({"text": "hi"}));
+        assert_eq!(chat("hi"), serde_json::json!("hi"));
+    }
+
+    #[test]
+    fn test_output() {
+        let buffer = Buffer::default();
+        let output = Output::new(Box::new(buffer.clone()));
+        for connection in 0..2 {
+            output.write(&Record {
+                connection,
+                timestamp: 1_618_000_000_000,
+                direction: &Direction::Serverbound,
+                state: &State::Play,
+                packet: "KeepAliveServerbound",
+                fields: serde_json::json!({"keep_alive_id": 3}),
+            });
+        }
+        let written = String::from_utf8(buffer.0.lock().clone()).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            serde_json::from_str::<Value>(lines[1]).unwrap(),
+            serde_json::json!({
+                "connection": 1,
+                "timestamp": 1_618_000_000_000u64,
+                "direction": "serverbound",
+                "state": "play",
+                "packet": "KeepAliveServerbound",
+                "fields": {"keep_alive_id": 3},
+            })
+        );
+    }
+}
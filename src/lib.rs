@@ -0,0 +1,29 @@
+pub mod capture;
+pub mod cipher;
+pub mod codec;
+pub mod download;
+pub mod error;
+pub mod functions;
+pub mod handlers;
+pub mod json;
+pub mod metadata;
+pub mod mitm;
+pub mod nbt;
+pub mod secrets;
+pub mod slot;
+pub mod types;
+
+pub mod packet;
+pub mod pcapng;
+pub mod replay;
+pub mod schema;
+pub mod utils;
+pub mod versions;
+pub mod world;
+
+pub mod clientbound;
+pub mod serverbound;
+
+pub use error::{Error, ErrorKind};
+pub use packet::{Packet, Parsable};
+pub use types::{Direction, State, Status};
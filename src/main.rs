@@ -1,6 +1,6 @@
-use miniz_oxide::inflate::decompress_to_vec_zlib;
 use parking_lot::Mutex;
-use std::{io::Write, sync::Arc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
@@ -8,41 +8,63 @@ use tokio::{
         tcp::{OwnedReadHalf, OwnedWriteHalf},
         TcpListener, TcpStream,
     },
+    sync::mpsc,
 };
 
-use colored::*;
-use env_logger::Builder;
-use log::LevelFilter;
-
-type DataQueue = deadqueue::unlimited::Queue<Vec<u8>>;
-
-mod cipher;
-mod functions;
-mod types;
+use mc_proxy::{
+    capture, codec, download, functions, handlers, json, mitm, packet, replay, schema, secrets,
+    types, utils, versions, Direction, Error, Packet, Parsable, Status,
+};
 
-pub mod packet;
-pub mod utils;
+// Every connection gets a number, so they can be told apart in the JSON output.
+static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+type Writer = Arc<tokio::sync::Mutex<OwnedWriteHalf>>;
 
-pub mod clientbound;
-pub mod serverbound;
+// In active mode the parsers send the packets on themselves, these are where they go.
+#[derive(Clone)]
+struct Outputs {
+    clientbound: Writer,
+    serverbound: Writer,
+}
 
-pub use packet::{Packet, Parsable};
-pub use types::{Direction, State, Status};
+impl Outputs {
+    fn get(&self, direction: &Direction) -> &Writer {
+        match direction {
+            Direction::Clientbound => &self.clientbound,
+            Direction::Serverbound => &self.serverbound,
+        }
+    }
+}
 
 // This function starts a loop that parses all the recieved bytes into packets and then handels the packets
 async fn packet_parser(
-    queue: Arc<DataQueue>,
+    mut queue: mpsc::UnboundedReceiver<Vec<u8>>,
     direction: Direction,
     status: Arc<Mutex<Status>>,
     config: &types::ConfigFormat,
-) -> Result<(), ()> {
-    // It initializes a variable that will hold all the not yet parsed data
-    let mut data = packet::Packet::new();
+    outputs: Option<Outputs>,
+    chain: Arc<Mutex<handlers::Chain>>,
+    schema: Option<Arc<schema::Schema>>,
+) -> Result<(), Error> {
+    // It initializes a decoder that will hold all the not yet parsed data
+    let mut decoder = codec::Decoder::new();
     // It then gets the functions that need to be called with each packet ID
-    let functions = functions::get_functions();
+    let mut functions = functions::get_functions();
+    if let Some(schema) = &schema {
+        functions.add_schema(schema);
+    }
+    // Without a parser for the EncResponse the secret is never looked for, so it isn't waited for.
+    let waits_for_secret = direction == Direction::Clientbound && config.parses("EncResponse");
     loop {
-        // It gets a single byte from the queue
-        let new_byte = queue.pop().await;
+        // It gets a single byte from the queue, once the listener is gone the connection is over
+        let new_byte = match queue.recv().await {
+            Some(new_byte) => new_byte,
+            None => return Ok(()),
+        };
+        // What the server sends after an EncRequest is held back until the serverbound side has the secret
+        if waits_for_secret {
+            wait_for_secret(&status).await;
+        }
         // It then decrypts it with the correct cipher
         let new_byte = match direction {
             Direction::Serverbound => status.lock().server_cipher.decrypt(new_byte),
@@ -50,99 +72,337 @@ async fn packet_parser(
         };
 
         // And then adds the byte to the list that still needs to be parsed
-        data.push_vec(new_byte);
+        decoder.push(new_byte);
         // Then it does this loop until the queue is empty or until there is not enough data to parse the next packet.
-        while data.len() > 0 {
-            // It takes a backup of the data before trying to parse anything,
-            // because there is a decent chance that the parsing fails and it needs to be restored.
-            let o_data: Vec<u8> = data.get_vec();
-            // It then starts parsing the packet by seeing the length the next packet will be.
-            let packet_length = match data.decode_varint() {
-                Ok(packet_length) => packet_length,
-                Err(()) => {
-                    data.set(o_data);
-                    break;
-                }
+        loop {
+            // It reads the next packet, if there is no way to find where the next packet starts it stops parsing.
+            let frame = match decoder.next_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(err) => return Err(err.with_context(&direction, &status.lock().state, None)),
             };
-            // If there is enough data to parse the packet, continue else break
-            if (data.len() as i32) < packet_length {
-                data.set(o_data);
-                break;
-            }
-            // It then puts the data in a new object that should be empty at the end.
-            let mut packet = packet::Packet::from(data.read(packet_length as usize).unwrap());
-            // If the packet is compressed, decompress it and put it back in the object.
-            if status.lock().compress > 0 {
-                let data_length = packet.decode_varint()?;
-                if data_length > 0 {
-                    let decompressed_packet = match decompress_to_vec_zlib(&packet.get_vec()) {
-                        Ok(decompressed_packet) => decompressed_packet,
-                        Err(why) => {
-                            log::error!("Decompress error: {:?}", why);
-                            break;
-                        }
-                    };
-                    packet.set(decompressed_packet);
-                } else {
-                    ()
+            // In active mode nothing else sends on what can't be read, so it's kept to send it
+            // on the way it came in.
+            let raw_frame = match outputs {
+                Some(_) => frame.get_vec(),
+                None => Vec::new(),
+            };
+            // If the packet is compressed, decompress it.
+            let compression = status.lock().compress;
+            let mut packet = match codec::decompress(frame, compression) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    let state = status.lock().state.clone();
+                    log::error!("{}", err.with_context(&direction, &state, None));
+                    if !forward_frame(&outputs, &direction, &status, raw_frame).await {
+                        return Ok(());
+                    }
+                    continue;
                 }
-            }
+            };
+            // Packets are parsed (and sent on) the way the version from the handshake has them.
+            let protocol_version = versions::parsing_version(status.lock().protocol_version);
+            packet.set_protocol_version(protocol_version);
             // Get the packet id
-            let packet_id = packet.decode_varint()?;
-
-            // Try to parse the packet with the packet ID, if the id is not found just continue to the next packet
-            let func_name = match functions.get_name(&direction, &status.lock().state, &packet_id) {
-                Some(func_name) => func_name,
-                None => continue,
+            let packet_id = match packet.decode_varint() {
+                Ok(packet_id) => packet_id,
+                Err(err) => {
+                    let state = status.lock().state.clone();
+                    log::error!("{}", err.with_context(&direction, &state, None));
+                    if !forward_frame(&outputs, &direction, &status, raw_frame).await {
+                        return Ok(());
+                    }
+                    continue;
+                }
             };
-            let mut parsed_packet = match functions.get(func_name) {
-                Some(func) => func.clone(),
-                None => continue,
+
+            // It's recorded the way it came in, before the handlers can change it.
+            capture::record(
+                &mut status.lock(),
+                &direction,
+                packet_id,
+                packet.get_slice(),
+            );
+
+            // In active mode the packet has to be sent on, so it keeps a copy in case it can't be parsed.
+            let raw_packet = match outputs {
+                Some(_) => packet.get_vec(),
+                None => Vec::new(),
             };
 
-            if config.parsing_packets.contains(&func_name.to_string())
-                || config.parsing_packets.contains(&"*".to_string())
-            {
-                // It then parses the packet with the found parser
-                match parsed_packet.parse_packet(packet) {
-                    Ok(_) => {
-                        // And prints the parsed packet data (with fancy colours)
-                        let packet_info = parsed_packet.get_printable();
-                        if config.printing_packets.contains(&func_name.to_string())
-                            || config.printing_packets.contains(&"*".to_string())
-                        {
-                            log::info!(
-                                "{} [{}]{3:4$} {}",
-                                direction.to_string().yellow(),
-                                func_name.to_string().blue(),
-                                packet_info,
-                                "",
-                                20 - func_name.to_string().len()
-                            )
+            // Try to parse the packet with the packet ID, if the id is not found it's not parsed
+            let func_name = functions.get_name(
+                &direction,
+                &status.lock().state,
+                protocol_version,
+                &packet_id,
+            );
+            let mut dropped = false;
+            let mut injected = Vec::new();
+            let mut encryption = None;
+            // What the packet serializes to before the handlers get it, to tell if they changed it.
+            let mut serialized = Vec::new();
+            let parser = func_name
+                .filter(|f| config.parses(&f.to_string()))
+                .and_then(|f| functions.get(f))
+                .map(dyn_clone::clone_box);
+            let parsed_packet = match (func_name, parser) {
+                (Some(func_name), Some(mut parsed_packet)) => {
+                    // It then parses the packet with the found parser
+                    match packet::parse(parsed_packet.as_mut(), packet) {
+                        Ok(body) => {
+                            serialized = body;
+                            // And prints the parsed packet data
+                            if config.prints(&func_name.to_string()) {
+                                utils::log_packet(
+                                    &direction,
+                                    &func_name.to_string(),
+                                    &parsed_packet.get_printable(),
+                                );
+                                let (output, connection, state) = {
+                                    let status = status.lock();
+                                    (status.json.clone(), status.connection, status.state.clone())
+                                };
+                                if let Some(output) = output {
+                                    output.write(&json::Record {
+                                        connection,
+                                        timestamp: json::now(),
+                                        direction: &direction,
+                                        state: &state,
+                                        packet: &func_name.to_string(),
+                                        fields: parsed_packet.to_json(protocol_version),
+                                    });
+                                }
+                            }
+                            // In active mode it lets the handlers change, drop or add packets
+                            if outputs.is_some() {
+                                let mut context = {
+                                    let status = status.lock();
+                                    handlers::Context::new(
+                                        status.state.clone(),
+                                        status.protocol_version,
+                                    )
+                                };
+                                dropped = chain.lock().handle(
+                                    &direction,
+                                    func_name,
+                                    parsed_packet.as_mut(),
+                                    &mut context,
+                                ) == handlers::Action::Drop;
+                                // What blocks is done once the chain is unlocked, so the other
+                                // direction and the other connections can use it meanwhile.
+                                if context.has_blocking() {
+                                    let blocking = tokio::task::spawn_blocking(move || {
+                                        (context.run_blocking(), context)
+                                    });
+                                    let action;
+                                    (action, context) = match blocking.await {
+                                        Ok(done) => done,
+                                        Err(why) => {
+                                            log::error!("A handler failed: {}", why);
+                                            return Ok(());
+                                        }
+                                    };
+                                    dropped |= action == handlers::Action::Drop;
+                                }
+                                injected = context.take_injected();
+                                encryption = context.take_encryption();
+                            }
+                            Some((func_name, parsed_packet))
+                        }
+                        Err(err) => {
+                            // If it can't parse the packet just log why and move on
+                            let state = status.lock().state.clone();
+                            log::error!(
+                                "Could not parse packet: {}",
+                                err.with_context(&direction, &state, Some(func_name.to_string()))
+                            );
+                            None
                         }
                     }
-                    Err(_) => {
-                        // If it can't parse the packet just fail and move on
-                        log::error!("Could not parse packet!");
-                        continue;
+                }
+                _ => None,
+            };
+
+            let outputs = match &outputs {
+                Some(outputs) => outputs,
+                None => {
+                    // It then updates the status if needed
+                    if let Some((func_name, parsed_packet)) = &parsed_packet {
+                        update_status(parsed_packet.as_ref(), func_name, &direction, &status);
                     }
+                    continue;
+                }
+            };
+
+            // Packets are sent on the way they came in, unless a handler changed them. Older versions
+            // are parsed as a newer one, so serializing what wasn't changed could still change it.
+            let body = match &parsed_packet {
+                Some((_, parsed_packet)) => {
+                    let body = parsed_packet.serialize(protocol_version).get_vec();
+                    if body != serialized {
+                        body
+                    } else {
+                        raw_packet
+                    }
+                }
+                None => raw_packet,
+            };
+            let mut writer = outputs.get(&direction).lock().await;
+            // The packet is encoded before the status is updated, because packets like SetCompression
+            // still have to be sent the old way. The status is updated before it's actually sent, so
+            // the answer to it is never read the old way.
+            let bytes = match dropped {
+                true => None,
+                false => Some(codec::encode(
+                    &mut status.lock(),
+                    &direction,
+                    codec::build(packet_id, body),
+                )),
+            };
+            if let Some((func_name, parsed_packet)) = &parsed_packet {
+                update_status(parsed_packet.as_ref(), func_name, &direction, &status);
+            }
+            // The client reads and writes with its own key, the server with the one the proxy made.
+            if let Some((client_secret, server_secret)) = encryption {
+                let mut status = status.lock();
+                status
+                    .client_cipher
+                    .enable_keys(server_secret.as_deref(), Some(&client_secret));
+                status
+                    .server_cipher
+                    .enable_keys(Some(&client_secret), server_secret.as_deref());
+            }
+            if let Some(bytes) = bytes {
+                if let Err(e) = writer.write_all(&bytes).await {
+                    log::error!("failed to write to socket; err = {:?}", e);
+                    return Ok(());
+                }
+            }
+            drop(writer);
+
+            // And then it sends all the packets the handlers added
+            for (inject_direction, id, packet) in injected {
+                let (state, protocol_version) = {
+                    let status = status.lock();
+                    (
+                        status.state.clone(),
+                        versions::parsing_version(status.protocol_version),
+                    )
                 };
-                // It then updates the status if needed
-                if parsed_packet.status_updating() {
-                    parsed_packet.update_status(&mut status.lock()).unwrap()
+                let packet_id =
+                    match functions.get_id(&inject_direction, &state, protocol_version, &id) {
+                        Some(packet_id) => packet_id,
+                        None => {
+                            log::error!("Can't inject {}, it has no ID in the current state", id);
+                            continue;
+                        }
+                    };
+                let mut writer = outputs.get(&inject_direction).lock().await;
+                let bytes = codec::encode(
+                    &mut status.lock(),
+                    &inject_direction,
+                    codec::build(packet_id, packet.serialize(protocol_version).get_vec()),
+                );
+                if let Err(e) = writer.write_all(&bytes).await {
+                    log::error!("failed to write to socket; err = {:?}", e);
+                    return Ok(());
                 }
             }
         }
     }
 }
 
-async fn packet_listener(mut rx: OwnedReadHalf, mut tx: OwnedWriteHalf, queue: Arc<DataQueue>) {
+// Waits until the serverbound side is done with the secret, if it's still being looked for.
+async fn wait_for_secret(status: &Mutex<Status>) {
+    loop {
+        let (waiting, ready) = {
+            let status = status.lock();
+            (status.waits_for_secret(), status.secret_ready.clone())
+        };
+        if !waiting {
+            return;
+        }
+        ready.notified().await;
+    }
+}
+
+// Sends a frame on as it is in active mode, false if the connection is gone.
+async fn forward_frame(
+    outputs: &Option<Outputs>,
+    direction: &Direction,
+    status: &Mutex<Status>,
+    frame: Vec<u8>,
+) -> bool {
+    let outputs = match outputs {
+        Some(outputs) => outputs,
+        None => return true,
+    };
+    let mut writer = outputs.get(direction).lock().await;
+    let bytes = codec::encode_frame(&mut status.lock(), direction, Packet::from(frame));
+    if let Err(e) = writer.write_all(&bytes).await {
+        log::error!("failed to write to socket; err = {:?}", e);
+        return false;
+    }
+    true
+}
+
+// It updates the status if the packet changes it
+fn update_status(
+    parsed_packet: &dyn Parsable,
+    func_name: &functions::Fid,
+    direction: &Direction,
+    status: &Mutex<Status>,
+) {
+    if parsed_packet.status_updating() {
+        let result = parsed_packet
+            .update_status_unlocked(status)
+            .and_then(|_| parsed_packet.update_status(&mut status.lock()));
+        if let Err(err) = result {
+            let state = status.lock().state.clone();
+            log::error!(
+                "Could not update status: {}",
+                err.with_context(direction, &state, Some(func_name.to_string()))
+            );
+        }
+    }
+}
+
+// Saves the chunks that are still loaded, and forgets them so they're only saved once. They're
+// taken out of the status, so the parsers don't wait while they're saved.
+fn save_world(status: &Mutex<Status>) {
+    let (download, world, protocol_version) = {
+        let mut status = status.lock();
+        match status.download.clone() {
+            Some(download) => (download, status.world.take(), status.protocol_version),
+            None => return,
+        }
+    };
+    download.save_world(world, protocol_version);
+}
+
+// Once a parser is done nothing more goes the way it sent, so that side is closed. The other
+// side notices, so the other listener and parser stop as well.
+async fn shutdown(outputs: &Option<Outputs>, direction: &Direction) {
+    if let Some(outputs) = outputs {
+        if let Err(e) = outputs.get(direction).lock().await.shutdown().await {
+            log::debug!("failed to shut down socket; err = {:?}", e);
+        }
+    }
+}
+
+// In active mode there is no tx, because the parser sends on the packets.
+async fn packet_listener(
+    mut rx: OwnedReadHalf,
+    mut tx: Option<OwnedWriteHalf>,
+    queue: mpsc::UnboundedSender<Vec<u8>>,
+) {
     // This makes a buffer to hold all the sent bytes
     let mut buf = [0; 4096];
     loop {
         // It waits for bytes from the rx
         let n = match rx.read(&mut buf).await {
-            Ok(n) if n == 0 => {
+            Ok(0) => {
                 log::warn!("Socket closed");
                 return;
             }
@@ -152,25 +412,44 @@ async fn packet_listener(mut rx: OwnedReadHalf, mut tx: OwnedWriteHalf, queue: A
                 return;
             }
         };
-        // Then adds them to the parsing queue (byte for byte)
-        queue.push(buf[0..n].to_vec());
-        // Then it sends them over to the tx
-        if let Err(e) = tx.write_all(&buf[0..n]).await {
-            log::error!("failed to write to socket; err = {:?}", e);
+        // Then adds them to the parsing queue (byte for byte), if the parser stopped there's no point going on
+        if queue.send(buf[0..n].to_vec()).is_err() {
             return;
         }
+        // Then it sends them over to the tx
+        if let Some(tx) = &mut tx {
+            if let Err(e) = tx.write_all(&buf[0..n]).await {
+                log::error!("failed to write to socket; err = {:?}", e);
+                return;
+            }
+        }
     }
 }
 
 async fn handle_connection(
     client_stream: TcpStream,
     config: types::ConfigFormat,
+    keys: Option<Arc<mitm::Keys>>,
+    secret_source: Option<Arc<dyn secrets::SecretSource>>,
+    schema: Option<Arc<schema::Schema>>,
+    downloader: Option<Arc<download::Downloader>>,
+    json: Option<Arc<json::Output>>,
 ) -> std::io::Result<()> {
-    // It makes two queues that will hold all new packets.
-    let serverbound_queue = Arc::new(DataQueue::new());
-    let clientbound_queue = Arc::new(DataQueue::new());
+    // It makes two queues that will hold all new packets. They're closed when the listener is done.
+    let (serverbound_sender, serverbound_queue) = mpsc::unbounded_channel();
+    let (clientbound_sender, clientbound_queue) = mpsc::unbounded_channel();
     // It also makes a shared status that hold the current state + compression + ciphers
     let status: Arc<Mutex<Status>> = Arc::new(Mutex::new(Status::new()));
+    {
+        let mut status = status.lock();
+        status.mitm = config.mitm.is_some();
+        status.secret_source = secret_source;
+        status.secret_timeout = std::time::Duration::from_millis(config.secret_timeout);
+        status.download = downloader;
+        status.capture = config.capture.as_deref().map(capture::Recorder::new);
+        status.connection = CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+        status.json = json;
+    }
     log::info!("Connecting to {}...", &config.connect_ip);
 
     // This makes the connection to the actual server
@@ -179,38 +458,81 @@ async fn handle_connection(
     let (srx, stx) = server_stream.into_split();
     let (crx, ctx) = client_stream.into_split();
 
+    // In active mode the parsers get the tx halves, so everything they send goes through them.
+    // The MITM has to change packets, so it needs active mode too.
+    let (stx, ctx, outputs) = if config.active_mode || config.mitm.is_some() {
+        let outputs = Outputs {
+            clientbound: Arc::new(tokio::sync::Mutex::new(ctx)),
+            serverbound: Arc::new(tokio::sync::Mutex::new(stx)),
+        };
+        (None, None, Some(outputs))
+    } else {
+        (Some(stx), Some(ctx), None)
+    };
+    // Both directions share the handlers, so they can keep track of the whole connection.
+    let chain = Arc::new(Mutex::new(handlers::get_chain(&config, keys)));
+
     // It then starts a thread listening to new packets for both the tx and rx pairs.
-    let sb_queue = serverbound_queue.clone();
-    tokio::spawn(async move { packet_listener(crx, stx, sb_queue).await });
+    // Once either side is gone, whatever is left of the world is saved and the capture is written out.
+    let sb_status = status.clone();
+    tokio::spawn(async move {
+        packet_listener(crx, stx, serverbound_sender).await;
+        save_world(&sb_status);
+        capture::flush(&mut sb_status.lock());
+    });
 
-    let cb_queue = clientbound_queue.clone();
-    tokio::spawn(async move { packet_listener(srx, ctx, cb_queue).await });
+    let cb_status = status.clone();
+    tokio::spawn(async move {
+        packet_listener(srx, ctx, clientbound_sender).await;
+        save_world(&cb_status);
+        capture::flush(&mut cb_status.lock());
+    });
 
     // It also starts two threads to parse all the new packets both ways
     let c_status = status.clone();
     let c_config = config.clone();
+    let c_outputs = outputs.clone();
+    let c_chain = chain.clone();
+    let c_schema = schema.clone();
+    let c_shutdown = outputs.clone();
     tokio::spawn(async move {
-        packet_parser(
+        if let Err(err) = packet_parser(
             clientbound_queue,
             Direction::Clientbound,
             c_status,
             &c_config,
+            c_outputs,
+            c_chain,
+            c_schema,
         )
         .await
-        .unwrap();
+        {
+            log::error!("Stopped parsing packets: {}", err);
+        }
+        shutdown(&c_shutdown, &Direction::Clientbound).await;
     });
 
     let s_status = status.clone();
     let s_config = config.clone();
+    let s_shutdown = outputs.clone();
+    let s_secret = status.clone();
     tokio::spawn(async move {
-        packet_parser(
+        if let Err(err) = packet_parser(
             serverbound_queue,
             Direction::Serverbound,
             s_status,
             &s_config,
+            outputs,
+            chain,
+            schema,
         )
         .await
-        .unwrap();
+        {
+            log::error!("Stopped parsing packets: {}", err);
+        }
+        // If the client is gone before the secret is there, the clientbound side doesn't wait for it.
+        s_secret.lock().finish_secret();
+        shutdown(&s_shutdown, &Direction::Serverbound).await;
     });
 
     // Then it returns, because this is no longer needed
@@ -219,36 +541,113 @@ async fn handle_connection(
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
-    // Load the logger, it has a fancy format with colours and it's spaced.
-    Builder::from_default_env()
-        .format(|buf, record| {
-            let formatted_level = buf.default_styled_level(record.level());
-            writeln!(buf, "{:<5} {}", formatted_level, record.args())
-        })
-        .filter_level(LevelFilter::Info)
-        .parse_default_env()
-        .init();
+    // Load the logger
+    utils::init_logger();
 
     log::info!("Reading config...");
-    let mut settings = config::Config::default();
-    settings.merge(config::File::with_name("settings")).unwrap();
-
-    let config = match settings.try_into::<types::ConfigFormat>() {
+    let config = match types::ConfigFormat::load("settings") {
         Ok(config) => config,
         Err(err) => {
             panic!("Could not parse config file!\n{}", err)
         }
     };
 
+    // The keypair is made once, it takes a while.
+    let keys = match &config.mitm {
+        Some(mitm) => {
+            if !mitm.offline_backend && (mitm.access_token.is_empty() || mitm.profile.is_empty()) {
+                panic!("The MITM needs an access_token and profile to join online-mode servers");
+            }
+            log::info!("Generating keypair...");
+            match mitm::Keys::generate(mitm::KEY_BITS) {
+                Ok(keys) => Some(Arc::new(keys)),
+                Err(err) => panic!("{}", err),
+            }
+        }
+        None => None,
+    };
+
+    // Without the MITM the secret has to come from somewhere else. If it can't, the proxy
+    // still works for offline-mode servers, so it only logs why.
+    let secret_source = match config.mitm {
+        Some(_) => None,
+        None => match secrets::get_source(&config.secret_source) {
+            Ok(source) => Some(source),
+            Err(err) => {
+                log::error!("Could not set up the secret source: {}", err);
+                None
+            }
+        },
+    };
+
+    // The schema is only read once, a broken one is better found out about right away.
+    let schema = config.packet_schema.as_ref().map(|path| {
+        log::info!("Reading packet schema...");
+        match schema::Schema::load(path) {
+            Ok(schema) => Arc::new(schema),
+            Err(err) => panic!("{}", err),
+        }
+    });
+
+    // The block report is big, so it's only read once too.
+    let downloader = config.download.as_ref().map(|download| {
+        log::info!("Reading block report...");
+        match download::BlockRegistry::load(&download.blocks) {
+            Ok(blocks) => Arc::new(download::Downloader::new(&download.directory, blocks)),
+            Err(err) => panic!("{}", err),
+        }
+    });
+
+    // All connections write to the same output, so it's opened here.
+    let json = config
+        .json_output
+        .as_ref()
+        .map(|path| match json::Output::open(path) {
+            Ok(output) => Arc::new(output),
+            Err(err) => panic!("{}", err),
+        });
+
     log::info!("Starting listener...");
     // Start listening on `BIND_ADDRESS` for new connections
     let mc_client_listener = TcpListener::bind(&config.listen_ip).await?;
 
+    // In replay mode there's no server, every client gets the recording.
+    if let Some(replay) = &config.replay {
+        let replay = match replay::Replay::load(replay) {
+            Ok(replay) => Arc::new(replay),
+            Err(err) => panic!("{}", err),
+        };
+        log::info!(
+            "Replaying {} packets of {}",
+            replay.capture.entries.len(),
+            versions::name(replay.protocol_version())
+        );
+        loop {
+            let (socket, _) = mc_client_listener.accept().await?;
+            log::info!("Client connected...");
+            let replay = replay.clone();
+            tokio::spawn(async move {
+                if let Err(err) = replay::serve(socket, replay).await {
+                    log::error!("Stopped replaying: {}", err);
+                }
+            });
+        }
+    }
+
     loop {
         // If this continues, a new client is connected.
         let (socket, _) = mc_client_listener.accept().await?;
         log::info!("Client connected...");
         // Start the client-handeling thread (this will complete quickly)
-        handle_connection(socket, config.clone()).await?;
+        handle_connection(
+            socket,
+            config.clone(),
+            keys.clone(),
+            secret_source.clone(),
+            schema.clone(),
+            downloader.clone(),
+            json.clone(),
+        )
+        .await?;
     }
 }
@@ -0,0 +1,521 @@
+use crate::error::{Error, ErrorKind};
+use crate::json;
+use crate::nbt::Nbt;
+use crate::packet::{Field, Packet};
+use crate::slot::Slot;
+use crate::utils;
+use crate::versions;
+use serde::Serialize;
+
+// The value types in the order 1.14 numbers them. 1.13 doesn't have the last three and
+// 1.12.2 doesn't have optional chat or anything after NBT either.
+const OPTIONAL_CHAT: i32 = 5;
+const NBT: i32 = 14;
+const PARTICLE: i32 = 15;
+const POSE: i32 = 18;
+
+// Which type IDs a version knows about, everything after the last one is an error.
+fn last_type(protocol_version: i32) -> i32 {
+    if protocol_version >= versions::V1_14 {
+        POSE
+    } else if protocol_version >= versions::V1_13 {
+        PARTICLE
+    } else {
+        NBT
+    }
+}
+
+// 1.13 added optional chat in the middle, which moved everything after it up by one.
+fn from_wire(type_id: i32, protocol_version: i32) -> i32 {
+    if protocol_version < versions::V1_13 && type_id >= OPTIONAL_CHAT {
+        type_id + 1
+    } else {
+        type_id
+    }
+}
+
+fn to_wire(type_id: i32, protocol_version: i32) -> i32 {
+    if protocol_version < versions::V1_13 && type_id > OPTIONAL_CHAT {
+        type_id - 1
+    } else {
+        type_id
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum ParticleData {
+    #[default]
+    None,
+    // The block state, for block and falling dust particles.
+    Block(i32),
+    Dust {
+        red: f32,
+        green: f32,
+        blue: f32,
+        scale: f32,
+    },
+    Item(Slot),
+}
+
+// A particle, the IDs of the ones with extra data moved around between versions.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Particle {
+    pub id: i32,
+    pub data: ParticleData,
+}
+
+impl Particle {
+    // The IDs of block, dust, falling dust and item.
+    fn data_ids(protocol_version: i32) -> (i32, i32, i32, i32) {
+        if protocol_version >= versions::V1_16 {
+            (3, 14, 23, 34)
+        } else if protocol_version >= versions::V1_14 {
+            (3, 14, 23, 32)
+        } else {
+            (3, 11, 20, 27)
+        }
+    }
+}
+
+impl Field for Particle {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        let id = packet.decode_varint()?;
+        let data = ParticleData::decode(packet, id)?;
+        Ok(Self { id, data })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        packet.encode_varint(self.id);
+        self.data.encode(packet);
+    }
+
+    fn printable(&self) -> String {
+        match &self.data {
+            ParticleData::None => format!("#{}", self.id),
+            data => format!("#{} {}", self.id, data.printable()),
+        }
+    }
+}
+
+// The particle packet has the data apart from the ID, so it's read and written by itself too.
+impl ParticleData {
+    pub fn decode(packet: &mut Packet, id: i32) -> Result<Self, Error> {
+        let (block, dust, falling_dust, item) = Particle::data_ids(packet.protocol_version());
+        Ok(if id == block || id == falling_dust {
+            Self::Block(packet.decode_varint()?)
+        } else if id == dust {
+            Self::Dust {
+                red: packet.decode_float()?,
+                green: packet.decode_float()?,
+                blue: packet.decode_float()?,
+                scale: packet.decode_float()?,
+            }
+        } else if id == item {
+            Self::Item(packet.decode_slot()?)
+        } else {
+            Self::None
+        })
+    }
+
+    pub fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::None => {}
+            Self::Block(state) => packet.encode_varint(*state),
+            Self::Dust {
+                red,
+                green,
+                blue,
+                scale,
+            } => {
+                packet.encode_float(*red);
+                packet.encode_float(*green);
+                packet.encode_float(*blue);
+                packet.encode_float(*scale);
+            }
+            Self::Item(slot) => packet.encode_slot(slot),
+        }
+    }
+
+    pub fn printable(&self) -> String {
+        match self {
+            Self::None => "None".into(),
+            Self::Block(state) => format!("block {}", state),
+            Self::Dust {
+                red,
+                green,
+                blue,
+                scale,
+            } => format!("dust {} {} {} {}", red, green, blue, scale),
+            Self::Item(slot) => format!("item {}", slot.printable()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum Value {
+    Byte(i8),
+    VarInt(i32),
+    Float(f32),
+    String(String),
+    Chat(#[serde(serialize_with = "json::chat")] String),
+    OptionalChat(#[serde(serialize_with = "json::optional_chat")] Option<String>),
+    Slot(Slot),
+    Bool(bool),
+    Rotation(f32, f32, f32),
+    Position(#[serde(serialize_with = "json::position")] (i64, i64, i64)),
+    OptionalPosition(#[serde(serialize_with = "json::optional_position")] Option<(i64, i64, i64)>),
+    Direction(i32),
+    OptionalUuid(#[serde(serialize_with = "json::optional_uuid")] Option<u128>),
+    // 0 (air) means there's no block.
+    OptionalBlockId(i32),
+    Nbt(Nbt),
+    Particle(Particle),
+    VillagerData {
+        villager_type: i32,
+        profession: i32,
+        level: i32,
+    },
+    // Sent as the value plus one, 0 means there's nothing.
+    OptionalVarInt(Option<i32>),
+    Pose(i32),
+}
+
+impl Value {
+    fn type_id(&self) -> i32 {
+        match self {
+            Self::Byte(_) => 0,
+            Self::VarInt(_) => 1,
+            Self::Float(_) => 2,
+            Self::String(_) => 3,
+            Self::Chat(_) => 4,
+            Self::OptionalChat(_) => 5,
+            Self::Slot(_) => 6,
+            Self::Bool(_) => 7,
+            Self::Rotation(..) => 8,
+            Self::Position(_) => 9,
+            Self::OptionalPosition(_) => 10,
+            Self::Direction(_) => 11,
+            Self::OptionalUuid(_) => 12,
+            Self::OptionalBlockId(_) => 13,
+            Self::Nbt(_) => 14,
+            Self::Particle(_) => 15,
+            Self::VillagerData { .. } => 16,
+            Self::OptionalVarInt(_) => 17,
+            Self::Pose(_) => 18,
+        }
+    }
+
+    fn decode(type_id: i32, packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match type_id {
+            0 => Self::Byte(packet.decode_byte()?),
+            1 => Self::VarInt(packet.decode_varint()?),
+            2 => Self::Float(packet.decode_float()?),
+            3 => Self::String(packet.decode_string()?),
+            4 => Self::Chat(packet.decode_chat()?),
+            5 => Self::OptionalChat(match packet.decode_bool()? {
+                true => Some(packet.decode_chat()?),
+                false => None,
+            }),
+            6 => Self::Slot(packet.decode_slot()?),
+            7 => Self::Bool(packet.decode_bool()?),
+            8 => Self::Rotation(
+                packet.decode_float()?,
+                packet.decode_float()?,
+                packet.decode_float()?,
+            ),
+            9 => Self::Position(packet.decode_position()?),
+            10 => Self::OptionalPosition(match packet.decode_bool()? {
+                true => Some(packet.decode_position()?),
+                false => None,
+            }),
+            11 => Self::Direction(packet.decode_varint()?),
+            12 => Self::OptionalUuid(match packet.decode_bool()? {
+                true => Some(packet.decode_uuid()?),
+                false => None,
+            }),
+            13 => Self::OptionalBlockId(packet.decode_varint()?),
+            14 => Self::Nbt(packet.decode_nbt()?),
+            15 => Self::Particle(Particle::decode(packet)?),
+            16 => Self::VillagerData {
+                villager_type: packet.decode_varint()?,
+                profession: packet.decode_varint()?,
+                level: packet.decode_varint()?,
+            },
+            17 => Self::OptionalVarInt(match packet.decode_varint()? {
+                0 => None,
+                value => Some(value - 1),
+            }),
+            _ => Self::Pose(packet.decode_varint()?),
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::Byte(value) => packet.encode_byte(*value),
+            Self::VarInt(value)
+            | Self::Direction(value)
+            | Self::OptionalBlockId(value)
+            | Self::Pose(value) => packet.encode_varint(*value),
+            Self::Float(value) => packet.encode_float(*value),
+            Self::String(value) => packet.encode_string(value),
+            Self::Chat(value) => packet.encode_chat(value),
+            Self::OptionalChat(value) => {
+                packet.encode_bool(value.is_some());
+                if let Some(value) = value {
+                    packet.encode_chat(value);
+                }
+            }
+            Self::Slot(slot) => packet.encode_slot(slot),
+            Self::Bool(value) => packet.encode_bool(*value),
+            Self::Rotation(x, y, z) => {
+                packet.encode_float(*x);
+                packet.encode_float(*y);
+                packet.encode_float(*z);
+            }
+            Self::Position(position) => packet.encode_position(*position),
+            Self::OptionalPosition(position) => {
+                packet.encode_bool(position.is_some());
+                if let Some(position) = position {
+                    packet.encode_position(*position);
+                }
+            }
+            Self::OptionalUuid(uuid) => {
+                packet.encode_bool(uuid.is_some());
+                if let Some(uuid) = uuid {
+                    packet.encode_uuid(*uuid);
+                }
+            }
+            Self::Nbt(nbt) => packet.encode_nbt(nbt),
+            Self::Particle(particle) => particle.encode(packet),
+            Self::VillagerData {
+                villager_type,
+                profession,
+                level,
+            } => {
+                packet.encode_varint(*villager_type);
+                packet.encode_varint(*profession);
+                packet.encode_varint(*level);
+            }
+            Self::OptionalVarInt(value) => packet.encode_varint(value.map_or(0, |value| value + 1)),
+        }
+    }
+
+    fn printable(&self) -> String {
+        let position = |(x, y, z): &(i64, i64, i64)| format!("{} {} {}", x, y, z);
+        match self {
+            Self::Byte(value) => value.to_string(),
+            Self::VarInt(value) => value.to_string(),
+            Self::Float(value) => value.to_string(),
+            Self::String(value) | Self::Chat(value) => value.clone(),
+            Self::OptionalChat(value) => value.clone().unwrap_or_else(|| "None".into()),
+            Self::Slot(slot) => slot.printable(),
+            Self::Bool(value) => value.to_string(),
+            Self::Rotation(x, y, z) => format!("{} {} {}", x, y, z),
+            Self::Position(value) => position(value),
+            Self::OptionalPosition(value) => value.as_ref().map_or("None".into(), position),
+            Self::Direction(value) => match value {
+                0 => "down".into(),
+                1 => "up".into(),
+                2 => "north".into(),
+                3 => "south".into(),
+                4 => "west".into(),
+                5 => "east".into(),
+                value => value.to_string(),
+            },
+            Self::OptionalUuid(value) => value.map_or("None".into(), utils::format_uuid),
+            Self::OptionalBlockId(0) => "None".into(),
+            Self::OptionalBlockId(value) => value.to_string(),
+            Self::Nbt(nbt) => nbt.printable(),
+            Self::Particle(particle) => particle.printable(),
+            Self::VillagerData {
+                villager_type,
+                profession,
+                level,
+            } => format!("{} {} {}", villager_type, profession, level),
+            Self::OptionalVarInt(value) => value.map_or("None".into(), |value| value.to_string()),
+            Self::Pose(value) => match value {
+                0 => "standing".into(),
+                1 => "fall_flying".into(),
+                2 => "sleeping".into(),
+                3 => "swimming".into(),
+                4 => "spin_attack".into(),
+                5 => "sneaking".into(),
+                6 => "dying".into(),
+                value => value.to_string(),
+            },
+        }
+    }
+}
+
+// The entity metadata, a list of indexes with a typed value each. What an index means depends on
+// the entity and the version, so they're only numbers here.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Metadata {
+    pub entries: Vec<(u8, Value)>,
+}
+
+impl Metadata {
+    pub fn get(&self, index: u8) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| *entry == index)
+            .map(|(_, value)| value)
+    }
+}
+
+impl Field for Metadata {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        let protocol_version = packet.protocol_version();
+        let mut entries = Vec::new();
+        loop {
+            // 0xff ends the list.
+            let index = packet.decode_ubyte()?;
+            if index == 0xff {
+                break;
+            }
+            let wire_type = packet.decode_varint()?;
+            let type_id = from_wire(wire_type, protocol_version);
+            if wire_type < 0 || type_id > last_type(protocol_version) {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "metadata type",
+                    value: wire_type as i64,
+                }));
+            }
+            entries.push((index, Value::decode(type_id, packet)?));
+        }
+        Ok(Self { entries })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        let protocol_version = packet.protocol_version();
+        for (index, value) in &self.entries {
+            packet.encode_ubyte(*index);
+            packet.encode_varint(to_wire(value.type_id(), protocol_version));
+            value.encode(packet);
+        }
+        packet.encode_ubyte(0xff);
+    }
+
+    fn printable(&self) -> String {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(index, value)| format!("{}: {}", index, value.printable()))
+            .collect::<Vec<String>>();
+        format!("{{{}}}", entries.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::Tag;
+
+    fn all_values() -> Vec<(u8, Value)> {
+        let item = Slot {
+            present: true,
+            item_id: 1,
+            count: 64,
+            ..Slot::default()
+        };
+        vec![
+            (0, Value::Byte(0x02)),
+            (1, Value::VarInt(300)),
+            (2, Value::Float(1.5)),
+            (3, Value::String("Dinnerbone".into())),
+            (4, Value::Chat(r#"{"text":"Grumm"}"#.into())),
+            (5, Value::OptionalChat(None)),
+            (6, Value::Slot(item.clone())),
+            (7, Value::Bool(true)),
+            (8, Value::Rotation(0.0, 45.0, -90.0)),
+            (9, Value::Position((1, 2, 3))),
+            (10, Value::OptionalPosition(Some((-1, 64, 1)))),
+            (11, Value::Direction(2)),
+            (
+                12,
+                Value::OptionalUuid(Some(0x069a79f444e94726a5befca90e38aaf5)),
+            ),
+            (13, Value::OptionalBlockId(0)),
+            (
+                14,
+                Value::Nbt(Nbt {
+                    name: String::new(),
+                    tag: Some(Tag::Compound(vec![("x".into(), Tag::Int(5))])),
+                }),
+            ),
+            (
+                15,
+                Value::Particle(Particle {
+                    id: 14,
+                    data: ParticleData::Dust {
+                        red: 1.0,
+                        green: 0.0,
+                        blue: 0.5,
+                        scale: 1.0,
+                    },
+                }),
+            ),
+            (
+                16,
+                Value::VillagerData {
+                    villager_type: 2,
+                    profession: 5,
+                    level: 1,
+                },
+            ),
+            (17, Value::OptionalVarInt(Some(0))),
+            (18, Value::Pose(5)),
+            (
+                19,
+                Value::Particle(Particle {
+                    id: 34,
+                    data: ParticleData::Item(item),
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_metadata() {
+        let metadata = Metadata {
+            entries: all_values(),
+        };
+        let mut packet = Packet::new();
+        metadata.encode(&mut packet);
+        assert_eq!(packet.get_slice()[..3], [0x00, 0x00, 0x02]);
+        assert_eq!(packet.get_slice().last(), Some(&0xff));
+        let decoded = Metadata::decode(&mut packet).unwrap();
+        assert!(packet.is_empty());
+        assert_eq!(decoded, metadata);
+        assert_eq!(decoded.get(3), Some(&Value::String("Dinnerbone".into())));
+        assert_eq!(decoded.get(20), None);
+        assert_eq!(
+            decoded.printable(),
+            r#"{0: 2, 1: 300, 2: 1.5, 3: Dinnerbone, 4: {"text":"Grumm"}, 5: None, 6: 64x #1, 7: true, 8: 0 45 -90, 9: 1 2 3, 10: -1 64 1, 11: north, 12: 069a79f4-44e9-4726-a5be-fca90e38aaf5, 13: None, 14: {x: 5}, 15: #14 dust 1 0 0.5 1, 16: 2 5 1, 17: 0, 18: sneaking, 19: #34 item 64x #1}"#
+        );
+
+        // 1.12.2 numbers everything after chat one lower and stops at NBT.
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        packet.push_vec(vec![0x06, 0x06, 0x01, 0x0d, 0x0d, 0x00, 0xff]);
+        let metadata = Metadata::decode(&mut packet).unwrap();
+        assert_eq!(
+            metadata.entries,
+            vec![(6, Value::Bool(true)), (13, Value::Nbt(Nbt::default()))]
+        );
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        metadata.encode(&mut packet);
+        assert_eq!(packet.get_slice()[..5], [0x06, 0x06, 0x01, 0x0d, 0x0d]);
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        packet.push_vec(vec![0x00, 0x0e, 0x00, 0xff]);
+        assert!(Metadata::decode(&mut packet).is_err());
+
+        // 1.13 doesn't have villager data yet, and the item particle has a different ID.
+        let mut packet = Packet::with_version(versions::V1_13);
+        packet.push_vec(vec![0x00, 0x10, 0x00, 0x00, 0x00, 0xff]);
+        assert!(Metadata::decode(&mut packet).is_err());
+        let mut packet = Packet::with_version(versions::V1_13);
+        packet.push_vec(vec![0x00, 0x0f, 0x1b, 0xff, 0xff, 0xff]);
+        let metadata = Metadata::decode(&mut packet).unwrap();
+        assert_eq!(metadata.printable(), "{0: #27 item Empty}");
+    }
+}
@@ -0,0 +1,625 @@
+use crate::{
+    clientbound::login::{Disconnect, EncRequest},
+    functions::Fid,
+    handlers::{Action, Context, Handler},
+    packet::Parsable,
+    serverbound::login::{EncResponse, LoginStart},
+    types::{Direction, MitmConfig},
+};
+use rand::RngCore;
+use rsa::{
+    pkcs8::{DecodePublicKey, EncodePublicKey},
+    Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey,
+};
+use sha1::{Digest, Sha1};
+use std::sync::Arc;
+
+// The size vanilla servers use.
+pub const KEY_BITS: usize = 1024;
+
+// Keys holds the keypair the proxy uses to talk to the client as if it was the server.
+// It's made once, when the proxy starts, and shared by all connections.
+pub struct Keys {
+    private_key: RsaPrivateKey,
+    public_key: Vec<u8>,
+}
+
+impl Keys {
+    pub fn generate(bits: usize) -> Result<Self, String> {
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), bits)
+            .map_err(|why| format!("Could not generate keypair: {}", why))?;
+        let public_key = RsaPublicKey::from(&private_key)
+            .to_public_key_der()
+            .map_err(|why| format!("Could not encode public key: {}", why))?
+            .as_bytes()
+            .to_vec();
+        Ok(Self {
+            private_key,
+            public_key,
+        })
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        self.private_key
+            .decrypt(Pkcs1v15Encrypt, data)
+            .map_err(|why| format!("Could not decrypt: {}", why))
+    }
+}
+
+// Encrypts something with a public key the way it's sent in the protocol (DER encoded).
+pub fn encrypt(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+    RsaPublicKey::from_public_key_der(public_key)
+        .map_err(|why| format!("Invalid public key: {}", why))?
+        .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, data)
+        .map_err(|why| format!("Could not encrypt: {}", why))
+}
+
+// The "server hash" both sides send to the session server. It's a SHA-1 digest printed as
+// a signed (two's complement) number in hex, which is what Java's BigInteger does.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(server_id.as_bytes());
+    hasher.update(shared_secret);
+    hasher.update(public_key);
+    let mut digest: Vec<u8> = hasher.finalize().to_vec();
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        // Two's complement: flip all the bits and add one.
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                let (value, overflow) = byte.overflowing_add(1);
+                *byte = value;
+                carry = overflow;
+            }
+        }
+    }
+    let hex = hex::encode(digest);
+    let hex = hex.trim_start_matches('0');
+    format!("{}{}", if negative { "-" } else { "" }, hex)
+}
+
+// Tells the session server the player is joining the server, this is what the client normally does.
+pub fn join(config: &MitmConfig, server_hash: &str) -> Result<(), String> {
+    let url = format!(
+        "{}/session/minecraft/join",
+        config.session_server.trim_end_matches('/')
+    );
+    let response = ureq::post(&url).send_json(serde_json::json!({
+        "accessToken": config.access_token,
+        "selectedProfile": config.profile.replace('-', ""),
+        "serverId": server_hash,
+    }));
+    match response {
+        Ok(_) => Ok(()),
+        Err(why) => Err(format!("Could not join the server: {}", why)),
+    }
+}
+
+// Asks the session server if the player really joined, this is what the server normally does.
+pub fn has_joined(config: &MitmConfig, username: &str, server_hash: &str) -> Result<bool, String> {
+    let url = format!(
+        "{}/session/minecraft/hasJoined",
+        config.session_server.trim_end_matches('/')
+    );
+    let response = ureq::get(&url)
+        .query("username", username)
+        .query("serverId", server_hash)
+        .call();
+    match response {
+        // It answers with the player's profile if it joined, or with nothing at all if it didn't.
+        Ok(response) => Ok(response.status() == 200),
+        Err(why) => Err(format!("Could not check if the player joined: {}", why)),
+    }
+}
+
+// MitmHandler sits in the login: it answers the client's encryption with its own keypair,
+// and does the encryption with the server itself, so it ends up with a different key for both sides.
+// With `offline_backend` the server never asks for encryption, so the proxy asks the client itself.
+pub struct MitmHandler {
+    keys: Arc<Keys>,
+    config: MitmConfig,
+    username: String,
+    login_start: Option<LoginStart>,
+    server_id: String,
+    server_public_key: Vec<u8>,
+    server_verify_token: Vec<u8>,
+    verify_token: Vec<u8>,
+}
+
+impl MitmHandler {
+    pub fn new(keys: Arc<Keys>, config: MitmConfig) -> Self {
+        Self {
+            keys,
+            config,
+            username: String::new(),
+            login_start: None,
+            server_id: String::new(),
+            server_public_key: Vec::new(),
+            server_verify_token: Vec::new(),
+            verify_token: Vec::new(),
+        }
+    }
+
+    fn new_verify_token(&mut self) -> Vec<u8> {
+        self.verify_token = vec![0; 4];
+        rand::thread_rng().fill_bytes(&mut self.verify_token);
+        self.verify_token.clone()
+    }
+
+    // The LoginStart is held back, and the client gets an EncRequest as if the server sent it.
+    fn handle_login_start(&mut self, packet: &LoginStart, context: &mut Context) {
+        self.login_start = Some(packet.clone());
+        let verify_token = self.new_verify_token();
+        context.inject(
+            Direction::Clientbound,
+            Fid::EncRequest,
+            Box::new(EncRequest {
+                server_id: "".into(),
+                public_key_length: self.keys.public_key.len() as i32,
+                public_key: self.keys.public_key.clone(),
+                verify_token_length: verify_token.len() as i32,
+                verify_token,
+            }),
+        );
+    }
+
+    fn handle_enc_request(&mut self, packet: &mut EncRequest) {
+        self.server_id = packet.server_id.clone();
+        self.server_public_key = packet.public_key.clone();
+        self.server_verify_token = packet.verify_token.clone();
+
+        packet.public_key = self.keys.public_key.clone();
+        packet.public_key_length = packet.public_key.len() as i32;
+        packet.verify_token = self.new_verify_token();
+        packet.verify_token_length = packet.verify_token.len() as i32;
+    }
+
+    // Gets the secret out of the client's EncResponse. Checking the client is who it says it is
+    // blocks, so that's left to `verify_client`.
+    fn client_secret(&self, packet: &EncResponse) -> Result<Vec<u8>, String> {
+        let client_secret = self.keys.decrypt(&packet.shared_secret)?;
+        if self.keys.decrypt(&packet.verify_token)? != self.verify_token {
+            return Err("The client sent back the wrong verify token".into());
+        }
+        if client_secret.len() != 16 {
+            return Err(format!(
+                "The client sent a secret of {} bytes",
+                client_secret.len()
+            ));
+        }
+        Ok(client_secret)
+    }
+
+    // Asks the session server if the client joined, if that's turned on.
+    fn verify_client(&self, client_secret: &[u8]) -> impl FnOnce() -> Result<(), String> {
+        let config = self.config.clone();
+        let username = self.username.clone();
+        let hash = server_hash(&self.server_id, client_secret, &self.keys.public_key);
+        move || {
+            if config.verify_client && !has_joined(&config, &username, &hash)? {
+                return Err(format!("{} didn't join with the session server", username));
+            }
+            Ok(())
+        }
+    }
+
+    // The session server is only asked once the handlers are done, the packet waits for it.
+    fn handle_enc_response(
+        &mut self,
+        packet: &mut EncResponse,
+        context: &mut Context,
+    ) -> Result<(), String> {
+        let client_secret = self.client_secret(packet)?;
+        let verify_client = self.verify_client(&client_secret);
+        let mut server_secret = vec![0; 16];
+        rand::thread_rng().fill_bytes(&mut server_secret);
+        let hash = server_hash(&self.server_id, &server_secret, &self.server_public_key);
+
+        packet.shared_secret = encrypt(&self.server_public_key, &server_secret)?;
+        packet.shared_secret_length = packet.shared_secret.len() as i32;
+        packet.verify_token = encrypt(&self.server_public_key, &self.server_verify_token)?;
+        packet.verify_token_length = packet.verify_token.len() as i32;
+
+        let config = self.config.clone();
+        context.block(Box::new(move |context| {
+            if let Err(why) = verify_client().and_then(|_| join(&config, &hash)) {
+                return disconnect(context, why);
+            }
+            log::debug!(
+                "Client secret {}, server secret {}",
+                hex::encode(&client_secret),
+                hex::encode(&server_secret)
+            );
+            context.enable_encryption(client_secret, Some(server_secret));
+            Action::Pass
+        }));
+        Ok(())
+    }
+
+    // The server doesn't know about the encryption, so it only gets the LoginStart that was held back.
+    fn handle_offline_enc_response(
+        &mut self,
+        packet: &EncResponse,
+        context: &mut Context,
+    ) -> Result<(), String> {
+        let client_secret = self.client_secret(packet)?;
+        let verify_client = self.verify_client(&client_secret);
+        let login_start = match self.login_start.take() {
+            Some(login_start) => login_start,
+            None => return Err("The client sent an EncResponse before logging in".into()),
+        };
+        context.block(Box::new(move |context| {
+            if let Err(why) = verify_client() {
+                return disconnect(context, why);
+            }
+            log::debug!("Client secret {}", hex::encode(&client_secret));
+            context.enable_encryption(client_secret, None);
+            context.inject(
+                Direction::Serverbound,
+                Fid::LoginStart,
+                Box::new(login_start),
+            );
+            Action::Pass
+        }));
+        Ok(())
+    }
+}
+
+// The client can't be told anything useful by the server, so it's kicked with the reason instead.
+fn disconnect(context: &mut Context, why: String) -> Action {
+    log::error!("{}", why);
+    context.inject(
+        Direction::Clientbound,
+        Fid::Disconnect,
+        Box::new(Disconnect {
+            reason: serde_json::json!({ "text": why }).to_string(),
+        }),
+    );
+    Action::Drop
+}
+
+impl Handler for MitmHandler {
+    fn handle(
+        &mut self,
+        _direction: &Direction,
+        id: &Fid,
+        packet: &mut dyn Parsable,
+        context: &mut Context,
+    ) -> Action {
+        match id {
+            Fid::LoginStart => {
+                if let Some(packet) = packet.as_any_mut().downcast_mut::<LoginStart>() {
+                    self.username = packet.username.clone();
+                    if self.config.offline_backend {
+                        self.handle_login_start(packet, context);
+                        return Action::Drop;
+                    }
+                }
+            }
+            Fid::EncRequest if self.config.offline_backend => {
+                return disconnect(
+                    context,
+                    "The server is in online mode, but offline_backend is turned on".into(),
+                );
+            }
+            Fid::EncRequest => {
+                if let Some(packet) = packet.as_any_mut().downcast_mut::<EncRequest>() {
+                    self.handle_enc_request(packet);
+                }
+            }
+            Fid::EncResponse => {
+                if let Some(packet) = packet.as_any_mut().downcast_mut::<EncResponse>() {
+                    if self.config.offline_backend {
+                        return match self.handle_offline_enc_response(packet, context) {
+                            Ok(_) => Action::Drop,
+                            Err(why) => disconnect(context, why),
+                        };
+                    }
+                    if let Err(why) = self.handle_enc_response(packet, context) {
+                        return disconnect(context, why);
+                    }
+                }
+            }
+            _ => (),
+        }
+        Action::Pass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::State;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_server_hash() {
+        // Gotten from wiki.vg, these are hashes of just the name.
+        let values = vec![
+            ("Notch", "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"),
+            ("jeb_", "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"),
+            ("simon", "88e16a1019277b15d58faf0541e11910eb756f6"),
+        ];
+        for (name, hash) in values {
+            assert_eq!(server_hash(name, &[], &[]), hash);
+        }
+    }
+
+    // A session server that answers every request with the given status, and sends back what it got.
+    fn mock_session_server(status: u16) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                request.push_str(&String::from_utf8(body).unwrap());
+                let body = if status == 200 { r#"{"id":"x"}"# } else { "" };
+                write!(
+                    stream,
+                    "HTTP/1.1 {} OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+                sender.send(request).unwrap();
+            }
+        });
+        (address, receiver)
+    }
+
+    #[test]
+    fn test_mitm_login() {
+        let (session_server, requests) = mock_session_server(200);
+        let config = MitmConfig {
+            session_server,
+            access_token: "token".into(),
+            profile: "069a79f4-44e9-4726-a5be-fca90e38aaf5".into(),
+            verify_client: true,
+            offline_backend: false,
+        };
+        // Small keys, so the test doesn't take forever.
+        let proxy_keys = Arc::new(Keys::generate(512).unwrap());
+        let server_keys = Keys::generate(512).unwrap();
+        let mut handler = MitmHandler::new(proxy_keys.clone(), config);
+        let mut context = Context::new(State::Login, 754);
+
+        let mut login_start = LoginStart {
+            username: "Notch".into(),
+        };
+        handler.handle(
+            &Direction::Serverbound,
+            &Fid::LoginStart,
+            &mut login_start,
+            &mut context,
+        );
+
+        let mut request = EncRequest {
+            server_id: "".into(),
+            public_key_length: server_keys.public_key.len() as i32,
+            public_key: server_keys.public_key.clone(),
+            verify_token_length: 4,
+            verify_token: vec![1, 2, 3, 4],
+        };
+        handler.handle(
+            &Direction::Clientbound,
+            &Fid::EncRequest,
+            &mut request,
+            &mut context,
+        );
+        // The client has to get the proxy's key instead of the server's.
+        assert_eq!(request.public_key, proxy_keys.public_key);
+
+        let client_secret = vec![7; 16];
+        let mut response = EncResponse {
+            shared_secret_length: 0,
+            shared_secret: encrypt(&request.public_key, &client_secret).unwrap(),
+            verify_token_length: 0,
+            verify_token: encrypt(&request.public_key, &request.verify_token).unwrap(),
+        };
+        let action = handler.handle(
+            &Direction::Serverbound,
+            &Fid::EncResponse,
+            &mut response,
+            &mut context,
+        );
+        assert_eq!(action, Action::Pass);
+        // The session server isn't asked until the handlers are done.
+        assert_eq!(context.take_encryption(), None);
+        assert_eq!(context.run_blocking(), Action::Pass);
+
+        // The server has to be able to read what the proxy sent it.
+        let server_secret = server_keys.decrypt(&response.shared_secret).unwrap();
+        assert_eq!(
+            server_keys.decrypt(&response.verify_token).unwrap(),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(
+            context.take_encryption(),
+            Some((client_secret.clone(), Some(server_secret.clone())))
+        );
+
+        let has_joined = requests.recv().unwrap();
+        assert!(has_joined.starts_with(&format!(
+            "GET /session/minecraft/hasJoined?username=Notch&serverId={}",
+            server_hash("", &client_secret, &proxy_keys.public_key)
+        )));
+        let join = requests.recv().unwrap();
+        assert!(join.starts_with("POST /session/minecraft/join"));
+        assert!(join.contains(&server_hash("", &server_secret, &server_keys.public_key)));
+        assert!(join.contains(r#""selectedProfile":"069a79f444e94726a5befca90e38aaf5""#));
+    }
+
+    #[test]
+    fn test_mitm_bad_token() {
+        let config = MitmConfig {
+            session_server: "http://127.0.0.1:1".into(),
+            access_token: "token".into(),
+            profile: "069a79f444e94726a5befca90e38aaf5".into(),
+            verify_client: true,
+            offline_backend: false,
+        };
+        let proxy_keys = Arc::new(Keys::generate(512).unwrap());
+        let mut handler = MitmHandler::new(proxy_keys.clone(), config);
+        let mut context = Context::new(State::Login, 754);
+        let mut request = EncRequest {
+            server_id: "".into(),
+            public_key_length: 0,
+            public_key: Vec::new(),
+            verify_token_length: 4,
+            verify_token: vec![1, 2, 3, 4],
+        };
+        handler.handle(
+            &Direction::Clientbound,
+            &Fid::EncRequest,
+            &mut request,
+            &mut context,
+        );
+        let mut response = EncResponse {
+            shared_secret_length: 0,
+            shared_secret: encrypt(&proxy_keys.public_key, &[7; 16]).unwrap(),
+            verify_token_length: 0,
+            verify_token: encrypt(&proxy_keys.public_key, &[0, 0, 0, 0]).unwrap(),
+        };
+        let action = handler.handle(
+            &Direction::Serverbound,
+            &Fid::EncResponse,
+            &mut response,
+            &mut context,
+        );
+        assert_eq!(action, Action::Drop);
+        let injected = context.take_injected();
+        assert_eq!(injected[0].1, Fid::Disconnect);
+        assert_eq!(context.take_encryption(), None);
+    }
+
+    #[test]
+    fn test_offline_backend() {
+        let (session_server, requests) = mock_session_server(200);
+        let config = MitmConfig {
+            session_server,
+            access_token: "".into(),
+            profile: "".into(),
+            verify_client: true,
+            offline_backend: true,
+        };
+        let proxy_keys = Arc::new(Keys::generate(512).unwrap());
+        let mut handler = MitmHandler::new(proxy_keys.clone(), config);
+        let mut context = Context::new(State::Login, 754);
+
+        // The server doesn't get the LoginStart yet, the client gets asked for encryption first.
+        let mut login_start = LoginStart {
+            username: "Notch".into(),
+        };
+        let action = handler.handle(
+            &Direction::Serverbound,
+            &Fid::LoginStart,
+            &mut login_start,
+            &mut context,
+        );
+        assert_eq!(action, Action::Drop);
+        let injected = context.take_injected();
+        assert_eq!(injected[0].0, Direction::Clientbound);
+        let request = injected[0].2.as_any().downcast_ref::<EncRequest>().unwrap();
+        assert_eq!(request.public_key, proxy_keys.public_key);
+
+        let client_secret = vec![7; 16];
+        let mut response = EncResponse {
+            shared_secret_length: 0,
+            shared_secret: encrypt(&request.public_key, &client_secret).unwrap(),
+            verify_token_length: 0,
+            verify_token: encrypt(&request.public_key, &request.verify_token).unwrap(),
+        };
+        let action = handler.handle(
+            &Direction::Serverbound,
+            &Fid::EncResponse,
+            &mut response,
+            &mut context,
+        );
+        assert_eq!(action, Action::Drop);
+        assert_eq!(context.run_blocking(), Action::Pass);
+        // Only the client side is encrypted, and now the server gets the LoginStart.
+        assert_eq!(context.take_encryption(), Some((client_secret, None)));
+        let injected = context.take_injected();
+        assert_eq!(injected[0].0, Direction::Serverbound);
+        assert_eq!(injected[0].1, Fid::LoginStart);
+        let has_joined = requests.recv().unwrap();
+        assert!(has_joined.starts_with("GET /session/minecraft/hasJoined?username=Notch"));
+
+        // An online-mode server can't be used like this.
+        let mut request = EncRequest::empty();
+        let action = handler.handle(
+            &Direction::Clientbound,
+            &Fid::EncRequest,
+            &mut request,
+            &mut context,
+        );
+        assert_eq!(action, Action::Drop);
+        assert_eq!(context.take_injected()[0].1, Fid::Disconnect);
+    }
+
+    #[test]
+    fn test_not_joined() {
+        // The session server doesn't know about the join.
+        let (session_server, _requests) = mock_session_server(204);
+        let config = MitmConfig {
+            session_server,
+            access_token: "".into(),
+            profile: "".into(),
+            verify_client: true,
+            offline_backend: true,
+        };
+        let proxy_keys = Arc::new(Keys::generate(512).unwrap());
+        let mut handler = MitmHandler::new(proxy_keys.clone(), config);
+        let mut context = Context::new(State::Login, 754);
+        let mut login_start = LoginStart {
+            username: "Notch".into(),
+        };
+        handler.handle(
+            &Direction::Serverbound,
+            &Fid::LoginStart,
+            &mut login_start,
+            &mut context,
+        );
+        let injected = context.take_injected();
+        let request = injected[0].2.as_any().downcast_ref::<EncRequest>().unwrap();
+        let mut response = EncResponse {
+            shared_secret_length: 0,
+            shared_secret: encrypt(&request.public_key, &[7; 16]).unwrap(),
+            verify_token_length: 0,
+            verify_token: encrypt(&request.public_key, &request.verify_token).unwrap(),
+        };
+        handler.handle(
+            &Direction::Serverbound,
+            &Fid::EncResponse,
+            &mut response,
+            &mut context,
+        );
+        assert_eq!(context.run_blocking(), Action::Drop);
+        assert_eq!(context.take_encryption(), None);
+        let injected = context.take_injected();
+        assert_eq!(injected.len(), 1);
+        assert_eq!(injected[0].1, Fid::Disconnect);
+    }
+}
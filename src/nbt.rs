@@ -0,0 +1,621 @@
+use crate::error::{Error, ErrorKind};
+use crate::packet::{Field, Packet};
+use serde::{Serialize, Serializer};
+use std::{convert::TryInto, fmt};
+
+// The game refuses anything nested deeper than this, so there's no need to go further.
+pub const MAX_DEPTH: usize = 512;
+
+// One NBT value, the variants are in the order of their tag IDs (TAG_End, 0, has no value).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    // The element tag ID is kept, so an empty list is written back with the one it was sent with.
+    // All elements have to be of that type.
+    List(u8, Vec<Tag>),
+    // The entries are kept in the order they were sent in, for the same reason.
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Byte(_) => 1,
+            Self::Short(_) => 2,
+            Self::Int(_) => 3,
+            Self::Long(_) => 4,
+            Self::Float(_) => 5,
+            Self::Double(_) => 6,
+            Self::ByteArray(_) => 7,
+            Self::String(_) => 8,
+            Self::List(..) => 9,
+            Self::Compound(_) => 10,
+            Self::IntArray(_) => 11,
+            Self::LongArray(_) => 12,
+        }
+    }
+
+    // Looks up an entry of a compound, anything else doesn't have any.
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Self::Compound(entries) => entries
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, tag)| tag),
+            _ => None,
+        }
+    }
+
+    // Sets an entry of a compound, replacing the one with the same name. Other tags stay the same.
+    pub fn insert(&mut self, name: &str, tag: Tag) {
+        if let Self::Compound(entries) = self {
+            match entries.iter_mut().find(|(key, _)| key == name) {
+                Some(entry) => entry.1 = tag,
+                None => entries.push((name.into(), tag)),
+            }
+        }
+    }
+
+    // Numbers are sometimes sent as a different type than expected, this takes any integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Byte(value) => Some(*value as i64),
+            Self::Short(value) => Some(*value as i64),
+            Self::Int(value) => Some(*value as i64),
+            Self::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::Byte(value) => packet.encode_byte(*value),
+            Self::Short(value) => packet.encode_short(*value),
+            Self::Int(value) => packet.encode_int(*value),
+            Self::Long(value) => packet.encode_long(*value),
+            Self::Float(value) => packet.encode_float(*value),
+            Self::Double(value) => packet.encode_double(*value),
+            Self::ByteArray(values) => {
+                packet.encode_int(values.len() as i32);
+                packet.push_vec(values.iter().map(|value| *value as u8).collect());
+            }
+            Self::String(value) => encode_string(packet, value),
+            Self::List(element, values) => {
+                packet.encode_ubyte(*element);
+                packet.encode_int(values.len() as i32);
+                for value in values {
+                    value.encode(packet);
+                }
+            }
+            Self::Compound(entries) => {
+                for (name, value) in entries {
+                    packet.encode_ubyte(value.id());
+                    encode_string(packet, name);
+                    value.encode(packet);
+                }
+                packet.encode_ubyte(0);
+            }
+            Self::IntArray(values) => {
+                packet.encode_int(values.len() as i32);
+                for value in values {
+                    packet.encode_int(*value);
+                }
+            }
+            Self::LongArray(values) => {
+                packet.encode_int(values.len() as i32);
+                for value in values {
+                    packet.encode_long(*value);
+                }
+            }
+        }
+    }
+
+    // Writes the tag as SNBT, the way /data shows it. Compounds and lists that hold them
+    // are spread over multiple lines when `pretty` is set.
+    fn write_snbt(&self, f: &mut fmt::Formatter, pretty: bool, indent: usize) -> fmt::Result {
+        match self {
+            Self::Byte(value) => write!(f, "{}b", value),
+            Self::Short(value) => write!(f, "{}s", value),
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Long(value) => write!(f, "{}L", value),
+            Self::Float(value) => write!(f, "{}f", value),
+            Self::Double(value) => write!(f, "{}d", value),
+            Self::ByteArray(values) => write_array(f, "B", values, "b"),
+            Self::String(value) => write!(f, "{}", quote(value)),
+            Self::List(element, values) => {
+                let spread = pretty && (*element == 9 || *element == 10) && !values.is_empty();
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    if spread {
+                        write!(f, "\n{:1$}", "", (indent + 1) * 4)?;
+                    } else if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    value.write_snbt(f, pretty, indent + 1)?;
+                }
+                if spread {
+                    write!(f, "\n{:1$}", "", indent * 4)?;
+                }
+                write!(f, "]")
+            }
+            Self::Compound(entries) => {
+                let spread = pretty && !entries.is_empty();
+                write!(f, "{{")?;
+                for (index, (name, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    if spread {
+                        write!(f, "\n{:1$}", "", (indent + 1) * 4)?;
+                    } else if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}: ", key(name))?;
+                    value.write_snbt(f, pretty, indent + 1)?;
+                }
+                if spread {
+                    write!(f, "\n{:1$}", "", indent * 4)?;
+                }
+                write!(f, "}}")
+            }
+            Self::IntArray(values) => write_array(f, "I", values, ""),
+            Self::LongArray(values) => write_array(f, "L", values, "L"),
+        }
+    }
+}
+
+// `{:#}` pretty-prints it.
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_snbt(f, f.alternate(), 0)
+    }
+}
+
+fn write_array<T: fmt::Display>(
+    f: &mut fmt::Formatter,
+    kind: &str,
+    values: &[T],
+    suffix: &str,
+) -> fmt::Result {
+    write!(f, "[{};", kind)?;
+    for (index, value) in values.iter().enumerate() {
+        let separator = if index > 0 { "," } else { "" };
+        write!(f, "{} {}{}", separator, value, suffix)?;
+    }
+    write!(f, "]")
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Keys only need quotes when they have something in them that SNBT doesn't allow bare.
+fn key(name: &str) -> String {
+    let bare = |c: char| c.is_ascii_alphanumeric() || "_-.+".contains(c);
+    if !name.is_empty() && name.chars().all(bare) {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+// NBT strings are Java's modified UTF-8: a NUL is two bytes and everything outside of the BMP
+// is sent as two three byte surrogates.
+fn encode_string(packet: &mut Packet, value: &str) {
+    let mut bytes = Vec::with_capacity(value.len());
+    for unit in value.encode_utf16() {
+        match unit {
+            0x01..=0x7f => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | (unit >> 6 & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    packet.encode_ushort(bytes.len() as u16);
+    packet.push_vec(bytes);
+}
+
+fn decode_modified_utf8(bytes: &[u8]) -> Option<String> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    let continuation = |at: usize| match bytes.get(at) {
+        Some(byte) if byte & 0xc0 == 0x80 => Some((byte & 0x3f) as u16),
+        _ => None,
+    };
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let (unit, length) = match byte {
+            0x01..=0x7f => (byte as u16, 1),
+            0xc0..=0xdf => (((byte & 0x1f) as u16) << 6 | continuation(index + 1)?, 2),
+            0xe0..=0xef => (
+                ((byte & 0x0f) as u16) << 12
+                    | continuation(index + 1)? << 6
+                    | continuation(index + 2)?,
+                3,
+            ),
+            _ => return None,
+        };
+        units.push(unit);
+        index += length;
+    }
+    String::from_utf16(&units).ok()
+}
+
+// In JSON compounds become objects and lists and arrays become arrays, so the tag types are lost.
+impl Serialize for Tag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Byte(value) => serializer.serialize_i8(*value),
+            Self::Short(value) => serializer.serialize_i16(*value),
+            Self::Int(value) => serializer.serialize_i32(*value),
+            Self::Long(value) => serializer.serialize_i64(*value),
+            Self::Float(value) => serializer.serialize_f32(*value),
+            Self::Double(value) => serializer.serialize_f64(*value),
+            Self::ByteArray(values) => values.serialize(serializer),
+            Self::String(value) => serializer.serialize_str(value),
+            Self::List(_, values) => values.serialize(serializer),
+            Self::Compound(entries) => {
+                serializer.collect_map(entries.iter().map(|(name, tag)| (name, tag)))
+            }
+            Self::IntArray(values) => values.serialize(serializer),
+            Self::LongArray(values) => values.serialize(serializer),
+        }
+    }
+}
+
+// A named tag, like the game sends it. A lone TAG_End means there's no tag, which is what
+// `tag` being None is. Only the tag is in the JSON, the name is almost always empty.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Nbt {
+    #[serde(skip)]
+    pub name: String,
+    pub tag: Option<Tag>,
+}
+
+impl Nbt {
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_none()
+    }
+}
+
+// Prints the tag as SNBT, `{:#}` pretty-prints it. The name is only there if it has one.
+impl fmt::Display for Nbt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.tag {
+            None => write!(f, "None"),
+            Some(tag) => {
+                if !self.name.is_empty() {
+                    write!(f, "{}: ", key(&self.name))?;
+                }
+                tag.write_snbt(f, f.alternate(), 0)
+            }
+        }
+    }
+}
+
+// Reads from a slice instead of the packet itself, so nothing is taken out of the packet
+// when the NBT turns out to be invalid.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, amount: usize) -> Result<&'a [u8], ErrorKind> {
+        let available = self.data.len() - self.offset;
+        if available < amount {
+            return Err(ErrorKind::NotEnoughBytes {
+                needed: amount,
+                available,
+            });
+        }
+        let bytes = &self.data[self.offset..self.offset + amount];
+        self.offset += amount;
+        Ok(bytes)
+    }
+
+    fn ubyte(&mut self) -> Result<u8, ErrorKind> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn int(&mut self) -> Result<i32, ErrorKind> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn long(&mut self) -> Result<i64, ErrorKind> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    // Reads the length of an array or list with elements of at least `size` bytes. It's checked
+    // against what's left, so nothing gets allocated for lengths that can't be right.
+    fn length(&mut self, size: usize) -> Result<usize, ErrorKind> {
+        let length = self.int()?;
+        let available = self.data.len() - self.offset;
+        if length < 0 {
+            return Err(ErrorKind::InvalidLength {
+                length: length as i64,
+                max: available / size,
+            });
+        }
+        let needed = (length as usize).saturating_mul(size);
+        if needed > available {
+            return Err(ErrorKind::NotEnoughBytes { needed, available });
+        }
+        Ok(length as usize)
+    }
+
+    fn string(&mut self) -> Result<String, ErrorKind> {
+        let length = u16::from_be_bytes(self.take(2)?.try_into().unwrap());
+        let bytes = self.take(length as usize)?;
+        decode_modified_utf8(bytes).ok_or(ErrorKind::BadUtf8)
+    }
+
+    fn tag(&mut self, id: u8, depth: usize) -> Result<Tag, ErrorKind> {
+        if depth > MAX_DEPTH {
+            return Err(ErrorKind::Nbt(format!(
+                "nested deeper than {} levels",
+                MAX_DEPTH
+            )));
+        }
+        Ok(match id {
+            1 => Tag::Byte(self.ubyte()? as i8),
+            2 => Tag::Short(i16::from_be_bytes(self.take(2)?.try_into().unwrap())),
+            3 => Tag::Int(self.int()?),
+            4 => Tag::Long(self.long()?),
+            5 => Tag::Float(f32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+            6 => Tag::Double(f64::from_be_bytes(self.take(8)?.try_into().unwrap())),
+            7 => {
+                let length = self.length(1)?;
+                Tag::ByteArray(self.take(length)?.iter().map(|b| *b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let element = self.ubyte()?;
+                let length = self.length(1)?;
+                // Every element but TAG_End takes at least a byte, a list of those has to be empty.
+                if element == 0 && length > 0 {
+                    return Err(ErrorKind::Nbt("list of TAG_End isn't empty".into()));
+                }
+                let mut values = Vec::with_capacity(length);
+                for _ in 0..length {
+                    values.push(self.tag(element, depth + 1)?);
+                }
+                Tag::List(element, values)
+            }
+            10 => {
+                let mut entries = Vec::new();
+                loop {
+                    let id = self.ubyte()?;
+                    if id == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    entries.push((name, self.tag(id, depth + 1)?));
+                }
+                Tag::Compound(entries)
+            }
+            11 => {
+                let length = self.length(4)?;
+                Tag::IntArray((0..length).map(|_| self.int()).collect::<Result<_, _>>()?)
+            }
+            12 => {
+                let length = self.length(8)?;
+                Tag::LongArray((0..length).map(|_| self.long()).collect::<Result<_, _>>()?)
+            }
+            id => {
+                return Err(ErrorKind::UnknownEnumValue {
+                    name: "NBT tag",
+                    value: id as i64,
+                })
+            }
+        })
+    }
+
+    fn nbt(&mut self) -> Result<Nbt, ErrorKind> {
+        let id = self.ubyte()?;
+        if id == 0 {
+            return Ok(Nbt::default());
+        }
+        let name = self.string()?;
+        let tag = self.tag(id, 0)?;
+        Ok(Nbt {
+            name,
+            tag: Some(tag),
+        })
+    }
+}
+
+impl Field for Nbt {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        let mut reader = Reader {
+            data: packet.get_slice(),
+            offset: 0,
+        };
+        let result = reader.nbt();
+        let length = reader.offset;
+        match result {
+            Ok(nbt) => {
+                packet.read(length)?;
+                Ok(nbt)
+            }
+            Err(kind) => Err(Error::new(kind, packet.offset() + length)),
+        }
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match &self.tag {
+            None => packet.encode_ubyte(0),
+            Some(tag) => {
+                packet.encode_ubyte(tag.id());
+                encode_string(packet, &self.name);
+                tag.encode(packet);
+            }
+        }
+    }
+
+    fn printable(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nbt() {
+        // The "hello world" example from the NBT spec, with a list and an int array added.
+        let mut data = vec![0x0a, 0x00, 0x0b];
+        data.extend(b"hello world");
+        data.extend(vec![0x08, 0x00, 0x04]);
+        data.extend(b"name");
+        data.extend(vec![0x00, 0x09]);
+        data.extend(b"Bananrama");
+        data.extend(vec![0x09, 0x00, 0x01, b'l', 0x03, 0x00, 0x00, 0x00, 0x02]);
+        data.extend(vec![0, 0, 0, 1, 0, 0, 0, 2]);
+        data.extend(vec![
+            0x0b, 0x00, 0x01, b'a', 0x00, 0x00, 0x00, 0x01, 0, 0, 0, 7,
+        ]);
+        data.push(0x00);
+
+        let mut packet = Packet::new();
+        packet.push_vec(data.clone());
+        packet.push_vec(vec![0x00, 0x42]);
+        let nbt = Nbt::decode(&mut packet).unwrap();
+        assert_eq!(nbt.name, "hello world");
+        let tag = nbt.tag.as_ref().unwrap();
+        assert_eq!(tag.get("name"), Some(&Tag::String("Bananrama".into())));
+        assert_eq!(
+            tag.get("l"),
+            Some(&Tag::List(3, vec![Tag::Int(1), Tag::Int(2)]))
+        );
+        assert_eq!(tag.get("a"), Some(&Tag::IntArray(vec![7])));
+        assert_eq!(tag.get("missing"), None);
+        let empty = Nbt::decode(&mut packet).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(packet.get_vec(), vec![0x42]);
+
+        let mut packet = Packet::new();
+        nbt.encode(&mut packet);
+        empty.encode(&mut packet);
+        let mut expected = data.clone();
+        expected.push(0x00);
+        assert_eq!(packet.get_vec(), expected);
+
+        assert_eq!(
+            nbt.printable(),
+            r#""hello world": {name: "Bananrama", l: [1, 2], a: [I; 7]}"#
+        );
+        assert_eq!(empty.printable(), "None");
+
+        // Cut short anywhere, it should fail without reading anything.
+        for length in 1..data.len() {
+            let mut packet = Packet::from(data[..length].to_vec());
+            assert!(Nbt::decode(&mut packet).is_err(), "{}", length);
+            assert_eq!(packet.len(), length);
+        }
+
+        let mut packet = Packet::from(vec![0x09, 0x00, 0x00, 0x00, 0x7f, 0xff, 0xff, 0xff]);
+        assert!(Nbt::decode(&mut packet).is_err());
+        let mut packet = Packet::from(vec![0x0d, 0x00, 0x00]);
+        assert!(Nbt::decode(&mut packet).is_err());
+        let mut packet = Packet::from(vec![0x0c, 0x00, 0x00, 0x7f, 0xff, 0xff, 0xff]);
+        assert!(Nbt::decode(&mut packet).is_err());
+
+        // Lists in lists, deeper than allowed.
+        let mut data = vec![0x09, 0x00, 0x00];
+        for _ in 0..600 {
+            data.extend(vec![0x09, 0x00, 0x00, 0x00, 0x01]);
+        }
+        let mut packet = Packet::from(data);
+        assert!(matches!(
+            Nbt::decode(&mut packet).unwrap_err().kind,
+            ErrorKind::Nbt(_)
+        ));
+    }
+
+    #[test]
+    fn test_nbt_strings() {
+        // A NUL and something outside of the BMP, both of which aren't plain UTF-8 in NBT.
+        let nbt = Nbt {
+            name: "\0".into(),
+            tag: Some(Tag::String("a😀".into())),
+        };
+        let mut packet = Packet::new();
+        nbt.encode(&mut packet);
+        assert_eq!(
+            packet.get_vec(),
+            vec![
+                0x08, 0x00, 0x02, 0xc0, 0x80, 0x00, 0x07, b'a', 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80
+            ]
+        );
+        assert_eq!(Nbt::decode(&mut packet).unwrap(), nbt);
+
+        // A lone surrogate and a cut off character.
+        for bytes in [vec![0xed, 0xa0, 0xbd], vec![b'a', 0xc3]] {
+            let mut packet = Packet::from(vec![0x08, 0x00, 0x00, 0x00, bytes.len() as u8]);
+            packet.push_vec(bytes);
+            assert_eq!(
+                Nbt::decode(&mut packet).unwrap_err().kind,
+                ErrorKind::BadUtf8
+            );
+        }
+    }
+
+    #[test]
+    fn test_snbt() {
+        let tag = Tag::Compound(vec![
+            ("byte".into(), Tag::Byte(-1)),
+            ("short".into(), Tag::Short(2)),
+            ("long".into(), Tag::Long(3)),
+            ("float".into(), Tag::Float(0.5)),
+            ("double".into(), Tag::Double(1.25)),
+            ("bytes".into(), Tag::ByteArray(vec![1, 2])),
+            ("longs".into(), Tag::LongArray(vec![])),
+            ("minecraft:id".into(), Tag::String(r#"say "hi" \o/"#.into())),
+            (
+                "list".into(),
+                Tag::List(10, vec![Tag::Compound(vec![]), Tag::Compound(vec![])]),
+            ),
+            ("empty".into(), Tag::List(0, vec![])),
+        ]);
+        assert_eq!(
+            tag.to_string(),
+            r#"{byte: -1b, short: 2s, long: 3L, float: 0.5f, double: 1.25d, bytes: [B; 1b, 2b], longs: [L;], "minecraft:id": "say \"hi\" \\o/", list: [{}, {}], empty: []}"#
+        );
+
+        let tag = Tag::Compound(vec![
+            ("pos".into(), Tag::List(6, vec![Tag::Double(1.0)])),
+            (
+                "items".into(),
+                Tag::List(
+                    10,
+                    vec![Tag::Compound(vec![("Count".into(), Tag::Byte(1))])],
+                ),
+            ),
+        ]);
+        assert_eq!(
+            format!("{:#}", tag),
+            "{\n    pos: [1d],\n    items: [\n        {\n            Count: 1b\n        }\n    ]\n}"
+        );
+    }
+}
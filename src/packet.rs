@@ -1,20 +1,65 @@
+use crate::error::{Error, ErrorKind};
+use crate::json::ToJson;
+use crate::metadata::Metadata;
+use crate::nbt::Nbt;
+use crate::slot::Slot;
 use crate::types::Status;
+use crate::versions;
 use dyn_clone::DynClone;
-use std::convert::TryInto;
+use parking_lot::Mutex;
+use std::{any::Any, convert::TryInto};
+
+// The longest a packet can be on the wire, the biggest number that fits in a 3 byte VarInt.
+pub const MAX_PACKET_LENGTH: usize = 2097151;
+// The longest a packet can be after it has been decompressed.
+pub const MAX_DATA_LENGTH: usize = 2097152;
+// The longest a string can be, in UTF-16 code units. Each of those can take up to 4 bytes in UTF-8.
+pub const MAX_STRING_LENGTH: usize = 32767;
+
+// The protocol version packets are parsed as when there hasn't been a handshake (1.16.5).
+pub const DEFAULT_PROTOCOL_VERSION: i32 = versions::V1_16_5;
+// 1.14 (from snapshot 18w43a) swapped the y and z fields of a position around.
+pub const POSITION_XZY_PROTOCOL_VERSION: i32 = 442;
 
 // Pakcet holds a raw (unparsed) packet.
 #[derive(Debug)]
 pub struct Packet {
     data: Vec<u8>,
+    // How many bytes have been read since the data was last set, used for error reporting.
+    offset: usize,
+    // Some types are encoded differently depending on the game version.
+    protocol_version: i32,
+}
+
+impl Default for Packet {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Packet {
     pub fn new() -> Packet {
-        Packet { data: Vec::new() }
+        Packet {
+            data: Vec::new(),
+            offset: 0,
+            protocol_version: DEFAULT_PROTOCOL_VERSION,
+        }
+    }
+
+    pub fn with_version(protocol_version: i32) -> Packet {
+        Packet {
+            data: Vec::new(),
+            offset: 0,
+            protocol_version,
+        }
     }
 
     pub fn from(packet_data: Vec<u8>) -> Packet {
-        Packet { data: packet_data }
+        Packet {
+            data: packet_data,
+            offset: 0,
+            protocol_version: DEFAULT_PROTOCOL_VERSION,
+        }
     }
 
     pub fn push(&mut self, data: u8) {
@@ -29,6 +74,10 @@ impl Packet {
         self.data.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     pub fn get_slice(&self) -> &[u8] {
         self.data.as_slice()
     }
@@ -39,155 +88,361 @@ impl Packet {
 
     pub fn clear(&mut self) {
         self.data = Vec::new();
+        self.offset = 0;
+    }
+
+    pub fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    pub fn set_protocol_version(&mut self, protocol_version: i32) {
+        self.protocol_version = protocol_version;
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // Makes an error that points at the current position in the packet.
+    pub fn error(&self, kind: ErrorKind) -> Error {
+        Error::new(kind, self.offset)
     }
 
     // This should never fail unless a wrong packet was delivered.
-    pub fn read(&mut self, amount: usize) -> Result<Vec<u8>, ()> {
+    pub fn read(&mut self, amount: usize) -> Result<Vec<u8>, Error> {
         if self.data.len() < amount {
-            return Err(());
+            return Err(self.error(ErrorKind::NotEnoughBytes {
+                needed: amount,
+                available: self.data.len(),
+            }));
         }
         let to_be_returned = self.data.drain(0..amount);
         let read_value = to_be_returned.collect::<Vec<u8>>();
+        self.offset += amount;
         Ok(read_value)
     }
 
+    // Reads a length that was sent in the packet itself, so it can't be trusted to be positive.
+    pub fn read_array(&mut self, length: i32) -> Result<Vec<u8>, Error> {
+        if length < 0 {
+            return Err(self.error(ErrorKind::InvalidLength {
+                length: length as i64,
+                max: self.data.len(),
+            }));
+        }
+        self.read(length as usize)
+    }
+
+    // Checks the length of an array that was sent in the packet. Every element takes at least one
+    // byte, so there can't be more of them than there are bytes left.
+    pub fn check_count(&self, count: i32) -> Result<usize, Error> {
+        if count < 0 || count as usize > self.data.len() {
+            return Err(self.error(ErrorKind::InvalidLength {
+                length: count as i64,
+                max: self.data.len(),
+            }));
+        }
+        Ok(count as usize)
+    }
+
+    pub fn decode_count(&mut self) -> Result<usize, Error> {
+        let count = self.decode_varint()?;
+        self.check_count(count)
+    }
+
+    // Reads a VarInt length followed by that many values.
+    pub fn decode_array<T: Field>(&mut self) -> Result<Vec<T>, Error> {
+        let count = self.decode_count()?;
+        let mut values = Vec::with_capacity(count);
+        for _ in 0..count {
+            values.push(T::decode(self)?);
+        }
+        Ok(values)
+    }
+
+    pub fn encode_array<T: Field>(&mut self, values: &[T]) {
+        self.encode_varint(values.len() as i32);
+        for value in values {
+            value.encode(self);
+        }
+    }
+
     pub fn set(&mut self, value: Vec<u8>) {
         self.data = value;
+        self.offset = 0;
     }
 
-    pub fn decode_bool(&mut self) -> Result<bool, ()> {
+    pub fn decode_bool(&mut self) -> Result<bool, Error> {
         Ok(match self.read(1)?[0] {
             0x00 => false,
             0x01 => true,
-            _ => return Err(()),
+            value => {
+                return Err(self.error(ErrorKind::UnknownEnumValue {
+                    name: "Boolean",
+                    value: value as i64,
+                }))
+            }
         })
     }
 
-    pub fn decode_byte(&mut self) -> Result<i8, ()> {
+    pub fn decode_byte(&mut self) -> Result<i8, Error> {
         Ok(i8::from_be_bytes(self.read(1)?.try_into().unwrap()))
     }
 
-    pub fn decode_ubyte(&mut self) -> Result<u8, ()> {
+    pub fn decode_ubyte(&mut self) -> Result<u8, Error> {
         Ok(self.read(1)?[0])
     }
 
-    pub fn decode_short(&mut self) -> Result<i16, ()> {
+    pub fn decode_short(&mut self) -> Result<i16, Error> {
         Ok(i16::from_be_bytes(self.read(2)?.try_into().unwrap()))
     }
 
-    pub fn decode_ushort(&mut self) -> Result<u16, ()> {
+    pub fn decode_ushort(&mut self) -> Result<u16, Error> {
         Ok(u16::from_be_bytes(self.read(2)?.try_into().unwrap()))
     }
 
-    pub fn decode_int(&mut self) -> Result<i32, ()> {
+    pub fn decode_int(&mut self) -> Result<i32, Error> {
         Ok(i32::from_be_bytes(self.read(4)?.try_into().unwrap()))
     }
 
-    pub fn decode_long(&mut self) -> Result<i64, ()> {
+    pub fn decode_long(&mut self) -> Result<i64, Error> {
         Ok(i64::from_be_bytes(self.read(8)?.try_into().unwrap()))
     }
 
-    pub fn decode_float(&mut self) -> Result<f32, ()> {
+    pub fn decode_float(&mut self) -> Result<f32, Error> {
         Ok(f32::from_be_bytes(self.read(4)?.try_into().unwrap()))
     }
 
-    pub fn decode_double(&mut self) -> Result<f64, ()> {
+    pub fn decode_double(&mut self) -> Result<f64, Error> {
         Ok(f64::from_be_bytes(self.read(8)?.try_into().unwrap()))
     }
 
-    pub fn decode_string(&mut self) -> Result<String, ()> {
+    pub fn decode_string(&mut self) -> Result<String, Error> {
         let string_length = self.decode_varint()?;
-        return Ok(String::from_utf8(self.read(string_length.try_into().unwrap())?).unwrap());
+        if string_length < 0 || string_length as usize > MAX_STRING_LENGTH * 4 {
+            return Err(self.error(ErrorKind::InvalidLength {
+                length: string_length as i64,
+                max: MAX_STRING_LENGTH * 4,
+            }));
+        }
+        let offset = self.offset;
+        let string = String::from_utf8(self.read(string_length as usize)?)
+            .map_err(|_| Error::new(ErrorKind::BadUtf8, offset))?;
+        let utf16_length = string.encode_utf16().count();
+        if utf16_length > MAX_STRING_LENGTH {
+            return Err(Error::new(
+                ErrorKind::InvalidLength {
+                    length: utf16_length as i64,
+                    max: MAX_STRING_LENGTH,
+                },
+                offset,
+            ));
+        }
+        Ok(string)
     }
 
-    pub fn decode_chat(&mut self) -> Result<String, ()> {
-        Ok(self.decode_string()?)
+    pub fn decode_chat(&mut self) -> Result<String, Error> {
+        self.decode_string()
     }
 
-    pub fn decode_identifier(&mut self) -> Result<String, ()> {
-        Ok(self.decode_string()?)
+    pub fn decode_identifier(&mut self) -> Result<String, Error> {
+        self.decode_string()
     }
 
     // no touchies
-    pub fn decode_varint(&mut self) -> Result<i32, ()> {
+    pub fn decode_varint(&mut self) -> Result<i32, Error> {
         let mut num_read = 0;
         let mut result: i32 = 0;
         let mut read: u8;
         loop {
+            // This is checked before reading, so a too long value can't overflow the shift.
+            if num_read >= 5 {
+                return Err(self.error(ErrorKind::BadVarInt));
+            }
             read = self.read(1)?[0];
             let value: i32 = (read & 0b01111111) as i32;
             result |= value << (7 * num_read);
 
             num_read += 1;
-            if num_read > 5 {
-                return Err(());
-            }
             if (read & 0b10000000) == 0 {
                 break;
             }
         }
-        return Ok(result);
+        Ok(result)
     }
 
     // no touchies either
-    pub fn decode_varlong(&mut self) -> Result<i64, ()> {
+    pub fn decode_varlong(&mut self) -> Result<i64, Error> {
         let mut num_read = 0;
         let mut result: i64 = 0;
         let mut read: u8;
         loop {
+            // This is checked before reading, so a too long value can't overflow the shift.
+            if num_read >= 10 {
+                return Err(self.error(ErrorKind::BadVarInt));
+            }
             read = self.read(1)?[0];
             let value: i64 = (read & 0b01111111) as i64;
             result |= value << (7 * num_read);
 
             num_read += 1;
-            if num_read > 10 {
-                return Err(());
-            }
             if (read & 0b10000000) == 0 {
                 break;
             }
         }
-        return Ok(result);
+        Ok(result)
     }
 
     // Entity Metadata
+    pub fn decode_metadata(&mut self) -> Result<Metadata, Error> {
+        Metadata::decode(self)
+    }
 
     // Slot
+    pub fn decode_slot(&mut self) -> Result<Slot, Error> {
+        Slot::decode(self)
+    }
 
     // NBT Tag
+    pub fn decode_nbt(&mut self) -> Result<Nbt, Error> {
+        Nbt::decode(self)
+    }
 
-    pub fn decode_position(&mut self) -> Result<(i64, i64, i64), ()> {
+    // Positions are packed into a single long, x is always the top 26 bits.
+    // Before 1.14 it's followed by y (12 bits) and then z (26 bits), after that by z and then y.
+    // The fields are sign extended by shifting them to the top and back down again.
+    pub fn decode_position(&mut self) -> Result<(i64, i64, i64), Error> {
         let val = i64::from_be_bytes(self.read(8)?.try_into().unwrap());
-        let mut x = val >> 38;
-        let mut y = val & 0xFFF;
-        let mut z = val << 26 >> 38;
-
-        if x >= 2 ^ 25 {
-            x -= 2 ^ 26
-        }
-        if y >= 2 ^ 11 {
-            y -= 2 ^ 12
-        }
-        if z >= 2 ^ 25 {
-            z -= 2 ^ 26
-        }
+        let x = val >> 38;
+        let (y, z) = if self.protocol_version >= POSITION_XZY_PROTOCOL_VERSION {
+            (val << 52 >> 52, val << 26 >> 38)
+        } else {
+            (val << 26 >> 52, val << 38 >> 38)
+        };
 
         Ok((x, y, z))
     }
 
-    pub fn decode_angle(&mut self) -> Result<u8, ()> {
+    pub fn decode_angle(&mut self) -> Result<u8, Error> {
         Ok(self.read(1)?[0])
     }
 
-    pub fn decode_uuid(&mut self) -> Result<u128, ()> {
+    pub fn decode_uuid(&mut self) -> Result<u128, Error> {
         Ok(u128::from_be_bytes(self.read(16)?.try_into().unwrap()))
     }
+
+    pub fn encode_bool(&mut self, value: bool) {
+        self.push(value as u8)
+    }
+
+    pub fn encode_byte(&mut self, value: i8) {
+        self.push_vec(value.to_be_bytes().to_vec())
+    }
+
+    pub fn encode_ubyte(&mut self, value: u8) {
+        self.push(value)
+    }
+
+    pub fn encode_short(&mut self, value: i16) {
+        self.push_vec(value.to_be_bytes().to_vec())
+    }
+
+    pub fn encode_ushort(&mut self, value: u16) {
+        self.push_vec(value.to_be_bytes().to_vec())
+    }
+
+    pub fn encode_int(&mut self, value: i32) {
+        self.push_vec(value.to_be_bytes().to_vec())
+    }
+
+    pub fn encode_long(&mut self, value: i64) {
+        self.push_vec(value.to_be_bytes().to_vec())
+    }
+
+    pub fn encode_float(&mut self, value: f32) {
+        self.push_vec(value.to_be_bytes().to_vec())
+    }
+
+    pub fn encode_double(&mut self, value: f64) {
+        self.push_vec(value.to_be_bytes().to_vec())
+    }
+
+    pub fn encode_string(&mut self, value: &str) {
+        self.encode_varint(value.len() as i32);
+        self.push_vec(value.as_bytes().to_vec())
+    }
+
+    pub fn encode_chat(&mut self, value: &str) {
+        self.encode_string(value)
+    }
+
+    pub fn encode_identifier(&mut self, value: &str) {
+        self.encode_string(value)
+    }
+
+    // The value is treated as unsigned, so negative numbers always take 5 bytes.
+    pub fn encode_varint(&mut self, value: i32) {
+        let mut value = value as u32;
+        loop {
+            let temp = (value & 0b01111111) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.push(temp);
+                break;
+            }
+            self.push(temp | 0b10000000);
+        }
+    }
+
+    pub fn encode_varlong(&mut self, value: i64) {
+        let mut value = value as u64;
+        loop {
+            let temp = (value & 0b01111111) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.push(temp);
+                break;
+            }
+            self.push(temp | 0b10000000);
+        }
+    }
+
+    pub fn encode_position(&mut self, value: (i64, i64, i64)) {
+        let (x, y, z) = value;
+        let val = if self.protocol_version >= POSITION_XZY_PROTOCOL_VERSION {
+            ((x & 0x3FFFFFF) << 38) | ((z & 0x3FFFFFF) << 12) | (y & 0xFFF)
+        } else {
+            ((x & 0x3FFFFFF) << 38) | ((y & 0xFFF) << 26) | (z & 0x3FFFFFF)
+        };
+        self.encode_long(val)
+    }
+
+    pub fn encode_angle(&mut self, value: u8) {
+        self.push(value)
+    }
+
+    pub fn encode_uuid(&mut self, value: u128) {
+        self.push_vec(value.to_be_bytes().to_vec())
+    }
+
+    pub fn encode_metadata(&mut self, value: &Metadata) {
+        value.encode(self)
+    }
+
+    pub fn encode_slot(&mut self, value: &Slot) {
+        value.encode(self)
+    }
+
+    pub fn encode_nbt(&mut self, value: &Nbt) {
+        value.encode(self)
+    }
 }
 
 // More tests still need to be added (preferebly for everything that the packet can parse).
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::json;
 
     #[test]
     fn test_varint() {
@@ -246,18 +501,466 @@ mod tests {
             packet.clear()
         }
     }
+
+    #[test]
+    fn test_errors() {
+        let mut packet = Packet::from(vec![0x01, 0x02]);
+        packet.decode_ubyte().unwrap();
+        assert_eq!(
+            packet.decode_int().unwrap_err(),
+            Error::new(
+                ErrorKind::NotEnoughBytes {
+                    needed: 4,
+                    available: 1
+                },
+                1
+            )
+        );
+
+        packet.set(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        assert_eq!(
+            packet.decode_varint().unwrap_err().kind,
+            ErrorKind::BadVarInt
+        );
+
+        packet.set(vec![0x00, 0x02, 0xc3, 0x28]);
+        packet.decode_bool().unwrap();
+        assert_eq!(
+            packet.decode_string().unwrap_err(),
+            Error::new(ErrorKind::BadUtf8, 2)
+        );
+
+        packet.set(vec![0x02]);
+        assert_eq!(
+            packet.decode_bool().unwrap_err(),
+            Error::new(
+                ErrorKind::UnknownEnumValue {
+                    name: "Boolean",
+                    value: 2
+                },
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut packet = Packet::new();
+        packet.encode_varint(-1);
+        assert_eq!(
+            packet.decode_string().unwrap_err().kind,
+            ErrorKind::InvalidLength {
+                length: -1,
+                max: MAX_STRING_LENGTH * 4
+            }
+        );
+
+        packet.clear();
+        packet.encode_varint(MAX_STRING_LENGTH as i32 * 4 + 1);
+        assert!(packet.decode_string().is_err());
+
+        packet.clear();
+        packet.encode_string(&"a".repeat(MAX_STRING_LENGTH + 1));
+        assert!(packet.decode_string().is_err());
+
+        packet.clear();
+        packet.encode_string(&"a".repeat(MAX_STRING_LENGTH));
+        assert_eq!(packet.decode_string().unwrap().len(), MAX_STRING_LENGTH);
+
+        packet.set(vec![0x01]);
+        assert!(packet.read_array(-5).is_err());
+        assert_eq!(packet.read_array(1).unwrap(), vec![0x01]);
+    }
+
+    // Throws random bytes at every decoder, none of them should ever panic.
+    #[test]
+    fn test_fuzz_decoders() {
+        let mut rng = crate::utils::Rng::new(0x5eed);
+        let mut packet = Packet::new();
+        for _ in 0..20000 {
+            let length = rng.below(40);
+            packet.set(rng.bytes(length));
+            loop {
+                let result = match rng.below(17) {
+                    0 => packet.decode_bool().map(|_| ()),
+                    1 => packet.decode_byte().map(|_| ()),
+                    2 => packet.decode_ubyte().map(|_| ()),
+                    3 => packet.decode_short().map(|_| ()),
+                    4 => packet.decode_ushort().map(|_| ()),
+                    5 => packet.decode_int().map(|_| ()),
+                    6 => packet.decode_long().map(|_| ()),
+                    7 => packet.decode_float().map(|_| ()),
+                    8 => packet.decode_double().map(|_| ()),
+                    9 => packet.decode_string().map(|_| ()),
+                    10 => packet.decode_chat().map(|_| ()),
+                    11 => packet.decode_identifier().map(|_| ()),
+                    12 => packet.decode_varint().map(|_| ()),
+                    13 => packet.decode_varlong().map(|_| ()),
+                    14 => packet.decode_position().map(|_| ()),
+                    15 => packet.decode_angle().map(|_| ()),
+                    _ => packet.decode_uuid().map(|_| ()),
+                };
+                if result.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_varint() {
+        let mut packet = Packet::new();
+        let values = vec![
+            (vec![0x00], 0),
+            (vec![0x01], 1),
+            (vec![0x7f], 127),
+            (vec![0x80, 0x01], 128),
+            (vec![0xff, 0x01], 255),
+            (vec![0xff, 0xff, 0x7f], 2097151),
+            (vec![0xff, 0xff, 0xff, 0xff, 0x07], 2147483647),
+            (vec![0xff, 0xff, 0xff, 0xff, 0x0f], -1),
+            (vec![0x80, 0x80, 0x80, 0x80, 0x08], -2147483648),
+        ];
+        for (p, v) in values {
+            packet.encode_varint(v);
+            assert_eq!(packet.get_vec(), p);
+            assert_eq!(packet.decode_varint().unwrap(), v);
+            assert_eq!(packet.len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_encode_varlong() {
+        let mut packet = Packet::new();
+        let values = vec![
+            (vec![0x00], 0),
+            (vec![0x7f], 127),
+            (vec![0x80, 0x01], 128),
+            (vec![0xff, 0xff, 0xff, 0xff, 0x07], 2147483647),
+            (
+                vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+                9223372036854775807,
+            ),
+            (
+                vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+                -1,
+            ),
+            (
+                vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01],
+                -9223372036854775808,
+            ),
+        ];
+        for (p, v) in values {
+            packet.encode_varlong(v);
+            assert_eq!(packet.get_vec(), p);
+            assert_eq!(packet.decode_varlong().unwrap(), v);
+            assert_eq!(packet.len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_encode_string() {
+        let mut packet = Packet::new();
+        for v in ["", "zegevlier", "minecraft:overworld", "\u{00e9}\u{1f600}"] {
+            packet.encode_string(v);
+            assert_eq!(packet.decode_string().unwrap(), v);
+            assert_eq!(packet.len(), 0);
+        }
+        packet.encode_string("abc");
+        assert_eq!(packet.get_vec(), vec![0x03, b'a', b'b', b'c']);
+    }
+
+    #[test]
+    fn test_encode_numbers() {
+        let mut packet = Packet::new();
+        packet.encode_bool(true);
+        packet.encode_bool(false);
+        packet.encode_byte(-128);
+        packet.encode_ubyte(255);
+        packet.encode_short(-12345);
+        packet.encode_ushort(25565);
+        packet.encode_int(i32::MIN);
+        packet.encode_long(i64::MAX);
+        packet.encode_float(-1.5);
+        packet.encode_double(std::f64::consts::PI);
+        packet.encode_angle(200);
+        packet.encode_uuid(0x069a79f444e94726a5befca90e38aaf5);
+        assert!(packet.decode_bool().unwrap());
+        assert!(!packet.decode_bool().unwrap());
+        assert_eq!(packet.decode_byte().unwrap(), -128);
+        assert_eq!(packet.decode_ubyte().unwrap(), 255);
+        assert_eq!(packet.decode_short().unwrap(), -12345);
+        assert_eq!(packet.decode_ushort().unwrap(), 25565);
+        assert_eq!(packet.decode_int().unwrap(), i32::MIN);
+        assert_eq!(packet.decode_long().unwrap(), i64::MAX);
+        assert_eq!(packet.decode_float().unwrap(), -1.5);
+        assert_eq!(packet.decode_double().unwrap(), std::f64::consts::PI);
+        assert_eq!(packet.decode_angle().unwrap(), 200);
+        assert_eq!(
+            packet.decode_uuid().unwrap(),
+            0x069a79f444e94726a5befca90e38aaf5
+        );
+        assert_eq!(packet.len(), 0);
+    }
+
+    #[test]
+    fn test_position() {
+        let mut packet = Packet::new();
+        let values = vec![
+            // Gotten from wiki.vg
+            (
+                vec![0x46, 0x07, 0x63, 0x2c, 0x15, 0xb4, 0x83, 0x3f],
+                DEFAULT_PROTOCOL_VERSION,
+                (18357644, 831, -20882616),
+            ),
+            (
+                vec![0x46, 0x07, 0x63, 0x0c, 0xfe, 0xc1, 0x5b, 0x48],
+                340,
+                (18357644, 831, -20882616),
+            ),
+            (vec![0x00; 8], DEFAULT_PROTOCOL_VERSION, (0, 0, 0)),
+            (vec![0x00; 8], 340, (0, 0, 0)),
+            (vec![0xff; 8], DEFAULT_PROTOCOL_VERSION, (-1, -1, -1)),
+            (vec![0xff; 8], 340, (-1, -1, -1)),
+            (
+                vec![0xff, 0xff, 0xff, 0xc0, 0x00, 0x00, 0x10, 0x40],
+                DEFAULT_PROTOCOL_VERSION,
+                (-1, 64, 1),
+            ),
+            (
+                vec![0xff, 0xff, 0xff, 0xc1, 0x00, 0x00, 0x00, 0x01],
+                340,
+                (-1, 64, 1),
+            ),
+            (
+                vec![0x00, 0x00, 0x19, 0x3f, 0xff, 0xf9, 0xcf, 0xc0],
+                DEFAULT_PROTOCOL_VERSION,
+                (100, -64, -100),
+            ),
+            (
+                vec![0x00, 0x00, 0x19, 0x3f, 0x03, 0xff, 0xff, 0x9c],
+                340,
+                (100, -64, -100),
+            ),
+            (
+                vec![0x80, 0x00, 0x00, 0x20, 0x00, 0x00, 0x08, 0x00],
+                DEFAULT_PROTOCOL_VERSION,
+                (-33554432, -2048, -33554432),
+            ),
+            (
+                vec![0x80, 0x00, 0x00, 0x20, 0x02, 0x00, 0x00, 0x00],
+                340,
+                (-33554432, -2048, -33554432),
+            ),
+            (
+                vec![0x7f, 0xff, 0xff, 0xdf, 0xff, 0xff, 0xf7, 0xff],
+                DEFAULT_PROTOCOL_VERSION,
+                (33554431, 2047, 33554431),
+            ),
+            (
+                vec![0x7f, 0xff, 0xff, 0xdf, 0xfd, 0xff, 0xff, 0xff],
+                340,
+                (33554431, 2047, 33554431),
+            ),
+        ];
+        for (p, version, v) in values {
+            packet.set_protocol_version(version);
+            packet.set(p.clone());
+            assert_eq!(packet.decode_position().unwrap(), v);
+            packet.encode_position(v);
+            assert_eq!(packet.get_vec(), p);
+            packet.clear()
+        }
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+    enum Hand {
+        #[default]
+        Main,
+        Off,
+    }
+
+    impl Field for Hand {
+        fn decode(packet: &mut Packet) -> Result<Self, Error> {
+            match packet.decode_varint()? {
+                0 => Ok(Self::Main),
+                1 => Ok(Self::Off),
+                value => Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "Hand",
+                    value: value as i64,
+                })),
+            }
+        }
+
+        fn encode(&self, packet: &mut Packet) {
+            packet.encode_varint(match self {
+                Self::Main => 0,
+                Self::Off => 1,
+            });
+        }
+
+        fn printable(&self) -> String {
+            format!("{:?}", self)
+        }
+    }
+
+    #[derive(Clone, mc_proxy_derive::Parsable, serde::Serialize)]
+    struct Derived {
+        #[varint]
+        id: i32,
+        #[varlong]
+        time: i64,
+        #[angle]
+        yaw: u8,
+        #[position]
+        #[serde(serialize_with = "json::position")]
+        location: (i64, i64, i64),
+        #[identifier]
+        channel: String,
+        #[serde(serialize_with = "json::uuid")]
+        uuid: u128,
+        #[length_prefixed]
+        #[varint]
+        entities: Vec<i32>,
+        #[length_prefixed]
+        #[serde(serialize_with = "json::bytes")]
+        key: Vec<u8>,
+        title: Option<String>,
+        hand: Hand,
+        #[since(versions::V1_16)]
+        flag: bool,
+        #[serde(serialize_with = "json::bytes")]
+        rest: Vec<u8>,
+    }
+
+    #[test]
+    fn test_derive() {
+        let mut packet = Packet::with_version(versions::V1_16);
+        packet.encode_varint(300);
+        packet.encode_varlong(-1);
+        packet.encode_angle(200);
+        packet.encode_position((1, -2, 3));
+        packet.encode_identifier("minecraft:brand");
+        packet.encode_uuid(0xabcdef);
+        packet.push_vec(vec![0x02, 0x01, 0x80, 0x01]);
+        packet.push_vec(vec![0x02, 0xaa, 0xbb]);
+        packet.push_vec(vec![0x01, 0x02, b'h', b'i']);
+        packet.encode_varint(1);
+        packet.encode_bool(true);
+        packet.push_vec(vec![0x01, 0x02]);
+        let data = packet.get_vec();
+
+        let mut derived = Derived::empty();
+        derived
+            .parse_packet(Packet::with_version(versions::V1_16))
+            .unwrap_err();
+        let mut input = Packet::with_version(versions::V1_16);
+        input.set(data.clone());
+        derived.parse_packet(input).unwrap();
+        assert_eq!(derived.id, 300);
+        assert_eq!(derived.location, (1, -2, 3));
+        assert_eq!(derived.entities, vec![1, 128]);
+        assert_eq!(derived.title, Some("hi".into()));
+        assert_eq!(derived.hand, Hand::Off);
+        assert_eq!(derived.rest, vec![0x01, 0x02]);
+        assert_eq!(derived.serialize(versions::V1_16).get_vec(), data);
+        assert_eq!(
+            derived.get_printable(),
+            "300 -1 200 1 -2 3 minecraft:brand abcdef [1, 128] aabb hi Off true 0102"
+        );
+
+        // The flag isn't there in older versions, so the byte after the hand goes to the rest.
+        let mut input = Packet::with_version(versions::V1_15);
+        input.set(data.clone());
+        let mut derived = Derived::empty();
+        derived.parse_packet(input).unwrap();
+        assert!(!derived.flag);
+        assert_eq!(derived.rest, vec![0x01, 0x01, 0x02]);
+        assert_eq!(derived.serialize(versions::V1_15).get_vec(), data);
+        assert!(derived.get_printable().ends_with("Off 010102"));
+
+        // The JSON leaves the flag out because of the version, not because it's false.
+        let json = derived.to_json(versions::V1_15);
+        assert_eq!(
+            json["location"],
+            serde_json::json!({"x": 1, "y": -2, "z": 3})
+        );
+        assert_eq!(json["uuid"], "00000000-0000-0000-0000-000000abcdef");
+        assert_eq!(json["key"], "aabb");
+        assert_eq!(json["hand"], "Off");
+        assert_eq!(json.get("flag"), None);
+        assert_eq!(derived.to_json(versions::V1_16)["flag"], false);
+
+        // A count that can't fit in what's left shouldn't be trusted.
+        let mut input = Packet::new();
+        input.set(data[..53].to_vec());
+        input.push_vec(vec![0xff, 0xff, 0x7f]);
+        let error = Derived::empty().parse_packet(input).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::InvalidLength { .. }));
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut packet = Packet::new();
+        packet.encode_long(42);
+        let mut ping = crate::serverbound::status::StatusPing::empty();
+        assert_eq!(
+            parse(&mut ping, Packet::from(packet.get_vec())),
+            Ok(packet.get_vec())
+        );
+        assert_eq!(ping.payload, 42);
+
+        // A byte more than the parser reads.
+        packet.encode_bool(true);
+        let error = parse(&mut ping, packet).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::LeftoverBytes(1));
+        assert_eq!(error.offset, 8);
+    }
 }
 
-pub trait Parsable: DynClone {
+// This lets handlers get the actual packet back out of a `dyn Parsable`.
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait Parsable: DynClone + AsAny + ToJson {
     fn empty() -> Self
     where
         Self: Sized;
 
-    fn parse_packet(&mut self, packet: Packet) -> Result<(), ()>;
+    fn parse_packet(&mut self, packet: Packet) -> Result<(), Error>;
+
+    // This should give back the exact bytes `parse_packet` was given (without the packet ID),
+    // when it's given the same protocol version the packet was parsed with.
+    fn serialize(&self, protocol_version: i32) -> Packet;
 
     fn get_printable(&self) -> String;
 
-    fn update_status(&self, _status: &mut Status) -> Result<(), ()> {
+    // The fields as a JSON object for the JSON output, which is what `Serialize` gives.
+    // The protocol version is for fields that aren't in every version.
+    fn to_json(&self, _protocol_version: i32) -> serde_json::Value {
+        self.json_value()
+    }
+
+    fn update_status(&self, _status: &mut Status) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // Runs before `update_status`, for updates that have to wait for something. The status
+    // isn't locked, it's only locked for as long as it's needed.
+    fn update_status_unlocked(&self, _status: &Mutex<Status>) -> Result<(), Error> {
         Ok(())
     }
 
@@ -267,3 +970,30 @@ pub trait Parsable: DynClone {
 }
 
 dyn_clone::clone_trait_object!(Parsable);
+
+// Parses the packet and gives back what it serializes to. A parser that's right gives back as
+// many bytes as it was given, so if there are fewer it stopped before the end and read the
+// packet wrong. That's an error too, it's usually a version the parser doesn't know.
+pub fn parse(parser: &mut dyn Parsable, packet: Packet) -> Result<Vec<u8>, Error> {
+    let length = packet.len();
+    let protocol_version = packet.protocol_version();
+    parser.parse_packet(packet)?;
+    let body = parser.serialize(protocol_version).get_vec();
+    if body.len() < length {
+        return Err(Error::new(
+            ErrorKind::LeftoverBytes(length - body.len()),
+            body.len(),
+        ));
+    }
+    Ok(body)
+}
+
+// Field is for the types in a `#[derive(Parsable)]` struct that aren't built in, like enums.
+// They also need to implement `Default` for `empty`, and `Serialize` for the JSON output.
+pub trait Field: Sized {
+    fn decode(packet: &mut Packet) -> Result<Self, Error>;
+
+    fn encode(&self, packet: &mut Packet);
+
+    fn printable(&self) -> String;
+}
@@ -0,0 +1,375 @@
+use crate::capture::{Capture, Entry};
+use crate::codec;
+use crate::packet::Packet;
+use crate::types::{Direction, State};
+
+// Captures are exported as made up TCP connections, one per capture, between a client at
+// 10.0.0.1 and the server at 10.0.0.2 (on the port from the handshake). The packets are sent
+// the way they would be without encryption, so Wireshark can follow the stream. The encryption
+// request and response are left out, so nothing says the rest is encrypted. After the set
+// compression packets have the compressed layout, with a data length of 0 since they aren't
+// actually compressed. Each one has what the proxy parsed it as in a comment.
+const SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const ENHANCED_PACKET: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+// Raw IPv4 and IPv6, there's no link layer.
+const LINKTYPE_RAW: u16 = 101;
+const OPTION_END: u16 = 0;
+const OPTION_COMMENT: u16 = 1;
+
+const CLIENT_ADDRESS: [u8; 4] = [10, 0, 0, 1];
+const SERVER_ADDRESS: [u8; 4] = [10, 0, 0, 2];
+// The port of the first capture's client, the next one gets the one after it.
+const FIRST_CLIENT_PORT: u16 = 50000;
+// An IPv4 packet can't be longer than this, so big packets are split over more segments.
+const MAX_SEGMENT: usize = 65535 - 20 - 20;
+
+// The login packets that change how the rest is sent, their IDs are the same in every version.
+const ENCRYPTION: i32 = 0x01;
+const SET_COMPRESSION: i32 = 0x03;
+
+const SYN: u8 = 0x02;
+const ACK: u8 = 0x10;
+const PSH: u8 = 0x08;
+const FIN: u8 = 0x01;
+
+// Builds a pcapng file in memory, timestamps are in microseconds.
+pub struct Writer {
+    data: Vec<u8>,
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        let mut writer = Self { data: Vec::new() };
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // The length of the section isn't known up front.
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        writer.block(SECTION_HEADER, body);
+        let mut body = Vec::new();
+        body.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        writer.block(INTERFACE_DESCRIPTION, body);
+        writer
+    }
+
+    fn block(&mut self, block_type: u32, mut body: Vec<u8>) {
+        pad(&mut body);
+        let length = body.len() as u32 + 12;
+        self.data.extend_from_slice(&block_type.to_le_bytes());
+        self.data.extend_from_slice(&length.to_le_bytes());
+        self.data.extend(body);
+        self.data.extend_from_slice(&length.to_le_bytes());
+    }
+
+    pub fn packet(&mut self, timestamp: u64, packet: &[u8], comment: Option<&str>) {
+        let mut body = Vec::new();
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(packet);
+        pad(&mut body);
+        if let Some(comment) = comment {
+            // An option can't be longer than this, the end of a long printable is cut off.
+            let mut length = comment.len().min(u16::MAX as usize);
+            while !comment.is_char_boundary(length) {
+                length -= 1;
+            }
+            let comment = &comment[..length];
+            body.extend_from_slice(&OPTION_COMMENT.to_le_bytes());
+            body.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+            body.extend_from_slice(comment.as_bytes());
+            pad(&mut body);
+            body.extend_from_slice(&OPTION_END.to_le_bytes());
+            body.extend_from_slice(&0u16.to_le_bytes());
+        }
+        self.block(ENHANCED_PACKET, body);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+fn pad(data: &mut Vec<u8>) {
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+}
+
+// One side of a made up TCP connection, it keeps track of the sequence numbers.
+struct Endpoint {
+    address: [u8; 4],
+    port: u16,
+    sequence: u32,
+}
+
+// Adds a capture as a TCP connection. The comments are for every entry in the capture.
+pub fn add_capture(
+    writer: &mut Writer,
+    capture: &Capture,
+    comments: &[Option<String>],
+    index: u16,
+) {
+    let mut client = Endpoint {
+        address: CLIENT_ADDRESS,
+        port: FIRST_CLIENT_PORT.wrapping_add(index),
+        sequence: 0,
+    };
+    let mut server = Endpoint {
+        address: SERVER_ADDRESS,
+        port: capture.header.handshake.server_port,
+        sequence: 0,
+    };
+    let start = capture.header.start.max(0) as u64 * 1000;
+
+    // The three-way handshake, so Wireshark knows who started it.
+    segment(writer, start, &mut client, &server, SYN, &[], None);
+    segment(writer, start, &mut server, &client, SYN | ACK, &[], None);
+    segment(writer, start, &mut client, &server, ACK, &[], None);
+    let mut timestamp = start;
+    let mut compression = false;
+    for (index, entry) in capture.entries.iter().enumerate() {
+        if is_login(entry, ENCRYPTION) {
+            continue;
+        }
+        timestamp = start + entry.time * 1000;
+        let mut packet = codec::build(entry.packet_id, entry.payload.clone());
+        if compression {
+            let mut uncompressed = Packet::new();
+            uncompressed.encode_varint(0);
+            uncompressed.push_vec(packet.get_vec());
+            packet = uncompressed;
+        }
+        let frame = codec::frame(packet);
+        compression |=
+            is_login(entry, SET_COMPRESSION) && entry.direction == Direction::Clientbound;
+        let comment = comments.get(index).and_then(|comment| comment.as_deref());
+        let (from, to) = match entry.direction {
+            Direction::Serverbound => (&mut client, &server),
+            Direction::Clientbound => (&mut server, &client),
+        };
+        // Only the first segment of a packet has the comment.
+        for (chunk, data) in frame.chunks(MAX_SEGMENT).enumerate() {
+            let comment = if chunk == 0 { comment } else { None };
+            segment(writer, timestamp, from, to, PSH | ACK, data, comment);
+        }
+    }
+    segment(
+        writer,
+        timestamp,
+        &mut client,
+        &server,
+        FIN | ACK,
+        &[],
+        None,
+    );
+    segment(
+        writer,
+        timestamp,
+        &mut server,
+        &client,
+        FIN | ACK,
+        &[],
+        None,
+    );
+    segment(writer, timestamp, &mut client, &server, ACK, &[], None);
+}
+
+fn is_login(entry: &Entry, packet_id: i32) -> bool {
+    entry.state == State::Login && entry.packet_id == packet_id
+}
+
+fn segment(
+    writer: &mut Writer,
+    timestamp: u64,
+    from: &mut Endpoint,
+    to: &Endpoint,
+    flags: u8,
+    data: &[u8],
+    comment: Option<&str>,
+) {
+    let mut tcp = Vec::with_capacity(20 + data.len());
+    tcp.extend_from_slice(&from.port.to_be_bytes());
+    tcp.extend_from_slice(&to.port.to_be_bytes());
+    tcp.extend_from_slice(&from.sequence.to_be_bytes());
+    let acknowledgment = if flags & ACK != 0 { to.sequence } else { 0 };
+    tcp.extend_from_slice(&acknowledgment.to_be_bytes());
+    // 20 bytes of header, no options.
+    tcp.push(5 << 4);
+    tcp.push(flags);
+    tcp.extend_from_slice(&u16::MAX.to_be_bytes());
+    tcp.extend_from_slice(&[0, 0, 0, 0]);
+    tcp.extend_from_slice(data);
+    let mut pseudo_header = Vec::with_capacity(12 + tcp.len());
+    pseudo_header.extend_from_slice(&from.address);
+    pseudo_header.extend_from_slice(&to.address);
+    pseudo_header.extend_from_slice(&[0, 6]);
+    pseudo_header.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+    pseudo_header.extend_from_slice(&tcp);
+    let tcp_checksum = checksum(&pseudo_header);
+    tcp[16..18].copy_from_slice(&tcp_checksum.to_be_bytes());
+
+    let mut ip = Vec::with_capacity(20 + tcp.len());
+    ip.push(0x45);
+    ip.push(0);
+    ip.extend_from_slice(&((20 + tcp.len()) as u16).to_be_bytes());
+    // Identification, and don't fragment.
+    ip.extend_from_slice(&[0, 0, 0x40, 0]);
+    // The time to live, TCP, and the checksum that's filled in below.
+    ip.extend_from_slice(&[64, 6, 0, 0]);
+    ip.extend_from_slice(&from.address);
+    ip.extend_from_slice(&to.address);
+    let ip_checksum = checksum(&ip);
+    ip[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+    ip.extend(tcp);
+    writer.packet(timestamp, &ip, comment);
+
+    // SYN and FIN take up a sequence number, like a byte would.
+    let length = data.len() as u32 + (flags & (SYN | FIN) != 0) as u32;
+    from.sequence = from.sequence.wrapping_add(length);
+}
+
+// The internet checksum of IPv4 and TCP.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum = 0u32;
+    for pair in data.chunks(2) {
+        let word = u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]);
+        sum += word as u32;
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::testing::Builder;
+
+    // The blocks in a pcapng file, as their type and body.
+    fn blocks(data: &[u8]) -> Vec<(u32, &[u8])> {
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let word = |at: usize| {
+                u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+            };
+            let length = word(offset + 4) as usize;
+            assert!(length.is_multiple_of(4));
+            assert_eq!(word(offset + length - 4) as usize, length);
+            blocks.push((word(offset), &data[offset + 8..offset + length - 4]));
+            offset += length;
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_checksum() {
+        // The example from RFC 1071.
+        assert_eq!(
+            checksum(&[0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7]),
+            !0xddf2
+        );
+        assert_eq!(checksum(&[0xff]), 0x00ff);
+    }
+
+    #[test]
+    fn test_export() {
+        let capture = Builder::new(1_618_000_000_000, Some(256))
+            .entry(
+                0,
+                Direction::Serverbound,
+                State::Login,
+                0x00,
+                vec![0x04, b'j', b'e', b'b', b'_'],
+            )
+            .entry(3, Direction::Clientbound, State::Login, 0x01, vec![0x00; 8])
+            .entry(
+                5,
+                Direction::Clientbound,
+                State::Login,
+                0x03,
+                vec![0x80, 0x02],
+            )
+            .entry(
+                7,
+                Direction::Clientbound,
+                State::Play,
+                0x20,
+                vec![0x55; 70000],
+            )
+            .build();
+        let comments = vec![
+            Some("C>S [LoginStart] jeb_".to_string()),
+            Some("S>C [EncRequest]".to_string()),
+            None,
+            None,
+        ];
+        let mut writer = Writer::new();
+        add_capture(&mut writer, &capture, &comments, 0);
+        let data = writer.into_bytes();
+
+        let blocks = blocks(&data);
+        assert_eq!(blocks[0].0, SECTION_HEADER);
+        assert_eq!(blocks[1].0, INTERFACE_DESCRIPTION);
+        // The handshake, four segments (one packet is split in two, the encryption request is
+        // left out) and the goodbye.
+        assert_eq!(blocks.len(), 2 + 3 + 4 + 3);
+        let packet = |index: usize| {
+            let body = blocks[index].1;
+            let length = u32::from_le_bytes([body[12], body[13], body[14], body[15]]) as usize;
+            // The options come after the padding.
+            let padded = length.div_ceil(4) * 4;
+            (&body[..20], &body[20..20 + length], &body[20 + padded..])
+        };
+
+        // The login start, from the client to the server at 0.000 after it started.
+        let (header, ip, options) = packet(5);
+        let timestamp = u64::from(u32::from_le_bytes([
+            header[4], header[5], header[6], header[7],
+        ])) << 32
+            | u64::from(u32::from_le_bytes([
+                header[8], header[9], header[10], header[11],
+            ]));
+        assert_eq!(timestamp, 1_618_000_000_000_000);
+        assert_eq!(checksum(&ip[..20]), 0);
+        assert_eq!(ip[12..20], [10, 0, 0, 1, 10, 0, 0, 2]);
+        let tcp = &ip[20..];
+        assert_eq!(tcp[..4], [0xc3, 0x50, 0x63, 0xdd]);
+        assert_eq!(tcp[4..8], 1u32.to_be_bytes());
+        assert_eq!(tcp[8..12], 1u32.to_be_bytes());
+        assert_eq!(tcp[20..], [0x06, 0x00, 0x04, b'j', b'e', b'b', b'_']);
+        let mut pseudo_header = vec![10, 0, 0, 1, 10, 0, 0, 2, 0, 6, 0, tcp.len() as u8];
+        pseudo_header.extend_from_slice(tcp);
+        assert_eq!(checksum(&pseudo_header), 0);
+        assert_eq!(options[..4], [0x01, 0x00, 21, 0x00]);
+        assert_eq!(&options[4..25], b"C>S [LoginStart] jeb_");
+
+        // The set compression goes the other way, after the client's 7 bytes.
+        let (_, ip, options) = packet(6);
+        let tcp = &ip[20..];
+        assert_eq!(tcp[..4], [0x63, 0xdd, 0xc3, 0x50]);
+        assert_eq!(tcp[8..12], 8u32.to_be_bytes());
+        assert!(options.is_empty());
+        let (_, first, _) = packet(7);
+        let (_, second, _) = packet(8);
+        assert_eq!(first.len(), 65535);
+        // After the set compression there's a data length of 0 in front of the ID.
+        assert_eq!(first[40..45], [0xf2, 0xa2, 0x04, 0x00, 0x20]);
+        assert_eq!(first.len() + second.len() - 80, 70000 + 5);
+    }
+}
@@ -0,0 +1,487 @@
+use crate::capture::Capture;
+use crate::clientbound::play::{KeepAliveClientbound, PlayerPositionAndLook};
+use crate::codec::{self, Decoder};
+use crate::functions::{self, Fid, Functions};
+use crate::packet::{Packet, Parsable};
+use crate::serverbound::handshaking::Handshake;
+use crate::serverbound::login::LoginStart;
+use crate::serverbound::play::{PlayerPosition, PlayerPositionAndRotation, PlayerRotation};
+use crate::types::{Direction, ReplayConfig, State, Status};
+use crate::versions;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::time::{sleep, sleep_until, Instant};
+
+// The client gives up after 30 seconds without anything from the server, so when the recording
+// has a longer gap (or is played slowly) it gets a keep alive in between.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+// Teleports only set the position or only the rotation, the rest stays where it is.
+const RELATIVE_POSITION: i8 = 0x07;
+const RELATIVE_ROTATION: i8 = 0x18;
+
+// A recorded session that's played back to clients, like it's the server.
+pub struct Replay {
+    pub capture: Capture,
+    // 2.0 plays it twice as fast, 0 as fast as it can.
+    pub speed: f64,
+    // Moves the client where the recorded player went, so it's seen through their eyes.
+    pub follow_player: bool,
+}
+
+impl Replay {
+    pub fn load(config: &ReplayConfig) -> Result<Self, String> {
+        if config.speed < 0.0 || !config.speed.is_finite() {
+            return Err(format!("{} is not a speed", config.speed));
+        }
+        Ok(Self {
+            capture: Capture::load(&config.capture)?,
+            speed: config.speed,
+            follow_player: config.follow_player,
+        })
+    }
+
+    pub fn protocol_version(&self) -> i32 {
+        self.capture.header.handshake.protocol_version
+    }
+
+    // The packets after logging in, with when they're due (in ms after the first one).
+    // Everything the server sent goes as it is, the recorded player's movement as teleports.
+    pub fn playback(&self, functions: &Functions) -> Vec<(u64, i32, Vec<u8>)> {
+        let protocol_version = versions::parsing_version(self.protocol_version());
+        let teleport_id = functions.get_id(
+            &Direction::Clientbound,
+            &State::Play,
+            protocol_version,
+            &Fid::PlayerPositionAndLook,
+        );
+        let mut packets = Vec::new();
+        let mut start = None;
+        for entry in &self.capture.entries {
+            if entry.state != State::Play {
+                continue;
+            }
+            let time = entry.time.saturating_sub(*start.get_or_insert(entry.time));
+            match entry.direction {
+                Direction::Clientbound => {
+                    packets.push((time, entry.packet_id, entry.payload.clone()));
+                }
+                Direction::Serverbound if self.follow_player => {
+                    let name = functions.get_name(
+                        &entry.direction,
+                        &entry.state,
+                        protocol_version,
+                        &entry.packet_id,
+                    );
+                    let mut packet = Packet::from(entry.payload.clone());
+                    packet.set_protocol_version(protocol_version);
+                    let teleport = match name.map(|name| teleport(name, packet)) {
+                        Some(Some(teleport)) => teleport,
+                        _ => continue,
+                    };
+                    if let Some(teleport_id) = teleport_id {
+                        let payload = teleport.serialize(protocol_version).get_vec();
+                        packets.push((time, teleport_id, payload));
+                    }
+                }
+                Direction::Serverbound => {}
+            }
+        }
+        packets
+    }
+}
+
+// The teleport that puts the client where the recorded player moved to.
+fn teleport(name: &Fid, packet: Packet) -> Option<PlayerPositionAndLook> {
+    let mut teleport = PlayerPositionAndLook::empty();
+    match name {
+        Fid::PlayerPosition => {
+            let mut position = PlayerPosition::empty();
+            position.parse_packet(packet).ok()?;
+            teleport.x = position.x;
+            teleport.y = position.feet_y;
+            teleport.z = position.z;
+            teleport.flags = RELATIVE_ROTATION;
+        }
+        Fid::PlayerPositionAndRotation => {
+            let mut position = PlayerPositionAndRotation::empty();
+            position.parse_packet(packet).ok()?;
+            teleport.x = position.x;
+            teleport.y = position.feet_y;
+            teleport.z = position.z;
+            teleport.yaw = position.yaw;
+            teleport.pitch = position.pitch;
+        }
+        Fid::PlayerRotation => {
+            let mut rotation = PlayerRotation::empty();
+            rotation.parse_packet(packet).ok()?;
+            teleport.yaw = rotation.yaw;
+            teleport.pitch = rotation.pitch;
+            teleport.flags = RELATIVE_POSITION;
+        }
+        _ => return None,
+    }
+    Some(teleport)
+}
+
+// Reads the next packet the client sent, before logging in nothing is compressed or encrypted.
+async fn read_packet(
+    rx: &mut OwnedReadHalf,
+    decoder: &mut Decoder,
+) -> Result<(i32, Packet), String> {
+    let mut buf = [0; 4096];
+    loop {
+        if let Some(frame) = decoder.next_frame().map_err(|err| err.to_string())? {
+            let mut packet = codec::decompress(frame, None).map_err(|err| err.to_string())?;
+            let packet_id = packet.decode_varint().map_err(|err| err.to_string())?;
+            return Ok((packet_id, packet));
+        }
+        match rx.read(&mut buf).await {
+            Ok(0) => return Err("the client left".into()),
+            Ok(n) => decoder.push(buf[0..n].to_vec()),
+            Err(why) => return Err(why.to_string()),
+        }
+    }
+}
+
+async fn send(
+    tx: &mut OwnedWriteHalf,
+    status: &mut Status,
+    packet_id: i32,
+    payload: Vec<u8>,
+) -> Result<(), String> {
+    let bytes = codec::encode(
+        status,
+        &Direction::Clientbound,
+        codec::build(packet_id, payload),
+    );
+    tx.write_all(&bytes).await.map_err(|why| why.to_string())
+}
+
+// The ID of a packet the replay sends itself, in the state the connection is in now.
+fn packet_id(functions: &Functions, status: &Status, id: &Fid) -> Result<i32, String> {
+    let protocol_version = versions::parsing_version(status.protocol_version);
+    functions
+        .get_id(&Direction::Clientbound, &status.state, protocol_version, id)
+        .ok_or_else(|| format!("{} has no ID in {}", id, status.state))
+}
+
+// Acts like a server to one client: it answers the server list ping, and once the client has
+// logged in it gets the recorded packets. Anything else the client sends is ignored.
+pub async fn serve(stream: TcpStream, replay: Arc<Replay>) -> Result<(), String> {
+    let functions = functions::get_functions();
+    let (mut rx, mut tx) = stream.into_split();
+    let mut decoder = Decoder::new();
+    let mut status = Status::new();
+
+    let (_, packet) = read_packet(&mut rx, &mut decoder).await?;
+    let mut handshake = Handshake::empty();
+    handshake
+        .parse_packet(packet)
+        .map_err(|err| err.to_string())?;
+    handshake
+        .update_status(&mut status)
+        .map_err(|err| err.to_string())?;
+
+    if status.state == State::Status {
+        loop {
+            let (request, packet) = read_packet(&mut rx, &mut decoder).await?;
+            // The status request has nothing in it, the ping has a number that goes right back.
+            if request == 0x00 {
+                let mut response = Packet::new();
+                response.encode_string(&status_json(&replay));
+                let id = packet_id(&functions, &status, &Fid::StatusResponse)?;
+                send(&mut tx, &mut status, id, response.get_vec()).await?;
+            } else {
+                let id = packet_id(&functions, &status, &Fid::StatusPong)?;
+                return send(&mut tx, &mut status, id, packet.get_vec()).await;
+            }
+        }
+    }
+
+    let (_, packet) = read_packet(&mut rx, &mut decoder).await?;
+    let mut login_start = LoginStart::empty();
+    login_start
+        .parse_packet(packet)
+        .map_err(|err| err.to_string())?;
+    if status.protocol_version != replay.protocol_version() {
+        let mut reason = Packet::new();
+        reason.encode_chat(
+            &serde_json::json!({
+                "text": format!(
+                    "This replay is of {}, use that version to watch it",
+                    versions::name(replay.protocol_version())
+                )
+            })
+            .to_string(),
+        );
+        let id = packet_id(&functions, &status, &Fid::Disconnect)?;
+        send(&mut tx, &mut status, id, reason.get_vec()).await?;
+        return Err(format!(
+            "{} joined with {}, the replay is of {}",
+            login_start.username,
+            versions::name(status.protocol_version),
+            versions::name(replay.protocol_version())
+        ));
+    }
+
+    // The recorded login success is sent as it is, so the client becomes the recorded player.
+    let id = packet_id(&functions, &status, &Fid::LoginSuccess)?;
+    let login_success = replay.capture.entries.iter().find(|entry| {
+        entry.direction == Direction::Clientbound
+            && entry.state == State::Login
+            && entry.packet_id == id
+    });
+    let login_success = match login_success {
+        Some(entry) => entry.payload.clone(),
+        None => return Err("the capture doesn't have a login success".into()),
+    };
+    send(&mut tx, &mut status, id, login_success).await?;
+    status.state = State::Play;
+    log::info!("{} is watching the replay", login_start.username);
+
+    // The client still sends things, they're read so it doesn't get stuck.
+    tokio::spawn(async move {
+        let mut buf = [0; 4096];
+        while let Ok(n) = rx.read(&mut buf).await {
+            if n == 0 {
+                break;
+            }
+        }
+    });
+
+    let keep_alive_id = packet_id(&functions, &status, &Fid::KeepAliveClientbound).ok();
+    let mut keep_alive = KeepAliveClientbound::empty();
+    let start = Instant::now();
+    let mut last_sent = Instant::now();
+    for (time, id, payload) in replay.playback(&functions) {
+        if replay.speed > 0.0 {
+            let due = start + Duration::from_secs_f64(time as f64 / 1000.0 / replay.speed);
+            while due > last_sent + KEEP_ALIVE_INTERVAL {
+                sleep_until(last_sent + KEEP_ALIVE_INTERVAL).await;
+                if let Some(keep_alive_id) = keep_alive_id {
+                    keep_alive.keep_alive_id += 1;
+                    let payload = keep_alive.serialize(status.protocol_version).get_vec();
+                    send(&mut tx, &mut status, keep_alive_id, payload).await?;
+                }
+                last_sent = Instant::now();
+            }
+            sleep_until(due).await;
+        }
+        send(&mut tx, &mut status, id, payload).await?;
+        last_sent = Instant::now();
+    }
+    log::info!(
+        "The replay is over, {} can keep looking around",
+        login_start.username
+    );
+
+    // The client is kept in the world until it leaves.
+    let keep_alive_id = match keep_alive_id {
+        Some(keep_alive_id) => keep_alive_id,
+        None => return Ok(()),
+    };
+    loop {
+        sleep(KEEP_ALIVE_INTERVAL).await;
+        keep_alive.keep_alive_id += 1;
+        let payload = keep_alive.serialize(status.protocol_version).get_vec();
+        if send(&mut tx, &mut status, keep_alive_id, payload)
+            .await
+            .is_err()
+        {
+            return Ok(());
+        }
+    }
+}
+
+// What the server list shows for the replay.
+fn status_json(replay: &Replay) -> String {
+    let handshake = &replay.capture.header.handshake;
+    serde_json::json!({
+        "version": {
+            "name": versions::name(replay.protocol_version()),
+            "protocol": replay.protocol_version(),
+        },
+        "players": {"max": 1, "online": 0},
+        "description": {
+            "text": format!("Replay of {}:{}", handshake.server_address, handshake.server_port)
+        },
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::testing::Builder;
+    use tokio::net::TcpListener;
+
+    fn replay() -> Replay {
+        let mut position = Packet::new();
+        position.encode_double(1.5);
+        position.encode_double(64.0);
+        position.encode_double(-2.5);
+        position.encode_bool(true);
+        let mut login_success = vec![0x11; 16];
+        login_success.extend_from_slice(&[0x04, b'j', b'e', b'b', b'_']);
+        let capture = Builder::new(0, Some(256))
+            .entry(
+                0,
+                Direction::Serverbound,
+                State::Login,
+                0x00,
+                vec![0x04, b'j', b'e', b'b', b'_'],
+            )
+            .entry(
+                10,
+                Direction::Clientbound,
+                State::Login,
+                0x03,
+                vec![0x80, 0x02],
+            )
+            .entry(
+                20,
+                Direction::Clientbound,
+                State::Login,
+                0x02,
+                login_success,
+            )
+            .entry(
+                1000,
+                Direction::Clientbound,
+                State::Play,
+                0x1f,
+                vec![0, 0, 0, 0, 0, 0, 0, 7],
+            )
+            .entry(
+                1500,
+                Direction::Serverbound,
+                State::Play,
+                0x12,
+                position.get_vec(),
+            )
+            .entry(
+                1600,
+                Direction::Serverbound,
+                State::Play,
+                0x10,
+                vec![0, 0, 0, 0, 0, 0, 0, 7],
+            )
+            .entry(
+                2000,
+                Direction::Clientbound,
+                State::Play,
+                0x1f,
+                vec![0, 0, 0, 0, 0, 0, 0, 8],
+            )
+            .build();
+        Replay {
+            capture,
+            speed: 0.0,
+            follow_player: true,
+        }
+    }
+
+    #[test]
+    fn test_playback() {
+        let functions = functions::get_functions();
+        let mut replay = replay();
+        let playback = replay.playback(&functions);
+        let times: Vec<(u64, i32)> = playback.iter().map(|(time, id, _)| (*time, *id)).collect();
+        assert_eq!(times, vec![(0, 0x1f), (500, 0x34), (1000, 0x1f)]);
+        let mut teleport = PlayerPositionAndLook::empty();
+        teleport
+            .parse_packet(Packet::from(playback[1].2.clone()))
+            .unwrap();
+        assert_eq!((teleport.x, teleport.y, teleport.z), (1.5, 64.0, -2.5));
+        assert_eq!(teleport.flags, RELATIVE_ROTATION);
+
+        replay.follow_player = false;
+        assert_eq!(replay.playback(&functions).len(), 2);
+    }
+
+    async fn connect(listener: &TcpListener, replay: &Arc<Replay>) -> TcpStream {
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let replay = replay.clone();
+        tokio::spawn(async move { serve(server, replay).await });
+        client
+    }
+
+    async fn write(client: &mut TcpStream, packet_id: i32, payload: Vec<u8>) {
+        let frame = codec::frame(codec::build(packet_id, payload));
+        client.write_all(&frame).await.unwrap();
+    }
+
+    async fn read(client: &mut TcpStream, decoder: &mut Decoder) -> (i32, Vec<u8>) {
+        let mut buf = [0; 4096];
+        loop {
+            if let Some(mut frame) = decoder.next_frame().unwrap() {
+                return (frame.decode_varint().unwrap(), frame.get_vec());
+            }
+            let n = client.read(&mut buf).await.unwrap();
+            assert!(n > 0, "the replay closed the connection");
+            decoder.push(buf[..n].to_vec());
+        }
+    }
+
+    fn handshake(protocol_version: i32, next_state: State) -> Vec<u8> {
+        let mut handshake = Handshake::empty();
+        handshake.protocol_version = protocol_version;
+        handshake.server_address = "localhost".into();
+        handshake.server_port = 25555;
+        handshake.next_state = next_state;
+        handshake.serialize(protocol_version).get_vec()
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let replay = Arc::new(replay());
+
+        // The client gets the recorded login success and play packets, nothing else.
+        let mut client = connect(&listener, &replay).await;
+        let mut decoder = Decoder::new();
+        write(&mut client, 0x00, handshake(754, State::Login)).await;
+        write(&mut client, 0x00, vec![0x03, b'b', b'o', b'b']).await;
+        let (id, payload) = read(&mut client, &mut decoder).await;
+        assert_eq!((id, payload.len()), (0x02, 21));
+        assert_eq!(
+            read(&mut client, &mut decoder).await.1,
+            vec![0, 0, 0, 0, 0, 0, 0, 7]
+        );
+        assert_eq!(read(&mut client, &mut decoder).await.0, 0x34);
+        write(&mut client, 0x00, vec![0x01]).await;
+        assert_eq!(
+            read(&mut client, &mut decoder).await.1,
+            vec![0, 0, 0, 0, 0, 0, 0, 8]
+        );
+
+        // Other versions are sent away.
+        let mut client = connect(&listener, &replay).await;
+        let mut decoder = Decoder::new();
+        write(&mut client, 0x00, handshake(753, State::Login)).await;
+        write(&mut client, 0x00, vec![0x03, b'b', b'o', b'b']).await;
+        let (id, payload) = read(&mut client, &mut decoder).await;
+        assert_eq!(id, 0x00);
+        assert!(String::from_utf8_lossy(&payload).contains("1.16.5"));
+
+        // The server list shows what the replay is of.
+        let mut client = connect(&listener, &replay).await;
+        let mut decoder = Decoder::new();
+        write(&mut client, 0x00, handshake(754, State::Status)).await;
+        write(&mut client, 0x00, Vec::new()).await;
+        let (id, payload) = read(&mut client, &mut decoder).await;
+        assert_eq!(id, 0x00);
+        assert!(String::from_utf8_lossy(&payload).contains("Replay of mc:25565"));
+        write(&mut client, 0x01, vec![0, 0, 0, 0, 0, 0, 0x30, 0x39]).await;
+        assert_eq!(
+            read(&mut client, &mut decoder).await,
+            (0x01, vec![0, 0, 0, 0, 0, 0, 0x30, 0x39])
+        );
+    }
+}
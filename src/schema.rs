@@ -0,0 +1,789 @@
+use crate::error::{Error, ErrorKind};
+use crate::json;
+use crate::packet::{Packet, Parsable};
+use crate::types::{Direction, State};
+use serde::{Deserialize, Serializer};
+use std::{collections::HashMap, fmt, sync::Arc};
+
+// A schema file describes packets that don't have a parser in the code, so they can be parsed
+// without recompiling. It's TOML or JSON (going by the extension), and looks like this:
+//
+// [[packets]]
+// name = "EntityAnimation"
+// direction = "clientbound"
+// state = "play"
+// ids = { "340" = 0x06, "735" = 0x05 }
+// fields = [
+//     { name = "entity_id", type = "varint" },
+//     { name = "animation", type = "ubyte" },
+// ]
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Schema {
+    #[serde(default)]
+    pub packets: Vec<PacketDefinition>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PacketDefinition {
+    pub name: String,
+    pub direction: Option<Direction>,
+    pub state: Option<State>,
+    // The ID for every version, starting at the version in the key. `id` is the same for all of them.
+    #[serde(default)]
+    pub ids: HashMap<String, i32>,
+    pub id: Option<i32>,
+    #[serde(default)]
+    pub fields: Vec<Field>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Field {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: FieldType,
+    // The field is only there from `since`, and before `before`.
+    pub since: Option<i32>,
+    pub before: Option<i32>,
+}
+
+// Everything but the simple types refers to fields that came before it by their name.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FieldType {
+    Bool,
+    Byte,
+    UByte,
+    Short,
+    UShort,
+    Int,
+    Long,
+    Float,
+    Double,
+    VarInt,
+    VarLong,
+    String,
+    Chat,
+    Identifier,
+    Position,
+    Angle,
+    Uuid,
+    // Bytes with a VarInt length in front of them, or with the length in the field `length`.
+    Bytes {
+        length: Option<String>,
+    },
+    // All the bytes that are left.
+    Rest,
+    // There if the bool field `when` is true, without `when` there's a bool in front of it.
+    Optional {
+        when: Option<String>,
+        field: Box<FieldType>,
+    },
+    // A VarInt count and then the elements, or just the elements with the count in the field `count`.
+    Array {
+        count: Option<String>,
+        of: Box<FieldType>,
+    },
+    // What's there depends on the value of the field `on`.
+    Switch {
+        on: String,
+        cases: HashMap<String, FieldType>,
+        default: Option<Box<FieldType>>,
+    },
+    Container {
+        fields: Vec<Field>,
+    },
+}
+
+impl Field {
+    fn present(&self, protocol_version: i32) -> bool {
+        self.since.is_none_or(|since| protocol_version >= since)
+            && self.before.is_none_or(|before| protocol_version < before)
+    }
+}
+
+impl PacketDefinition {
+    // Gives back the ID the packet has starting at every version it has one for.
+    pub fn ids(&self) -> Result<Vec<(i32, i32)>, String> {
+        let mut ids = Vec::new();
+        if let Some(id) = self.id {
+            ids.push((0, id));
+        }
+        for (version, id) in &self.ids {
+            match version.parse() {
+                Ok(version) => ids.push((version, *id)),
+                Err(_) => return Err(format!("{}: {:?} is not a version", self.name, version)),
+            }
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    // The ID in the given version, None if the packet isn't there (yet).
+    pub fn id_for(&self, protocol_version: i32) -> Option<i32> {
+        self.ids()
+            .ok()?
+            .into_iter()
+            .rev()
+            .find(|(version, _)| *version <= protocol_version)
+            .map(|(_, id)| id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Void,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Position(i64, i64, i64),
+    Uuid(u128),
+    Bytes(Vec<u8>),
+    Optional(Option<Box<Value>>),
+    Array(Vec<Value>),
+    Container(Vec<(String, Value)>),
+}
+
+// Containers are objects and the rest is written the way the JSON output writes it for packets
+// that have a parser in the code.
+impl serde::Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Void => serializer.serialize_unit(),
+            Self::Bool(value) => serializer.serialize_bool(*value),
+            Self::Int(value) => serializer.serialize_i64(*value),
+            Self::Float(value) => serializer.serialize_f64(*value),
+            Self::String(value) => serializer.serialize_str(value),
+            Self::Position(x, y, z) => json::position(&(*x, *y, *z), serializer),
+            Self::Uuid(value) => json::uuid(value, serializer),
+            Self::Bytes(value) => json::bytes(value, serializer),
+            Self::Optional(value) => serde::Serialize::serialize(value, serializer),
+            Self::Array(values) => serde::Serialize::serialize(values, serializer),
+            Self::Container(values) => serialize_container(values, serializer),
+        }
+    }
+}
+
+fn serialize_container<S: Serializer>(
+    values: &[(String, Value)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(values.iter().map(|(name, value)| (name, value)))
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Void => write!(f, "-"),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{}", value),
+            Self::String(value) => write!(f, "{}", value),
+            Self::Position(x, y, z) => write!(f, "{} {} {}", x, y, z),
+            Self::Uuid(value) => write!(f, "{:x}", value),
+            Self::Bytes(value) => write!(
+                f,
+                "{}",
+                crate::utils::make_string_fixed_length(hex::encode(value), 20)
+            ),
+            Self::Optional(None) => write!(f, "None"),
+            Self::Optional(Some(value)) => write!(f, "{}", value),
+            Self::Array(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+            Self::Container(values) => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+                write!(f, "{{{}}}", values.join(" "))
+            }
+        }
+    }
+}
+
+fn schema_error(packet: &Packet, why: String) -> Error {
+    packet.error(ErrorKind::Schema(why))
+}
+
+// Finds the value of a field that came before, in the container it's in or the ones around it.
+fn lookup<'a>(
+    name: &str,
+    values: &'a [(String, Value)],
+    parents: &[&'a [(String, Value)]],
+) -> Option<&'a Value> {
+    values
+        .iter()
+        .chain(parents.iter().rev().flat_map(|scope| scope.iter()))
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value)
+}
+
+fn lookup_count(
+    packet: &Packet,
+    name: &str,
+    values: &[(String, Value)],
+    parents: &[&[(String, Value)]],
+) -> Result<i64, Error> {
+    match lookup(name, values, parents) {
+        Some(Value::Int(count)) => Ok(*count),
+        _ => Err(schema_error(
+            packet,
+            format!("{} is not a number that came before", name),
+        )),
+    }
+}
+
+fn lookup_bool(
+    packet: &Packet,
+    name: &str,
+    values: &[(String, Value)],
+    parents: &[&[(String, Value)]],
+) -> Result<bool, Error> {
+    match lookup(name, values, parents) {
+        Some(Value::Bool(value)) => Ok(*value),
+        _ => Err(schema_error(
+            packet,
+            format!("{} is not a bool that came before", name),
+        )),
+    }
+}
+
+// A count from the packet can't be trusted, every element takes at least a byte so that's the limit.
+fn check_count(packet: &Packet, count: i64) -> Result<usize, Error> {
+    if count < 0 || count as usize > packet.len() {
+        return Err(packet.error(ErrorKind::InvalidLength {
+            length: count,
+            max: packet.len(),
+        }));
+    }
+    Ok(count as usize)
+}
+
+fn decode_fields(
+    fields: &[Field],
+    packet: &mut Packet,
+    parents: &[&[(String, Value)]],
+) -> Result<Vec<(String, Value)>, Error> {
+    let mut values = Vec::new();
+    for field in fields {
+        if !field.present(packet.protocol_version()) {
+            continue;
+        }
+        let value = decode_value(&field.kind, packet, &values, parents)?;
+        values.push((field.name.clone(), value));
+    }
+    Ok(values)
+}
+
+fn decode_value(
+    kind: &FieldType,
+    packet: &mut Packet,
+    values: &[(String, Value)],
+    parents: &[&[(String, Value)]],
+) -> Result<Value, Error> {
+    Ok(match kind {
+        FieldType::Bool => Value::Bool(packet.decode_bool()?),
+        FieldType::Byte => Value::Int(packet.decode_byte()? as i64),
+        FieldType::UByte => Value::Int(packet.decode_ubyte()? as i64),
+        FieldType::Short => Value::Int(packet.decode_short()? as i64),
+        FieldType::UShort => Value::Int(packet.decode_ushort()? as i64),
+        FieldType::Int => Value::Int(packet.decode_int()? as i64),
+        FieldType::Long => Value::Int(packet.decode_long()?),
+        FieldType::Float => Value::Float(packet.decode_float()? as f64),
+        FieldType::Double => Value::Float(packet.decode_double()?),
+        FieldType::VarInt => Value::Int(packet.decode_varint()? as i64),
+        FieldType::VarLong => Value::Int(packet.decode_varlong()?),
+        FieldType::String => Value::String(packet.decode_string()?),
+        FieldType::Chat => Value::String(packet.decode_chat()?),
+        FieldType::Identifier => Value::String(packet.decode_identifier()?),
+        FieldType::Position => {
+            let (x, y, z) = packet.decode_position()?;
+            Value::Position(x, y, z)
+        }
+        FieldType::Angle => Value::Int(packet.decode_angle()? as i64),
+        FieldType::Uuid => Value::Uuid(packet.decode_uuid()?),
+        FieldType::Bytes { length } => {
+            let length = match length {
+                Some(name) => lookup_count(packet, name, values, parents)?,
+                None => packet.decode_varint()? as i64,
+            };
+            let length = check_count(packet, length)?;
+            Value::Bytes(packet.read(length)?)
+        }
+        FieldType::Rest => {
+            let rest = packet.len();
+            Value::Bytes(packet.read(rest)?)
+        }
+        FieldType::Optional { when, field } => {
+            let present = match when {
+                Some(name) => lookup_bool(packet, name, values, parents)?,
+                None => packet.decode_bool()?,
+            };
+            match present {
+                true => Value::Optional(Some(Box::new(decode_value(
+                    field, packet, values, parents,
+                )?))),
+                false => Value::Optional(None),
+            }
+        }
+        FieldType::Array { count, of } => {
+            let count = match count {
+                Some(name) => lookup_count(packet, name, values, parents)?,
+                None => packet.decode_varint()? as i64,
+            };
+            let count = check_count(packet, count)?;
+            let mut elements = Vec::with_capacity(count);
+            for _ in 0..count {
+                elements.push(decode_value(of, packet, values, parents)?);
+            }
+            Value::Array(elements)
+        }
+        FieldType::Switch { on, cases, default } => {
+            let key = match lookup(on, values, parents) {
+                Some(value) => value.to_string(),
+                None => return Err(schema_error(packet, format!("{} didn't come before", on))),
+            };
+            match cases.get(&key).or(default.as_deref()) {
+                Some(kind) => decode_value(kind, packet, values, parents)?,
+                None => Value::Void,
+            }
+        }
+        FieldType::Container { fields } => {
+            let mut scopes = parents.to_vec();
+            scopes.push(values);
+            Value::Container(decode_fields(fields, packet, &scopes)?)
+        }
+    })
+}
+
+fn encode_fields(
+    fields: &[Field],
+    values: &[(String, Value)],
+    packet: &mut Packet,
+    parents: &[&[(String, Value)]],
+) {
+    let protocol_version = packet.protocol_version();
+    for (index, (field, (_, value))) in fields
+        .iter()
+        .filter(|field| field.present(protocol_version))
+        .zip(values)
+        .enumerate()
+    {
+        encode_value(&field.kind, value, packet, &values[..index], parents);
+    }
+}
+
+// The values always come from decoding with the same schema, so a value that doesn't fit the type
+// can only come from a handler that changed it. Those are left out.
+fn encode_value(
+    kind: &FieldType,
+    value: &Value,
+    packet: &mut Packet,
+    values: &[(String, Value)],
+    parents: &[&[(String, Value)]],
+) {
+    match (kind, value) {
+        (FieldType::Bool, Value::Bool(value)) => packet.encode_bool(*value),
+        (FieldType::Byte, Value::Int(value)) => packet.encode_byte(*value as i8),
+        (FieldType::UByte, Value::Int(value)) => packet.encode_ubyte(*value as u8),
+        (FieldType::Short, Value::Int(value)) => packet.encode_short(*value as i16),
+        (FieldType::UShort, Value::Int(value)) => packet.encode_ushort(*value as u16),
+        (FieldType::Int, Value::Int(value)) => packet.encode_int(*value as i32),
+        (FieldType::Long, Value::Int(value)) => packet.encode_long(*value),
+        (FieldType::Float, Value::Float(value)) => packet.encode_float(*value as f32),
+        (FieldType::Double, Value::Float(value)) => packet.encode_double(*value),
+        (FieldType::VarInt, Value::Int(value)) => packet.encode_varint(*value as i32),
+        (FieldType::VarLong, Value::Int(value)) => packet.encode_varlong(*value),
+        (FieldType::String, Value::String(value)) => packet.encode_string(value),
+        (FieldType::Chat, Value::String(value)) => packet.encode_chat(value),
+        (FieldType::Identifier, Value::String(value)) => packet.encode_identifier(value),
+        (FieldType::Position, Value::Position(x, y, z)) => packet.encode_position((*x, *y, *z)),
+        (FieldType::Angle, Value::Int(value)) => packet.encode_angle(*value as u8),
+        (FieldType::Uuid, Value::Uuid(value)) => packet.encode_uuid(*value),
+        (FieldType::Bytes { length }, Value::Bytes(value)) => {
+            if length.is_none() {
+                packet.encode_varint(value.len() as i32);
+            }
+            packet.push_vec(value.clone())
+        }
+        (FieldType::Rest, Value::Bytes(value)) => packet.push_vec(value.clone()),
+        (FieldType::Optional { when, field }, Value::Optional(value)) => {
+            if when.is_none() {
+                packet.encode_bool(value.is_some());
+            }
+            if let Some(value) = value {
+                encode_value(field, value, packet, values, parents);
+            }
+        }
+        (FieldType::Array { count, of }, Value::Array(elements)) => {
+            if count.is_none() {
+                packet.encode_varint(elements.len() as i32);
+            }
+            for element in elements {
+                encode_value(of, element, packet, values, parents);
+            }
+        }
+        // The case is picked the same way as when decoding, from the field it switches on.
+        (FieldType::Switch { on, cases, default }, value) => {
+            let key = match lookup(on, values, parents) {
+                Some(key) => key.to_string(),
+                None => return,
+            };
+            if let Some(kind) = cases.get(&key).or(default.as_deref()) {
+                encode_value(kind, value, packet, values, parents);
+            }
+        }
+        (FieldType::Container { fields }, Value::Container(container)) => {
+            let mut scopes = parents.to_vec();
+            scopes.push(values);
+            encode_fields(fields, container, packet, &scopes)
+        }
+        _ => (),
+    }
+}
+
+// Checks that every field that's referred to comes before it, so that doesn't have to fail on every packet.
+fn check_fields(fields: &[Field], scope: &[String], packet: &str) -> Result<(), String> {
+    let mut scope = scope.to_vec();
+    for field in fields {
+        check_type(&field.kind, &scope, packet, &field.name)?;
+        scope.push(field.name.clone());
+    }
+    Ok(())
+}
+
+fn check_type(kind: &FieldType, scope: &[String], packet: &str, field: &str) -> Result<(), String> {
+    let check = |name: &Option<String>| match name {
+        Some(name) if !scope.contains(name) => Err(format!(
+            "{}.{} refers to {}, which doesn't come before it",
+            packet, field, name
+        )),
+        _ => Ok(()),
+    };
+    match kind {
+        FieldType::Bytes { length } => check(length),
+        FieldType::Optional { when, field: kind } => {
+            check(when)?;
+            check_type(kind, scope, packet, field)
+        }
+        FieldType::Array { count, of } => {
+            check(count)?;
+            check_type(of, scope, packet, field)
+        }
+        FieldType::Switch { on, cases, default } => {
+            check(&Some(on.clone()))?;
+            for kind in cases.values().chain(default.as_deref()) {
+                check_type(kind, scope, packet, field)?;
+            }
+            Ok(())
+        }
+        FieldType::Container { fields } => check_fields(fields, scope, packet),
+        _ => Ok(()),
+    }
+}
+
+impl Schema {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let schema: Self = toml::from_str(text).map_err(|why| why.to_string())?;
+        schema.check()?;
+        Ok(schema)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let schema: Self = serde_json::from_str(text).map_err(|why| why.to_string())?;
+        schema.check()?;
+        Ok(schema)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|why| format!("Could not read {}: {}", path, why))?;
+        let schema = if path.ends_with(".json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        };
+        schema.map_err(|why| format!("Could not load {}: {}", path, why))
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let mut names = Vec::new();
+        for packet in &self.packets {
+            if names.contains(&packet.name) {
+                return Err(format!("{} is in there twice", packet.name));
+            }
+            names.push(packet.name.clone());
+            if packet.direction.is_none() || packet.state.is_none() {
+                return Err(format!("{} needs a direction and state", packet.name));
+            }
+            packet.ids()?;
+            check_fields(&packet.fields, &[], &packet.name)?;
+        }
+        Ok(())
+    }
+}
+
+// SchemaPacket is the Parsable for every packet in the schema, it just holds the values by name.
+#[derive(Clone)]
+pub struct SchemaPacket {
+    pub definition: Arc<PacketDefinition>,
+    pub values: Vec<(String, Value)>,
+}
+
+impl SchemaPacket {
+    pub fn new(definition: Arc<PacketDefinition>) -> Self {
+        Self {
+            definition,
+            values: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        lookup(name, &self.values, &[])
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.values
+            .iter_mut()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl serde::Serialize for SchemaPacket {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_container(&self.values, serializer)
+    }
+}
+
+impl Parsable for SchemaPacket {
+    fn empty() -> Self {
+        Self::new(Arc::new(PacketDefinition::default()))
+    }
+
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
+        self.values = decode_fields(&self.definition.fields, &mut packet, &[])?;
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        encode_fields(&self.definition.fields, &self.values, &mut packet, &[]);
+        packet
+    }
+
+    fn get_printable(&self) -> String {
+        let values: Vec<String> = self.values.iter().map(|(_, v)| v.to_string()).collect();
+        values.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        [[packets]]
+        name = "Everything"
+        direction = "clientbound"
+        state = "play"
+        ids = { "340" = 0x70, "735" = 0x71 }
+        fields = [
+            { name = "a", type = "bool" },
+            { name = "b", type = "byte" },
+            { name = "c", type = "ubyte" },
+            { name = "d", type = "short" },
+            { name = "e", type = "ushort" },
+            { name = "f", type = "int" },
+            { name = "g", type = "long" },
+            { name = "h", type = "float" },
+            { name = "i", type = "double" },
+            { name = "j", type = "varint" },
+            { name = "k", type = "varlong" },
+            { name = "l", type = "string" },
+            { name = "m", type = "chat" },
+            { name = "n", type = "identifier" },
+            { name = "o", type = "position" },
+            { name = "p", type = "angle" },
+            { name = "q", type = "uuid" },
+            { name = "r", type = "bytes" },
+            { name = "has_s", type = "bool" },
+            { name = "s", type = "optional", when = "has_s", field = { type = "string" } },
+            { name = "t", type = "optional", field = { type = "varint" } },
+            { name = "count", type = "varint" },
+            { name = "u", type = "array", count = "count", of = { type = "container", fields = [
+                { name = "kind", type = "varint" },
+                { name = "data", type = "switch", on = "kind", cases = { "0" = { type = "string" }, "1" = { type = "position" } } },
+                { name = "extra", type = "switch", on = "count", cases = {}, default = { type = "bool" } },
+            ] } },
+            { name = "v", type = "array", of = { type = "short" } },
+            { name = "new", type = "varint", since = 735 },
+            { name = "old", type = "varint", before = 735 },
+            { name = "w", type = "rest" },
+        ]
+    "#;
+
+    fn fixture(protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_bool(true);
+        packet.encode_byte(-5);
+        packet.encode_ubyte(250);
+        packet.encode_short(-300);
+        packet.encode_ushort(60000);
+        packet.encode_int(-70000);
+        packet.encode_long(1 << 40);
+        packet.encode_float(0.5);
+        packet.encode_double(-0.25);
+        packet.encode_varint(300);
+        packet.encode_varlong(-1);
+        packet.encode_string("hello");
+        packet.encode_chat(r#"{"text":"hi"}"#);
+        packet.encode_identifier("minecraft:stone");
+        packet.encode_position((1, -2, 3));
+        packet.encode_angle(64);
+        packet.encode_uuid(0x069a79f444e94726a5befca90e38aaf5);
+        packet.encode_varint(2);
+        packet.push_vec(vec![0xde, 0xad]);
+        packet.encode_bool(true);
+        packet.encode_string("there");
+        packet.encode_bool(false);
+        packet.encode_varint(2);
+        packet.encode_varint(0);
+        packet.encode_string("zero");
+        packet.encode_bool(true);
+        packet.encode_varint(1);
+        packet.encode_position((4, 5, 6));
+        packet.encode_bool(false);
+        packet.encode_varint(3);
+        packet.encode_short(1);
+        packet.encode_short(2);
+        packet.encode_short(3);
+        packet.encode_varint(7);
+        packet.push_vec(vec![0x01, 0x02, 0x03]);
+        packet
+    }
+
+    #[test]
+    fn test_schema_roundtrip() {
+        let schema = Schema::from_toml(SCHEMA).unwrap();
+        let definition = Arc::new(schema.packets[0].clone());
+        for version in [340, 754] {
+            let packet = fixture(version);
+            let expected = packet.get_vec();
+            let mut parsed = SchemaPacket::new(definition.clone());
+            parsed.parse_packet(packet).unwrap();
+            assert_eq!(parsed.serialize(version).get_vec(), expected);
+            assert_eq!(parsed.get("l"), Some(&Value::String("hello".into())));
+            assert_eq!(parsed.get("t"), Some(&Value::Optional(None)));
+            assert_eq!(parsed.get("w"), Some(&Value::Bytes(vec![0x01, 0x02, 0x03])));
+            let u = match parsed.get("u") {
+                Some(Value::Array(u)) => u,
+                _ => panic!("u is not an array"),
+            };
+            assert_eq!(
+                u[1],
+                Value::Container(vec![
+                    ("kind".into(), Value::Int(1)),
+                    ("data".into(), Value::Position(4, 5, 6)),
+                    ("extra".into(), Value::Bool(false)),
+                ])
+            );
+            let (new, old) = match version {
+                754 => (Some(&Value::Int(7)), None),
+                _ => (None, Some(&Value::Int(7))),
+            };
+            assert_eq!(parsed.get("new"), new);
+            assert_eq!(parsed.get("old"), old);
+            assert!(parsed.get_printable().starts_with("true -5 250 -300 60000"));
+        }
+    }
+
+    #[test]
+    fn test_schema_switch() {
+        // The cases all hold an Int, so the value alone can't tell which one it was.
+        let schema = Schema::from_toml(
+            r#"
+            [[packets]]
+            name = "Switch"
+            direction = "clientbound"
+            state = "play"
+            id = 0x70
+            fields = [
+                { name = "values", type = "array", of = { type = "container", fields = [
+                    { name = "kind", type = "varint" },
+                    { name = "value", type = "switch", on = "kind", default = { type = "short" }, cases = { "0" = { type = "byte" }, "1" = { type = "long" }, "2" = { type = "varint" } } },
+                ] } },
+            ]
+        "#,
+        )
+        .unwrap();
+        let definition = Arc::new(schema.packets[0].clone());
+        let mut packet = Packet::with_version(754);
+        packet.encode_varint(4);
+        for kind in 0..4 {
+            packet.encode_varint(kind);
+            match kind {
+                0 => packet.encode_byte(-1),
+                1 => packet.encode_long(-1),
+                2 => packet.encode_varint(-1),
+                _ => packet.encode_short(-1),
+            }
+        }
+        let expected = packet.get_vec();
+        for _ in 0..10 {
+            let mut parsed = SchemaPacket::new(definition.clone());
+            parsed.parse_packet(Packet::from(expected.clone())).unwrap();
+            assert_eq!(parsed.serialize(754).get_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn test_schema_ids() {
+        let schema = Schema::from_toml(SCHEMA).unwrap();
+        let definition = &schema.packets[0];
+        assert_eq!(definition.id_for(47), None);
+        assert_eq!(definition.id_for(340), Some(0x70));
+        assert_eq!(definition.id_for(578), Some(0x70));
+        assert_eq!(definition.id_for(754), Some(0x71));
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let json = r#"{"packets": [{"name": "A", "direction": "serverbound", "state": "play", "id": 1,
+            "fields": [{"name": "a", "type": "array", "count": "b", "of": {"type": "varint"}}]}]}"#;
+        assert!(Schema::from_json(json).is_err());
+        let json = json.replace(r#""count": "b", "#, "");
+        let schema = Schema::from_json(&json).unwrap();
+        assert_eq!(schema.packets[0].direction, Some(Direction::Serverbound));
+        assert!(Schema::from_toml("[[packets]]\nname = \"A\"\n").is_err());
+
+        // A count that's bigger than the packet is an error, not a huge allocation.
+        let mut parsed = SchemaPacket::new(Arc::new(schema.packets[0].clone()));
+        let mut packet = Packet::new();
+        packet.encode_varint(i32::MAX);
+        assert!(parsed.parse_packet(packet).is_err());
+    }
+
+    #[test]
+    fn test_fuzz_schema() {
+        let schema = Schema::from_toml(SCHEMA).unwrap();
+        let definition = Arc::new(schema.packets[0].clone());
+        let fixture = fixture(754).get_vec();
+        let mut rng = crate::utils::Rng::new(0x5c4e3a);
+        for _ in 0..2000 {
+            let mut data = fixture.clone();
+            for _ in 0..=rng.below(4) {
+                let index = rng.below(data.len());
+                data[index] = rng.next_u64() as u8;
+            }
+            data.truncate(rng.below(data.len() + 1));
+            let mut parsed = SchemaPacket::new(definition.clone());
+            if parsed.parse_packet(Packet::from(data)).is_ok() {
+                parsed.get_printable();
+                parsed.serialize(754);
+            }
+        }
+    }
+}
@@ -0,0 +1,523 @@
+use crate::types::SecretSourceConfig;
+use parking_lot::Mutex;
+use regex::Regex;
+use std::io::{prelude::*, BufReader, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// What the proxy knows about the login that needs a secret.
+// The mark is where the source was when the login started, so older secrets aren't used.
+#[derive(Clone, Debug, Default)]
+pub struct Login {
+    pub username: Option<String>,
+    pub mark: Option<u64>,
+}
+
+// A SecretSource is somewhere the shared secret of a login can be read from,
+// so the proxy can decrypt the traffic without doing the encryption itself.
+pub trait SecretSource: Send + Sync {
+    // Where the source is right now, this is called when a login starts.
+    fn mark(&self) -> u64 {
+        0
+    }
+
+    // Gives back the secret for the login, None if it's not (yet) there.
+    fn secret(&self, login: &Login) -> Result<Option<Vec<u8>>, String>;
+}
+
+// The secrets are printed either as hex or as base64, they're always 16 bytes.
+pub fn parse_secret(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let secret = if text.len() == 32 && text.chars().all(|c| c.is_ascii_hexdigit()) {
+        hex::decode(text).map_err(|why| why.to_string())?
+    } else {
+        base64::decode(text).map_err(|_| format!("{:?} is not hex or base64", text))?
+    };
+    if secret.len() != 16 {
+        return Err(format!("the secret is {} bytes, not 16", secret.len()));
+    }
+    Ok(secret)
+}
+
+// A line can be just the secret, or the username and then the secret.
+fn parse_line(line: &str) -> Result<(Option<String>, Vec<u8>), String> {
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(username), Some(secret)) => Ok((Some(username.into()), parse_secret(secret)?)),
+        (Some(secret), None) => Ok((None, parse_secret(secret)?)),
+        _ => Err("the line is empty".into()),
+    }
+}
+
+fn matches(login: &Login, username: &Option<String>) -> bool {
+    match (&login.username, username) {
+        (Some(login), Some(username)) => login == username,
+        _ => true,
+    }
+}
+
+// The log of the Minecraft client, with a mod or agent that prints the secret in it.
+pub struct LogFile {
+    path: PathBuf,
+    regex: Regex,
+}
+
+impl LogFile {
+    pub fn new(path: PathBuf, regex: &str) -> Result<Self, String> {
+        let regex = Regex::new(regex).map_err(|why| format!("Invalid secret regex: {}", why))?;
+        Ok(Self { path, regex })
+    }
+
+    pub fn default_path() -> Result<PathBuf, String> {
+        let (variable, folder) = if cfg!(windows) {
+            ("APPDATA", ".minecraft/logs/latest.log")
+        } else {
+            ("HOME", ".minecraft/logs/latest.log")
+        };
+        match std::env::var(variable) {
+            Ok(base) => Ok(PathBuf::from(base).join(folder)),
+            Err(_) => Err(format!(
+                "{} is not set, so the log file can't be found",
+                variable
+            )),
+        }
+    }
+}
+
+impl SecretSource for LogFile {
+    // The mark is the length of the file, only lines written after it count.
+    fn mark(&self) -> u64 {
+        std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0)
+    }
+
+    fn secret(&self, login: &Login) -> Result<Option<Vec<u8>>, String> {
+        let mut file = std::fs::File::open(&self.path)
+            .map_err(|why| format!("Could not open {}: {}", self.path.display(), why))?;
+        let length = file.metadata().map(|m| m.len()).unwrap_or(0);
+        // If the file got shorter it's a new log, so it's read from the start.
+        let start = match login.mark {
+            Some(mark) if mark <= length => mark,
+            _ => 0,
+        };
+        file.seek(SeekFrom::Start(start))
+            .map_err(|why| format!("Could not read {}: {}", self.path.display(), why))?;
+
+        let mut found = None;
+        for line in BufReader::new(file).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => continue,
+            };
+            let captures = match self.regex.captures(&line) {
+                Some(captures) => captures,
+                None => continue,
+            };
+            let secret = match captures.name("secret").or_else(|| captures.get(1)) {
+                Some(secret) => secret.as_str(),
+                None => continue,
+            };
+            let username = captures.name("username").map(|u| u.as_str().to_string());
+            if !matches(login, &username) {
+                continue;
+            }
+            match parse_secret(secret) {
+                Ok(secret) => found = Some(secret),
+                Err(why) => log::warn!("Skipping secret in {}: {}", self.path.display(), why),
+            }
+            // With a mark the first secret after it belongs to this login,
+            // without one there's no way to know so the newest is used.
+            if login.mark.is_some() && found.is_some() {
+                break;
+            }
+        }
+        Ok(found)
+    }
+}
+
+// A file that has the secret (or a username and secret on every line) in it.
+pub struct KeyFile {
+    path: PathBuf,
+}
+
+impl SecretSource for KeyFile {
+    fn secret(&self, login: &Login) -> Result<Option<Vec<u8>>, String> {
+        let text = std::fs::read_to_string(&self.path)
+            .map_err(|why| format!("Could not read {}: {}", self.path.display(), why))?;
+        let mut found = None;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (username, secret) =
+                parse_line(line).map_err(|why| format!("In {}: {}", self.path.display(), why))?;
+            if matches(login, &username) {
+                found = Some(secret);
+            }
+        }
+        Ok(found)
+    }
+}
+
+// An environment variable with the secret in it.
+pub struct EnvVar {
+    name: String,
+}
+
+impl SecretSource for EnvVar {
+    fn secret(&self, _login: &Login) -> Result<Option<Vec<u8>>, String> {
+        match std::env::var(&self.name) {
+            Ok(value) => parse_secret(&value)
+                .map(Some)
+                .map_err(|why| format!("In {}: {}", self.name, why)),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+// A socket an agent in the client connects to, it sends a line for every login.
+// The secrets are numbered as they come in, so the mark is the number of the next one.
+type Secrets = Arc<Mutex<Vec<(u64, Option<String>, Vec<u8>)>>>;
+// The agent sends every login, also the ones to servers that aren't proxied. Those secrets are
+// never used, so only this many of the newest are kept.
+const MAX_SECRETS: usize = 32;
+
+pub struct Socket {
+    secrets: Secrets,
+    next: Arc<Mutex<u64>>,
+}
+
+impl Socket {
+    // The address is either `tcp:<ip>:<port>` or `unix:<path>`.
+    pub fn listen(address: &str) -> Result<Self, String> {
+        let socket = Self {
+            secrets: Arc::new(Mutex::new(Vec::new())),
+            next: Arc::new(Mutex::new(0)),
+        };
+        let (kind, rest) = address.split_once(':').unwrap_or(("tcp", address));
+        match kind {
+            "tcp" => {
+                let listener = std::net::TcpListener::bind(rest)
+                    .map_err(|why| format!("Could not listen on {}: {}", rest, why))?;
+                socket.spawn(move || listener.accept().map(|(stream, _)| stream));
+            }
+            #[cfg(unix)]
+            "unix" => {
+                // A socket file left from last time would make the bind fail.
+                let _ = std::fs::remove_file(rest);
+                let listener = std::os::unix::net::UnixListener::bind(rest)
+                    .map_err(|why| format!("Could not listen on {}: {}", rest, why))?;
+                socket.spawn(move || listener.accept().map(|(stream, _)| stream));
+            }
+            kind => return Err(format!("Unknown socket type {}", kind)),
+        }
+        Ok(socket)
+    }
+
+    // Every connection gets its own thread, there shouldn't be more than a few.
+    fn spawn<S, A>(&self, mut accept: A)
+    where
+        S: Read + Send + 'static,
+        A: FnMut() -> std::io::Result<S> + Send + 'static,
+    {
+        let secrets = self.secrets.clone();
+        let next = self.next.clone();
+        std::thread::spawn(move || {
+            while let Ok(stream) = accept() {
+                let secrets = secrets.clone();
+                let next = next.clone();
+                std::thread::spawn(move || {
+                    for line in BufReader::new(stream).lines() {
+                        let line = match line {
+                            Ok(line) => line,
+                            Err(_) => return,
+                        };
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        match parse_line(&line) {
+                            Ok((username, secret)) => add(&secrets, &next, username, secret),
+                            Err(why) => log::warn!("Got a bad secret from the agent: {}", why),
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
+
+// Numbers a secret from the agent and keeps it, dropping the oldest once there are too many.
+fn add(secrets: &Secrets, next: &Mutex<u64>, username: Option<String>, secret: Vec<u8>) {
+    let mut next = next.lock();
+    let mut secrets = secrets.lock();
+    secrets.push((*next, username, secret));
+    *next += 1;
+    if secrets.len() > MAX_SECRETS {
+        secrets.remove(0);
+    }
+}
+
+impl SecretSource for Socket {
+    fn mark(&self) -> u64 {
+        *self.next.lock()
+    }
+
+    fn secret(&self, login: &Login) -> Result<Option<Vec<u8>>, String> {
+        let mut secrets = self.secrets.lock();
+        let found = match login.mark {
+            Some(mark) => secrets
+                .iter()
+                .position(|(number, username, _)| *number >= mark && matches(login, username)),
+            None => secrets
+                .iter()
+                .rposition(|(_, username, _)| matches(login, username)),
+        };
+        // A secret is only used once, so two logins never get the same one.
+        Ok(found.map(|index| secrets.remove(index).2))
+    }
+}
+
+// Makes the source from the config file.
+pub fn get_source(config: &SecretSourceConfig) -> Result<Arc<dyn SecretSource>, String> {
+    Ok(match config {
+        SecretSourceConfig::LogFile { path, regex } => {
+            let path = match path {
+                Some(path) => PathBuf::from(path),
+                None => LogFile::default_path()?,
+            };
+            Arc::new(LogFile::new(path, regex)?)
+        }
+        SecretSourceConfig::KeyFile { path } => Arc::new(KeyFile {
+            path: PathBuf::from(path),
+        }),
+        SecretSourceConfig::Env { name } => Arc::new(EnvVar { name: name.clone() }),
+        SecretSourceConfig::Socket { address } => Arc::new(Socket::listen(address)?),
+    })
+}
+
+// The client can send the packet before the secret is written, so it keeps trying for a bit.
+pub fn wait_for_secret(
+    source: &dyn SecretSource,
+    login: &Login,
+    timeout: Duration,
+) -> Result<Vec<u8>, String> {
+    let start = Instant::now();
+    loop {
+        if let Some(secret) = source.secret(login)? {
+            return Ok(secret);
+        }
+        if start.elapsed() >= timeout {
+            return Err(match &login.username {
+                Some(username) => format!("no secret for {} after {:?}", username, timeout),
+                None => format!("no secret after {:?}", timeout),
+            });
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::Parsable;
+    use crate::serverbound::login::EncResponse;
+    use crate::types::{State, Status, DEFAULT_SECRET_REGEX};
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const SECRET: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mc_proxy_{}_{}", std::process::id(), name))
+    }
+
+    // A source that's only ready the second time, and checks nobody holds the status meanwhile.
+    struct Later {
+        status: Arc<Mutex<Status>>,
+        asked: AtomicUsize,
+    }
+
+    impl SecretSource for Later {
+        fn secret(&self, _login: &Login) -> Result<Option<Vec<u8>>, String> {
+            assert!(self.status.try_lock().is_some());
+            match self.asked.fetch_add(1, Ordering::Relaxed) {
+                0 => Ok(None),
+                _ => Ok(Some(vec![0x42; 16])),
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_wait_unlocked() {
+        let status = Arc::new(Mutex::new(Status::new()));
+        let source = Arc::new(Later {
+            status: status.clone(),
+            asked: AtomicUsize::new(0),
+        });
+        let ready = {
+            let mut status = status.lock();
+            status.secret_source = Some(source.clone());
+            // The server sent an EncRequest, so the clientbound side waits.
+            status.state = State::Login;
+            status.online_mode = true;
+            assert!(status.waits_for_secret());
+            status.secret_ready.clone()
+        };
+        EncResponse::empty()
+            .update_status_unlocked(&status)
+            .unwrap();
+        assert_eq!(source.asked.load(Ordering::Relaxed), 2);
+        assert_ne!(status.lock().client_cipher.encrypt(vec![0; 4]), vec![0; 4]);
+        assert!(!status.lock().waits_for_secret());
+        // It was told before it got to wait, so it doesn't have to wait at all.
+        tokio::time::timeout(Duration::from_secs(1), ready.notified())
+            .await
+            .unwrap();
+        // The source has the status and the status has the source.
+        status.lock().secret_source = None;
+    }
+
+    #[test]
+    fn test_parse_secret() {
+        let secret: Vec<u8> = (0..16).collect();
+        assert_eq!(parse_secret(SECRET).unwrap(), secret);
+        assert_eq!(parse_secret(&base64::encode(&secret)).unwrap(), secret);
+        assert!(parse_secret("0001").is_err());
+        assert!(parse_secret("not a secret!").is_err());
+    }
+
+    #[test]
+    fn test_log_file() {
+        let path = temp_path("latest.log");
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(
+            file,
+            "[Render thread/INFO]: [STDOUT]: Secret Key: {}",
+            base64::encode([1; 16])
+        )
+        .unwrap();
+        let source = LogFile::new(path.clone(), DEFAULT_SECRET_REGEX).unwrap();
+
+        let login = Login {
+            username: Some("Notch".into()),
+            mark: Some(source.mark()),
+        };
+        // The secret from the old login is before the mark, so there's nothing yet.
+        assert_eq!(source.secret(&login).unwrap(), None);
+        writeln!(
+            file,
+            "[Render thread/INFO]: [STDOUT]: Secret Key: {}",
+            base64::encode([2; 16])
+        )
+        .unwrap();
+        writeln!(
+            file,
+            "[Render thread/INFO]: [STDOUT]: Secret Key: {}",
+            base64::encode([3; 16])
+        )
+        .unwrap();
+        assert_eq!(source.secret(&login).unwrap(), Some(vec![2; 16]));
+        // Without a mark it falls back to the newest one.
+        assert_eq!(source.secret(&Login::default()).unwrap(), Some(vec![3; 16]));
+
+        let source = LogFile::new(
+            path.clone(),
+            r"(?P<username>\w+) joined with (?P<secret>\S+)",
+        )
+        .unwrap();
+        writeln!(file, "jeb_ joined with {}", SECRET).unwrap();
+        assert_eq!(source.secret(&login).unwrap(), None);
+        writeln!(file, "Notch joined with {}", base64::encode([4; 16])).unwrap();
+        assert_eq!(source.secret(&login).unwrap(), Some(vec![4; 16]));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(source.secret(&login).is_err());
+    }
+
+    #[test]
+    fn test_key_file() {
+        let path = temp_path("secret.key");
+        let source = KeyFile { path: path.clone() };
+        let login = Login {
+            username: Some("Notch".into()),
+            mark: None,
+        };
+        assert!(source.secret(&login).is_err());
+
+        std::fs::write(&path, format!("{}\n", SECRET)).unwrap();
+        assert_eq!(source.secret(&login).unwrap(), Some((0..16).collect()));
+        std::fs::write(&path, format!("jeb_ {}\n", SECRET)).unwrap();
+        assert_eq!(source.secret(&login).unwrap(), None);
+        std::fs::write(&path, "Notch nope\n").unwrap();
+        assert!(source.secret(&login).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_env_var() {
+        let name = format!("MC_PROXY_TEST_SECRET_{}", std::process::id());
+        let source = EnvVar { name: name.clone() };
+        assert_eq!(source.secret(&Login::default()).unwrap(), None);
+        std::env::set_var(&name, SECRET);
+        assert_eq!(
+            source.secret(&Login::default()).unwrap(),
+            Some((0..16).collect())
+        );
+        std::env::remove_var(&name);
+    }
+
+    #[test]
+    fn test_socket() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let source = Socket {
+            secrets: Arc::new(Mutex::new(Vec::new())),
+            next: Arc::new(Mutex::new(0)),
+        };
+        source.spawn(move || listener.accept().map(|(stream, _)| stream));
+
+        let mut agent = std::net::TcpStream::connect(address).unwrap();
+        writeln!(agent, "Notch {}", base64::encode([1; 16])).unwrap();
+        let old = Login {
+            username: Some("Notch".into()),
+            mark: Some(0),
+        };
+        assert_eq!(
+            wait_for_secret(&source, &old, Duration::from_secs(5)).unwrap(),
+            vec![1; 16]
+        );
+
+        let login = Login {
+            username: Some("Notch".into()),
+            mark: Some(source.mark()),
+        };
+        writeln!(agent, "jeb_ {}", base64::encode([2; 16])).unwrap();
+        writeln!(agent, "Notch {}", base64::encode([3; 16])).unwrap();
+        assert_eq!(
+            wait_for_secret(&source, &login, Duration::from_secs(5)).unwrap(),
+            vec![3; 16]
+        );
+        // It was used, so it's not there anymore.
+        assert!(wait_for_secret(&source, &login, Duration::from_millis(50)).is_err());
+
+        // The ones nobody picks up don't pile up, the oldest go first.
+        for number in 0..MAX_SECRETS + 1 {
+            add(&source.secrets, &source.next, None, vec![number as u8; 16]);
+        }
+        let secrets = source.secrets.lock();
+        assert_eq!(secrets.len(), MAX_SECRETS);
+        assert_eq!(secrets.last().unwrap().2, vec![MAX_SECRETS as u8; 16]);
+        assert!(secrets.iter().all(|(_, _, secret)| secret[0] != 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket() {
+        let path = temp_path("secrets.sock");
+        let source = Socket::listen(&format!("unix:{}", path.display())).unwrap();
+        let mut agent = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        writeln!(agent, "{}", SECRET).unwrap();
+        assert_eq!(
+            wait_for_secret(&source, &Login::default(), Duration::from_secs(5)).unwrap(),
+            (0..16).collect::<Vec<u8>>()
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,12 +1,15 @@
+use crate::error::{Error, ErrorKind};
 use crate::packet::{Packet, Parsable};
+use crate::versions;
 use crate::{State, Status};
+use serde::Serialize;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct Handshake {
-    protocol_version: i32,
-    server_address: String,
-    server_port: u16,
-    next_state: State,
+    pub protocol_version: i32,
+    pub server_address: String,
+    pub server_port: u16,
+    pub next_state: State,
 }
 
 impl Parsable for Handshake {
@@ -19,16 +22,34 @@ impl Parsable for Handshake {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.protocol_version = packet.decode_varint()?;
         self.server_address = packet.decode_string()?;
         self.server_port = packet.decode_ushort()?;
         self.next_state = match packet.decode_varint()? {
             1 => State::Status,
             2 => State::Login,
-            _ => return Err(()),
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "State",
+                    value: value as i64,
+                }))
+            }
         };
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.protocol_version);
+        packet.encode_string(&self.server_address);
+        packet.encode_ushort(self.server_port);
+        packet.encode_varint(match self.next_state {
+            State::Status => 1,
+            State::Login => 2,
+            _ => 0,
+        });
+        packet
     }
 
     fn get_printable(&self) -> String {
@@ -42,9 +63,19 @@ impl Parsable for Handshake {
         true
     }
 
-    fn update_status(&self, status: &mut Status) -> Result<(), ()> {
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
         status.state = self.next_state.clone();
+        status.protocol_version = self.protocol_version;
         log::debug!("State updated to {}", status.state);
+        // Pinging works for every version, logging in only for the ones with packet layouts.
+        if status.state == State::Login && !versions::is_supported(self.protocol_version) {
+            log::warn!(
+                "Protocol version {} is not supported, packets will be parsed like {} ({})",
+                self.protocol_version,
+                versions::name(versions::NEWEST_SUPPORTED),
+                versions::NEWEST_SUPPORTED
+            );
+        }
         Ok(())
     }
 }
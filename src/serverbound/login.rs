@@ -1,16 +1,17 @@
+use crate::error::{Error, ErrorKind};
+use crate::json;
 use crate::packet::{Packet, Parsable};
+use crate::secrets::{self, Login};
 use crate::types::Status;
-use base64::decode;
 use hex::encode;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
-use std::path::Path;
+use parking_lot::Mutex;
+use serde::Serialize;
 
 use crate::utils;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct LoginStart {
-    username: String,
+    pub username: String,
 }
 
 impl Parsable for LoginStart {
@@ -20,22 +21,48 @@ impl Parsable for LoginStart {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.username = packet.decode_string()?;
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_string(&self.username);
+        packet
     }
 
     fn get_printable(&self) -> String {
-        format!("{}", self.username,)
+        self.username.to_string()
+    }
+
+    fn status_updating(&self) -> bool {
+        true
+    }
+
+    // It remembers who is logging in and where the secret source is, to find the right secret later.
+    fn update_status(&self, status: &mut Status) -> Result<(), Error> {
+        status.login = Login {
+            username: Some(self.username.clone()),
+            mark: status.secret_source.as_ref().map(|source| source.mark()),
+        };
+        log::info!(
+            "{} is logging in, their offline UUID is {}",
+            self.username,
+            utils::format_uuid(utils::offline_uuid(&self.username))
+        );
+        Ok(())
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct EncResponse {
-    shared_secret_length: i32,
-    shared_secret: Vec<u8>,
-    verify_token_length: i32,
-    verify_token: Vec<u8>,
+    pub shared_secret_length: i32,
+    #[serde(serialize_with = "json::bytes")]
+    pub shared_secret: Vec<u8>,
+    pub verify_token_length: i32,
+    #[serde(serialize_with = "json::bytes")]
+    pub verify_token: Vec<u8>,
 }
 
 impl Parsable for EncResponse {
@@ -48,12 +75,21 @@ impl Parsable for EncResponse {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.shared_secret_length = packet.decode_varint()?;
-        self.shared_secret = packet.read(self.shared_secret_length as usize)?;
+        self.shared_secret = packet.read_array(self.shared_secret_length)?;
         self.verify_token_length = packet.decode_varint()?;
-        self.verify_token = packet.read(self.verify_token_length as usize)?;
-        return Ok(());
+        self.verify_token = packet.read_array(self.verify_token_length)?;
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.shared_secret_length);
+        packet.push_vec(self.shared_secret.clone());
+        packet.encode_varint(self.verify_token_length);
+        packet.push_vec(self.verify_token.clone());
+        packet
     }
 
     fn get_printable(&self) -> String {
@@ -70,58 +106,51 @@ impl Parsable for EncResponse {
         true
     }
 
-    fn update_status(&self, state: &mut Status) -> Result<(), ()> {
-        let path_str = if cfg!(windows) {
-            let appdata = std::env::var("APPDATA").unwrap();
-            Path::new(&appdata)
-                .join(".minecraft/logs/latest.log")
-                .to_str()
-                .unwrap()
-                .to_string()
-        } else {
-            let home = std::env::var("HOME").unwrap();
-            Path::new(&home)
-                .join(".minecraft/logs/latest.log")
-                .to_str()
-                .unwrap()
-                .to_string()
-        };
-        let path = Path::new(&path_str);
-
-        let file = File::open(path).unwrap();
-        let reader = BufReader::new(file);
-
-        let mut secret_key = String::new();
-
-        for line in reader.lines() {
-            let line = match line {
-                Ok(line) => line,
-                Err(_) => continue,
-            };
-            if line.contains("[STDOUT]: Secret Key: ") {
-                secret_key = line
-                    .split("[STDOUT]: Secret Key: ")
-                    .nth(1)
-                    .unwrap()
-                    .to_string()
-                    .replace("\n", "")
-                    .replace("\r", "");
+    // Waiting for the secret blocks, so it's done without the status locked and the rest of the
+    // runtime is told about it. The clientbound parser waits for it either way.
+    fn update_status_unlocked(&self, status: &Mutex<Status>) -> Result<(), Error> {
+        let result = enable_secret(status);
+        status.lock().finish_secret();
+        result
+    }
+}
+
+// Gets the secret from the source and turns encryption on with it.
+fn enable_secret(status: &Mutex<Status>) -> Result<(), Error> {
+    let (source, login, timeout) = {
+        let status = status.lock();
+        // The MITM handler already knows the keys, and turns encryption on itself.
+        if status.mitm {
+            return Ok(());
+        }
+        match &status.secret_source {
+            Some(source) => (source.clone(), status.login.clone(), status.secret_timeout),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::MissingSecret("no secret source is set up".into()),
+                    0,
+                ))
             }
         }
+    };
+    let secret =
+        tokio::task::block_in_place(|| secrets::wait_for_secret(source.as_ref(), &login, timeout))
+            .map_err(|why| Error::new(ErrorKind::MissingSecret(why), 0))?;
 
-        state.client_cipher.enable(&decode(&secret_key).unwrap());
-        state.server_cipher.enable(&decode(&secret_key).unwrap());
+    let mut status = status.lock();
+    status.client_cipher.enable(&secret);
+    status.server_cipher.enable(&secret);
 
-        log::debug!("Updated cipher with secret key {}", secret_key);
-        Ok(())
-    }
+    log::debug!("Updated cipher with secret key {}", encode(&secret));
+    Ok(())
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct PluginResponse {
-    message_id: i32,
-    success: bool,
-    data: Vec<u8>,
+    pub message_id: i32,
+    pub success: bool,
+    #[serde(serialize_with = "json::bytes")]
+    pub data: Vec<u8>,
 }
 
 impl Parsable for PluginResponse {
@@ -133,11 +162,19 @@ impl Parsable for PluginResponse {
         }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.message_id = packet.decode_varint()?;
         self.success = packet.decode_bool()?;
         self.data = packet.get_vec();
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_varint(self.message_id);
+        packet.encode_bool(self.success);
+        packet.push_vec(self.data.clone());
+        packet
     }
 
     fn get_printable(&self) -> String {
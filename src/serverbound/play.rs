@@ -1 +1,551 @@
+use crate::error::{Error, ErrorKind};
+use crate::json;
+use crate::packet::{Field, Packet};
+use crate::slot::Slot;
+use mc_proxy_derive::Parsable;
+use serde::Serialize;
 
+//0x00
+#[derive(Clone, Parsable, Serialize)]
+pub struct TeleportConfirm {
+    #[varint]
+    pub teleport_id: i32,
+}
+
+//0x01
+#[derive(Clone, Parsable, Serialize)]
+pub struct QueryBlockNbt {
+    #[varint]
+    pub transaction_id: i32,
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+}
+
+//0x02
+#[derive(Clone, Parsable, Serialize)]
+pub struct SetDifficulty {
+    pub new_difficulty: i8,
+}
+
+//0x03
+#[derive(Clone, Parsable, Serialize)]
+pub struct ChatMessageServerbound {
+    pub message: String,
+}
+
+//0x04
+#[derive(Clone, Parsable, Serialize)]
+pub struct ClientStatus {
+    // 0 to respawn, 1 to ask for the statistics.
+    #[varint]
+    pub action_id: i32,
+}
+
+//0x05
+#[derive(Clone, Parsable, Serialize)]
+pub struct ClientSettings {
+    pub locale: String,
+    pub view_distance: i8,
+    #[varint]
+    pub chat_mode: i32,
+    pub chat_colors: bool,
+    pub displayed_skin_parts: u8,
+    #[varint]
+    pub main_hand: i32,
+}
+
+//0x06
+#[derive(Clone, Parsable, Serialize)]
+pub struct TabCompleteServerbound {
+    #[varint]
+    pub transaction_id: i32,
+    pub text: String,
+}
+
+//0x07
+#[derive(Clone, Parsable, Serialize)]
+pub struct WindowConfirmationServerbound {
+    pub window_id: i8,
+    pub action_number: i16,
+    pub accepted: bool,
+}
+
+//0x08
+#[derive(Clone, Parsable, Serialize)]
+pub struct ClickWindowButton {
+    pub window_id: i8,
+    pub button_id: i8,
+}
+
+//0x09
+#[derive(Clone, Parsable, Serialize)]
+pub struct ClickWindow {
+    pub window_id: u8,
+    pub slot: i16,
+    pub button: i8,
+    pub action_number: i16,
+    #[varint]
+    pub mode: i32,
+    pub clicked_item: Slot,
+}
+
+//0x0a
+#[derive(Clone, Parsable, Serialize)]
+pub struct CloseWindowServerbound {
+    pub window_id: u8,
+}
+
+//0x0b
+#[derive(Clone, Parsable, Serialize)]
+pub struct PluginMessageServerbound {
+    #[identifier]
+    pub channel: String,
+    #[serde(serialize_with = "json::bytes")]
+    pub data: Vec<u8>,
+}
+
+//0x0c
+#[derive(Clone, Parsable, Serialize)]
+pub struct EditBook {
+    pub new_book: Slot,
+    pub is_signing: bool,
+    #[varint]
+    pub hand: i32,
+}
+
+//0x0d
+#[derive(Clone, Parsable, Serialize)]
+pub struct QueryEntityNbt {
+    #[varint]
+    pub transaction_id: i32,
+    #[varint]
+    pub entity_id: i32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum InteractKind {
+    Interact {
+        hand: i32,
+    },
+    #[default]
+    Attack,
+    InteractAt {
+        target_x: f32,
+        target_y: f32,
+        target_z: f32,
+        hand: i32,
+    },
+}
+
+impl Field for InteractKind {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_varint()? {
+            0 => Self::Interact {
+                hand: packet.decode_varint()?,
+            },
+            1 => Self::Attack,
+            2 => Self::InteractAt {
+                target_x: packet.decode_float()?,
+                target_y: packet.decode_float()?,
+                target_z: packet.decode_float()?,
+                hand: packet.decode_varint()?,
+            },
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "InteractKind",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::Interact { hand } => {
+                packet.encode_varint(0);
+                packet.encode_varint(*hand);
+            }
+            Self::Attack => packet.encode_varint(1),
+            Self::InteractAt {
+                target_x,
+                target_y,
+                target_z,
+                hand,
+            } => {
+                packet.encode_varint(2);
+                packet.encode_float(*target_x);
+                packet.encode_float(*target_y);
+                packet.encode_float(*target_z);
+                packet.encode_varint(*hand);
+            }
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::Interact { hand } => format!("Interact {}", hand),
+            Self::Attack => "Attack".into(),
+            Self::InteractAt {
+                target_x,
+                target_y,
+                target_z,
+                hand,
+            } => format!("InteractAt {} {} {} {}", target_x, target_y, target_z, hand),
+        }
+    }
+}
+
+//0x0e
+#[derive(Clone, Parsable, Serialize)]
+pub struct InteractEntity {
+    #[varint]
+    pub entity_id: i32,
+    pub kind: InteractKind,
+    pub sneaking: bool,
+}
+
+//0x0f
+#[derive(Clone, Parsable, Serialize)]
+pub struct GenerateStructure {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    #[varint]
+    pub levels: i32,
+    pub keep_jigsaws: bool,
+}
+
+//0x10
+#[derive(Clone, Parsable, Serialize)]
+pub struct KeepAliveServerbound {
+    pub keep_alive_id: i64,
+}
+
+//0x11
+#[derive(Clone, Parsable, Serialize)]
+pub struct LockDifficulty {
+    pub locked: bool,
+}
+
+//0x12
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerPosition {
+    pub x: f64,
+    pub feet_y: f64,
+    pub z: f64,
+    pub on_ground: bool,
+}
+
+//0x13
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerPositionAndRotation {
+    pub x: f64,
+    pub feet_y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+//0x14
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerRotation {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+//0x15
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerMovement {
+    pub on_ground: bool,
+}
+
+//0x16
+#[derive(Clone, Parsable, Serialize)]
+pub struct VehicleMoveServerbound {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+//0x17
+#[derive(Clone, Parsable, Serialize)]
+pub struct SteerBoat {
+    pub left_paddle_turning: bool,
+    pub right_paddle_turning: bool,
+}
+
+//0x18
+#[derive(Clone, Parsable, Serialize)]
+pub struct PickItem {
+    #[varint]
+    pub slot_to_use: i32,
+}
+
+//0x19
+#[derive(Clone, Parsable, Serialize)]
+pub struct CraftRecipeRequest {
+    pub window_id: i8,
+    #[identifier]
+    pub recipe: String,
+    pub make_all: bool,
+}
+
+//0x1a
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerAbilitiesServerbound {
+    // Only 0x02 (flying) is used.
+    pub flags: i8,
+}
+
+//0x1b
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerDigging {
+    // Started, cancelled or finished digging, dropping items, shooting arrows or eating, and swapping hands.
+    #[varint]
+    pub status: i32,
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    pub face: i8,
+}
+
+//0x1c
+#[derive(Clone, Parsable, Serialize)]
+pub struct EntityAction {
+    #[varint]
+    pub entity_id: i32,
+    #[varint]
+    pub action_id: i32,
+    #[varint]
+    pub jump_boost: i32,
+}
+
+//0x1d
+#[derive(Clone, Parsable, Serialize)]
+pub struct SteerVehicle {
+    pub sideways: f32,
+    pub forward: f32,
+    pub flags: u8,
+}
+
+//0x1e
+#[derive(Clone, Parsable, Serialize)]
+pub struct SetRecipeBookState {
+    #[varint]
+    pub book_id: i32,
+    pub book_open: bool,
+    pub filter_active: bool,
+}
+
+//0x1f
+#[derive(Clone, Parsable, Serialize)]
+pub struct SetDisplayedRecipe {
+    #[identifier]
+    pub recipe_id: String,
+}
+
+//0x20
+#[derive(Clone, Parsable, Serialize)]
+pub struct NameItem {
+    pub item_name: String,
+}
+
+//0x21
+#[derive(Clone, Parsable, Serialize)]
+pub struct ResourcePackStatus {
+    #[varint]
+    pub result: i32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub enum AdvancementTabAction {
+    OpenedTab(String),
+    #[default]
+    ClosedScreen,
+}
+
+impl Field for AdvancementTabAction {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        Ok(match packet.decode_varint()? {
+            0 => Self::OpenedTab(packet.decode_identifier()?),
+            1 => Self::ClosedScreen,
+            value => {
+                return Err(packet.error(ErrorKind::UnknownEnumValue {
+                    name: "AdvancementTabAction",
+                    value: value as i64,
+                }))
+            }
+        })
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        match self {
+            Self::OpenedTab(tab_id) => {
+                packet.encode_varint(0);
+                packet.encode_identifier(tab_id);
+            }
+            Self::ClosedScreen => packet.encode_varint(1),
+        }
+    }
+
+    fn printable(&self) -> String {
+        match self {
+            Self::OpenedTab(tab_id) => format!("OpenedTab {}", tab_id),
+            Self::ClosedScreen => "ClosedScreen".into(),
+        }
+    }
+}
+
+//0x22
+#[derive(Clone, Parsable, Serialize)]
+pub struct AdvancementTab {
+    pub action: AdvancementTabAction,
+}
+
+//0x23
+#[derive(Clone, Parsable, Serialize)]
+pub struct SelectTrade {
+    #[varint]
+    pub selected_slot: i32,
+}
+
+//0x24
+#[derive(Clone, Parsable, Serialize)]
+pub struct SetBeaconEffect {
+    #[varint]
+    pub primary_effect: i32,
+    #[varint]
+    pub secondary_effect: i32,
+}
+
+//0x25
+#[derive(Clone, Parsable, Serialize)]
+pub struct HeldItemChangeServerbound {
+    pub slot: i16,
+}
+
+//0x26
+#[derive(Clone, Parsable, Serialize)]
+pub struct UpdateCommandBlock {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    pub command: String,
+    #[varint]
+    pub mode: i32,
+    pub flags: i8,
+}
+
+//0x27
+#[derive(Clone, Parsable, Serialize)]
+pub struct UpdateCommandBlockMinecart {
+    #[varint]
+    pub entity_id: i32,
+    pub command: String,
+    pub track_output: bool,
+}
+
+//0x28
+#[derive(Clone, Parsable, Serialize)]
+pub struct CreativeInventoryAction {
+    pub slot: i16,
+    pub clicked_item: Slot,
+}
+
+//0x29
+#[derive(Clone, Parsable, Serialize)]
+pub struct UpdateJigsawBlock {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    #[identifier]
+    pub name: String,
+    #[identifier]
+    pub target: String,
+    #[identifier]
+    pub pool: String,
+    pub final_state: String,
+    pub joint_type: String,
+}
+
+//0x2a
+#[derive(Clone, Parsable, Serialize)]
+pub struct UpdateStructureBlock {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    #[varint]
+    pub action: i32,
+    #[varint]
+    pub mode: i32,
+    pub name: String,
+    pub offset_x: i8,
+    pub offset_y: i8,
+    pub offset_z: i8,
+    pub size_x: i8,
+    pub size_y: i8,
+    pub size_z: i8,
+    #[varint]
+    pub mirror: i32,
+    #[varint]
+    pub rotation: i32,
+    pub metadata: String,
+    pub integrity: f32,
+    #[varlong]
+    pub seed: i64,
+    pub flags: i8,
+}
+
+//0x2b
+#[derive(Clone, Parsable, Serialize)]
+pub struct UpdateSign {
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    pub line_1: String,
+    pub line_2: String,
+    pub line_3: String,
+    pub line_4: String,
+}
+
+//0x2c
+#[derive(Clone, Parsable, Serialize)]
+pub struct Animation {
+    #[varint]
+    pub hand: i32,
+}
+
+//0x2d
+#[derive(Clone, Parsable, Serialize)]
+pub struct Spectate {
+    #[serde(serialize_with = "json::uuid")]
+    pub target_player: u128,
+}
+
+//0x2e
+#[derive(Clone, Parsable, Serialize)]
+pub struct PlayerBlockPlacement {
+    #[varint]
+    pub hand: i32,
+    #[position]
+    #[serde(serialize_with = "json::position")]
+    pub location: (i64, i64, i64),
+    #[varint]
+    pub face: i32,
+    pub cursor_position_x: f32,
+    pub cursor_position_y: f32,
+    pub cursor_position_z: f32,
+    pub inside_block: bool,
+}
+
+//0x2f
+#[derive(Clone, Parsable, Serialize)]
+pub struct UseItem {
+    #[varint]
+    pub hand: i32,
+}
@@ -1,6 +1,8 @@
+use crate::error::Error;
 use crate::packet::{Packet, Parsable};
+use serde::Serialize;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct StatusRequest {}
 
 impl Parsable for StatusRequest {
@@ -8,18 +10,22 @@ impl Parsable for StatusRequest {
         Self {}
     }
 
-    fn parse_packet(&mut self, mut _packet: Packet) -> Result<(), ()> {
-        return Ok(());
+    fn parse_packet(&mut self, mut _packet: Packet) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        Packet::with_version(protocol_version)
     }
 
     fn get_printable(&self) -> String {
-        format!("",)
+        String::new()
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct StatusPing {
-    payload: i64,
+    pub payload: i64,
 }
 
 impl Parsable for StatusPing {
@@ -27,9 +33,15 @@ impl Parsable for StatusPing {
         Self { payload: 0 }
     }
 
-    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), ()> {
+    fn parse_packet(&mut self, mut packet: Packet) -> Result<(), Error> {
         self.payload = packet.decode_long()?;
-        return Ok(());
+        Ok(())
+    }
+
+    fn serialize(&self, protocol_version: i32) -> Packet {
+        let mut packet = Packet::with_version(protocol_version);
+        packet.encode_long(self.payload);
+        packet
     }
 
     fn get_printable(&self) -> String {
@@ -0,0 +1,224 @@
+use crate::error::Error;
+use crate::nbt::{Nbt, Tag};
+use crate::packet::{Field, Packet};
+use crate::versions;
+use serde::Serialize;
+
+// 1.13.2 (from 1.13.2-pre1) starts slots with whether there's an item and sends the ID as a VarInt.
+pub const SLOT_PRESENT_PROTOCOL_VERSION: i32 = 402;
+
+// The item stack in a slot, an empty slot isn't `present` and has nothing else set.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Slot {
+    pub present: bool,
+    pub item_id: i32,
+    pub count: i8,
+    // Only sent before 1.13, the damage or the variant of the item. After that it's in the NBT.
+    pub damage: i16,
+    pub nbt: Nbt,
+}
+
+impl Slot {
+    // The enchantments on the item (or stored in a book) as their name and level.
+    pub fn enchantments(&self) -> Vec<(String, i64)> {
+        let tag = match &self.nbt.tag {
+            Some(tag) => tag,
+            None => return Vec::new(),
+        };
+        // 1.13 renamed `ench`, books kept their list. Both got names instead of numbers then.
+        let lists = ["Enchantments", "StoredEnchantments", "ench"];
+        let mut enchantments = Vec::new();
+        for list in lists.iter().filter_map(|name| tag.get(name)) {
+            if let Tag::List(_, entries) = list {
+                for entry in entries {
+                    let name = match entry.get("id") {
+                        Some(Tag::String(id)) => id.trim_start_matches("minecraft:").to_string(),
+                        Some(id) => format!("#{}", id.as_i64().unwrap_or(-1)),
+                        None => continue,
+                    };
+                    let level = entry.get("lvl").and_then(Tag::as_i64).unwrap_or(0);
+                    enchantments.push((name, level));
+                }
+            }
+        }
+        enchantments
+    }
+
+    // The name the item was given in an anvil. It's a chat component from 1.13 on.
+    pub fn custom_name(&self) -> Option<&str> {
+        match self.nbt.tag.as_ref()?.get("display")?.get("Name")? {
+            Tag::String(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl Field for Slot {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        let mut slot = Self::default();
+        if packet.protocol_version() >= SLOT_PRESENT_PROTOCOL_VERSION {
+            slot.present = packet.decode_bool()?;
+            if !slot.present {
+                return Ok(slot);
+            }
+            slot.item_id = packet.decode_varint()?;
+        } else {
+            slot.item_id = packet.decode_short()? as i32;
+            if slot.item_id == -1 {
+                return Ok(Self::default());
+            }
+            slot.present = true;
+        }
+        slot.count = packet.decode_byte()?;
+        if packet.protocol_version() < versions::V1_13 {
+            slot.damage = packet.decode_short()?;
+        }
+        slot.nbt = packet.decode_nbt()?;
+        Ok(slot)
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        if packet.protocol_version() >= SLOT_PRESENT_PROTOCOL_VERSION {
+            packet.encode_bool(self.present);
+            if !self.present {
+                return;
+            }
+            packet.encode_varint(self.item_id);
+        } else {
+            if !self.present {
+                packet.encode_short(-1);
+                return;
+            }
+            packet.encode_short(self.item_id as i16);
+        }
+        packet.encode_byte(self.count);
+        if packet.protocol_version() < versions::V1_13 {
+            packet.encode_short(self.damage);
+        }
+        packet.encode_nbt(&self.nbt);
+    }
+
+    // Something like `2x #603 {"text":"Excalibur"} [sharpness 5, unbreaking 3]`. The item IDs
+    // depend on the version, so there are no item names.
+    fn printable(&self) -> String {
+        if !self.present {
+            return "Empty".into();
+        }
+        let mut printable = format!("{}x #{}", self.count, self.item_id);
+        if self.damage != 0 {
+            printable.push_str(&format!(":{}", self.damage));
+        }
+        if let Some(name) = self.custom_name() {
+            printable.push_str(&format!(" {}", name));
+        }
+        let enchantments = self.enchantments();
+        if !enchantments.is_empty() {
+            let enchantments = enchantments
+                .iter()
+                .map(|(name, level)| format!("{} {}", name, level))
+                .collect::<Vec<String>>();
+            printable.push_str(&format!(" [{}]", enchantments.join(", ")));
+        }
+        printable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot() {
+        let enchantment = |id: &str, level: i16| {
+            Tag::Compound(vec![
+                ("id".into(), Tag::String(id.into())),
+                ("lvl".into(), Tag::Short(level)),
+            ])
+        };
+        let sword = Slot {
+            present: true,
+            item_id: 603,
+            count: 1,
+            damage: 0,
+            nbt: Nbt {
+                name: String::new(),
+                tag: Some(Tag::Compound(vec![
+                    ("Damage".into(), Tag::Int(12)),
+                    (
+                        "display".into(),
+                        Tag::Compound(vec![(
+                            "Name".into(),
+                            Tag::String(r#"{"text":"Excalibur"}"#.into()),
+                        )]),
+                    ),
+                    (
+                        "Enchantments".into(),
+                        Tag::List(
+                            10,
+                            vec![
+                                enchantment("minecraft:sharpness", 5),
+                                enchantment("minecraft:unbreaking", 3),
+                            ],
+                        ),
+                    ),
+                ])),
+            },
+        };
+        assert_eq!(
+            sword.printable(),
+            r#"1x #603 {"text":"Excalibur"} [sharpness 5, unbreaking 3]"#
+        );
+
+        let mut packet = Packet::new();
+        sword.encode(&mut packet);
+        Slot::default().encode(&mut packet);
+        assert_eq!(packet.get_slice()[..4], [0x01, 0xdb, 0x04, 0x01]);
+        assert_eq!(Slot::decode(&mut packet).unwrap(), sword);
+        assert_eq!(Slot::decode(&mut packet).unwrap(), Slot::default());
+        assert!(packet.is_empty());
+
+        // Before 1.13 there's no present flag and a damage value, 1.13 itself dropped the damage.
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        packet.push_vec(vec![0x01, 0x13, 0x40, 0x00, 0x02, 0x00, 0xff, 0xff]);
+        let item = Slot::decode(&mut packet).unwrap();
+        assert_eq!((item.item_id, item.count, item.damage), (275, 64, 2));
+        assert!(item.nbt.is_empty());
+        assert_eq!(item.printable(), "64x #275:2");
+        assert_eq!(Slot::decode(&mut packet).unwrap(), Slot::default());
+        let mut packet = Packet::with_version(versions::V1_12_2);
+        item.encode(&mut packet);
+        Slot::default().encode(&mut packet);
+        assert_eq!(
+            packet.get_vec(),
+            vec![0x01, 0x13, 0x40, 0x00, 0x02, 0x00, 0xff, 0xff]
+        );
+
+        let mut packet = Packet::with_version(versions::V1_13);
+        packet.push_vec(vec![0x01, 0x13, 0x40, 0x00]);
+        let slot = Slot::decode(&mut packet).unwrap();
+        assert_eq!((slot.item_id, slot.count, slot.damage), (275, 64, 0));
+        assert!(packet.is_empty());
+
+        // Old books have numbered enchantments.
+        let book = Slot {
+            present: true,
+            item_id: 403,
+            count: 1,
+            damage: 0,
+            nbt: Nbt {
+                name: String::new(),
+                tag: Some(Tag::Compound(vec![(
+                    "StoredEnchantments".into(),
+                    Tag::List(
+                        10,
+                        vec![Tag::Compound(vec![
+                            ("id".into(), Tag::Short(16)),
+                            ("lvl".into(), Tag::Short(4)),
+                        ])],
+                    ),
+                )])),
+            },
+        };
+        assert_eq!(book.printable(), "1x #403 [#16 4]");
+    }
+}
@@ -1,7 +1,19 @@
+use crate::capture::Recorder;
 use crate::cipher::Cipher;
-use serde::Deserialize;
+use crate::download::Downloader;
+use crate::json;
+use crate::secrets::{Login, SecretSource};
+use crate::world::World;
+use serde::{Deserialize, Serialize};
 use std::fmt;
-#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+// What the old log scraping looked for, the secret is printed as base64.
+pub const DEFAULT_SECRET_REGEX: &str = r"\[STDOUT\]: Secret Key: (?P<secret>\S+)";
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum State {
     Handshaking,
     Status,
@@ -21,24 +33,83 @@ impl fmt::Display for State {
 }
 
 pub struct Status {
-    pub compress: u32,
+    // The compression threshold, None if compression is turned off.
+    pub compress: Option<u32>,
     pub state: State,
+    pub protocol_version: i32,
     pub client_cipher: Cipher,
     pub server_cipher: Cipher,
+    // If the proxy does the encryption itself, instead of getting the key from the client.
+    pub mitm: bool,
+    // Where the shared secret comes from when the proxy doesn't do the encryption itself.
+    pub secret_source: Option<Arc<dyn SecretSource>>,
+    pub secret_timeout: Duration,
+    // Set once the serverbound side is done with the EncResponse, whether it got the secret or not.
+    pub secret_done: bool,
+    // Wakes up the clientbound parser when `secret_done` is set.
+    pub secret_ready: Arc<Notify>,
+    // The login that's going on, so the right secret can be found.
+    pub login: Login,
+    // If the server sent an EncRequest, offline-mode servers never do.
+    pub online_mode: bool,
+    // The UUID of the player, once the server has sent it.
+    pub uuid: Option<u128>,
+    // The chunks and blocks the server has sent so far.
+    pub world: World,
+    // Saves the chunks before the world forgets about them, if the world downloader is on.
+    pub download: Option<Arc<Downloader>>,
+    // Records the connection, if captures are on.
+    pub capture: Option<Recorder>,
+    // Which connection this is, counting from 0 since the proxy started. It's in the JSON output.
+    pub connection: usize,
+    // Where the printed packets are written as JSON too, if that's on.
+    pub json: Option<Arc<json::Output>>,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Status {
     pub fn new() -> Status {
         Status {
-            compress: 0,
+            compress: None,
             state: State::Handshaking,
+            protocol_version: crate::packet::DEFAULT_PROTOCOL_VERSION,
             client_cipher: Cipher::new(),
             server_cipher: Cipher::new(),
+            mitm: false,
+            secret_source: None,
+            secret_timeout: Duration::from_millis(DEFAULT_SECRET_TIMEOUT),
+            secret_done: false,
+            secret_ready: Arc::new(Notify::new()),
+            login: Login::default(),
+            online_mode: false,
+            uuid: None,
+            world: World::default(),
+            download: None,
+            capture: None,
+            connection: 0,
+            json: None,
         }
     }
+
+    // Once the client has answered an EncRequest the server encrypts everything, so what it sends
+    // after the EncRequest can't be read until the secret is there.
+    pub fn waits_for_secret(&self) -> bool {
+        self.state == State::Login && self.online_mode && !self.mitm && !self.secret_done
+    }
+
+    pub fn finish_secret(&mut self) {
+        self.secret_done = true;
+        self.secret_ready.notify_one();
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction {
     Serverbound,
     Clientbound,
@@ -60,4 +131,151 @@ pub struct ConfigFormat {
     pub listen_ip: String,
     pub printing_packets: Vec<String>,
     pub parsing_packets: Vec<String>,
+    // In active mode all traffic goes through the parser and the handlers before it's sent on.
+    #[serde(default)]
+    pub active_mode: bool,
+    #[serde(default)]
+    pub dropping_packets: Vec<String>,
+    // If this is set the proxy logs in to online-mode servers itself, which also turns on active mode.
+    #[serde(default)]
+    pub mitm: Option<MitmConfig>,
+    #[serde(default)]
+    pub secret_source: SecretSourceConfig,
+    // How long to wait for the secret to show up, in milliseconds.
+    #[serde(default = "default_secret_timeout")]
+    pub secret_timeout: u64,
+    // A TOML or JSON file with packets that don't have a parser in the code.
+    #[serde(default)]
+    pub packet_schema: Option<String>,
+    // If this is set the chunks the client gets are saved to a world.
+    #[serde(default)]
+    pub download: Option<DownloadConfig>,
+    // Every connection is recorded to a capture file in this folder, except server list pings.
+    #[serde(default)]
+    pub capture: Option<String>,
+    // If this is set the proxy doesn't connect anywhere, it plays a capture back to the clients.
+    #[serde(default)]
+    pub replay: Option<ReplayConfig>,
+    // The printed packets are also written here as JSON, a line each. `-` is stdout.
+    #[serde(default)]
+    pub json_output: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ReplayConfig {
+    pub capture: String,
+    // How much faster than recorded it's played, 0 is as fast as possible.
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+    // Teleport the client to where the recorded player moved.
+    #[serde(default = "default_follow_player")]
+    pub follow_player: bool,
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+fn default_follow_player() -> bool {
+    true
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DownloadConfig {
+    // The world folder, it's made if it isn't there. Chunks already in it are overwritten.
+    pub directory: String,
+    // The block report of the server's version, to give the block state IDs their names.
+    pub blocks: String,
+}
+
+impl ConfigFormat {
+    // Reads the config file, the extension can be left out.
+    pub fn load(name: &str) -> Result<Self, String> {
+        let mut settings = config::Config::default();
+        settings
+            .merge(config::File::with_name(name))
+            .map_err(|err| err.to_string())?;
+        settings.try_into().map_err(|err| err.to_string())
+    }
+
+    pub fn parses(&self, name: &str) -> bool {
+        self.parsing_packets
+            .iter()
+            .any(|packet| packet == name || packet == "*")
+    }
+
+    pub fn prints(&self, name: &str) -> bool {
+        self.printing_packets
+            .iter()
+            .any(|packet| packet == name || packet == "*")
+    }
+}
+
+// Where to get the shared secret from, in the config file it's a table with a `type`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SecretSourceConfig {
+    // A log file, the regex has to have a `secret` group (or just one group) and can have a `username` group.
+    LogFile {
+        path: Option<String>,
+        #[serde(default = "default_secret_regex")]
+        regex: String,
+    },
+    // A file with the secret, or lines of a username and a secret.
+    KeyFile {
+        path: String,
+    },
+    // An environment variable with the secret.
+    Env {
+        name: String,
+    },
+    // A socket (`tcp:<ip>:<port>` or `unix:<path>`) an agent sends the secrets to.
+    Socket {
+        address: String,
+    },
+}
+
+impl Default for SecretSourceConfig {
+    fn default() -> Self {
+        Self::LogFile {
+            path: None,
+            regex: default_secret_regex(),
+        }
+    }
+}
+
+fn default_secret_regex() -> String {
+    DEFAULT_SECRET_REGEX.into()
+}
+
+const DEFAULT_SECRET_TIMEOUT: u64 = 2000;
+
+fn default_secret_timeout() -> u64 {
+    DEFAULT_SECRET_TIMEOUT
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MitmConfig {
+    #[serde(default = "default_session_server")]
+    pub session_server: String,
+    // The access token and profile (UUID) of the account the proxy joins the server with.
+    #[serde(default)]
+    pub access_token: String,
+    #[serde(default)]
+    pub profile: String,
+    // Check with the session server that the client really is who it says it is.
+    #[serde(default = "default_verify_client")]
+    pub verify_client: bool,
+    // Like BungeeCord: the client logs in to the proxy in online mode,
+    // and the proxy talks to an offline-mode server (so no account is needed).
+    #[serde(default)]
+    pub offline_backend: bool,
+}
+
+fn default_session_server() -> String {
+    "https://sessionserver.mojang.com".into()
+}
+
+fn default_verify_client() -> bool {
+    true
 }
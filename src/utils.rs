@@ -1,14 +1,126 @@
+use crate::types::Direction;
+use colored::*;
+use env_logger::Builder;
+use log::LevelFilter;
+use std::io::Write;
+
+// Both binaries log the same way, it has a fancy format with colours and it's spaced.
+pub fn init_logger() {
+    Builder::from_default_env()
+        .format(|buf, record| {
+            let formatted_level = buf.default_styled_level(record.level());
+            writeln!(buf, "{:<5} {}", formatted_level, record.args())
+        })
+        .filter_level(LevelFilter::Info)
+        .parse_default_env()
+        .init();
+}
+
+// Prints a parsed packet (with fancy colours), the names are padded so the packets line up.
+pub fn log_packet(direction: &Direction, name: &str, printable: &str) {
+    log::info!(
+        "{} [{}]{3:4$} {}",
+        direction.to_string().yellow(),
+        name.blue(),
+        printable,
+        "",
+        20usize.saturating_sub(name.len())
+    )
+}
+
 // This converts a long string into one that's shortened.
 // alongstringlikethis would become alongs...kethis
 pub fn make_string_fixed_length(string: String, length: usize) -> String {
     if string.len() <= length {
-        return string;
+        string
     } else {
         let part_size = length - 3 / 2;
         format!(
             "{}...{}",
-            string[0..part_size].to_string(),
-            string[string.len() - part_size..].to_string()
+            &string[0..part_size],
+            &string[string.len() - part_size..]
         )
     }
 }
+
+// Offline-mode servers make the UUID of a player out of their name, it's a version 3 UUID
+// of `OfflinePlayer:<name>` (which is what Java's UUID.nameUUIDFromBytes does).
+pub fn offline_uuid(username: &str) -> u128 {
+    use md5::{Digest, Md5};
+    let mut hash: [u8; 16] = Md5::digest(format!("OfflinePlayer:{}", username).as_bytes()).into();
+    hash[6] = (hash[6] & 0x0f) | 0x30;
+    hash[8] = (hash[8] & 0x3f) | 0x80;
+    u128::from_be_bytes(hash)
+}
+
+// Prints a UUID the usual way, with the dashes.
+pub fn format_uuid(uuid: u128) -> String {
+    let hex = format!("{:032x}", uuid);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+// The other way around, it also takes them without dashes.
+pub fn parse_uuid(uuid: &str) -> Option<u128> {
+    let hex = uuid.replace('-', "");
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u128::from_str_radix(&hex, 16).ok()
+}
+
+// A tiny random number generator so the fuzz tests don't need any extra dependencies.
+// It always starts from the same seed, so a failing run can be reproduced.
+#[cfg(test)]
+pub struct Rng(u64);
+
+#[cfg(test)]
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, max: usize) -> usize {
+        (self.next_u64() % max.max(1) as u64) as usize
+    }
+
+    pub fn bytes(&mut self, length: usize) -> Vec<u8> {
+        (0..length).map(|_| self.next_u64() as u8).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_uuid() {
+        let values = vec![
+            ("Notch", "b50ad385-829d-3141-a216-7e7d7539ba7f"),
+            ("jeb_", "a762f560-4fce-3236-812a-b80efff0b62b"),
+        ];
+        for (username, uuid) in values {
+            assert_eq!(format_uuid(offline_uuid(username)), uuid);
+            assert_eq!(parse_uuid(uuid), Some(offline_uuid(username)));
+        }
+        assert_eq!(
+            parse_uuid("069a79f444e94726a5befca90e38aaf5"),
+            Some(0x069a79f444e94726a5befca90e38aaf5)
+        );
+        assert_eq!(parse_uuid("+69a79f444e94726a5befca90e38aaf5"), None);
+        assert_eq!(parse_uuid("069a79f4"), None);
+    }
+}
@@ -0,0 +1,50 @@
+// The protocol versions the proxy knows about. Only the first version of a group of versions
+// with the same packets is here, anything in between is parsed like the one before it.
+pub const V1_12_2: i32 = 340;
+pub const V1_13: i32 = 393;
+pub const V1_14: i32 = 477;
+pub const V1_15: i32 = 573;
+pub const V1_16: i32 = 735;
+pub const V1_16_2: i32 = 751;
+pub const V1_16_5: i32 = 754;
+
+// Anything older than this changed too much to be parsed.
+pub const OLDEST_SUPPORTED: i32 = V1_12_2;
+pub const NEWEST_SUPPORTED: i32 = V1_16_5;
+
+pub fn is_supported(protocol_version: i32) -> bool {
+    (OLDEST_SUPPORTED..=NEWEST_SUPPORTED).contains(&protocol_version)
+}
+
+// The version packets are actually parsed as, unsupported versions are parsed as the newest one.
+pub fn parsing_version(protocol_version: i32) -> i32 {
+    if is_supported(protocol_version) {
+        protocol_version
+    } else {
+        NEWEST_SUPPORTED
+    }
+}
+
+// The game version a protocol version belongs to, for the logs.
+pub fn name(protocol_version: i32) -> &'static str {
+    match protocol_version {
+        754 => "1.16.5",
+        753 => "1.16.3",
+        751 => "1.16.2",
+        736 => "1.16.1",
+        735 => "1.16",
+        578 => "1.15.2",
+        575 => "1.15.1",
+        573 => "1.15",
+        498 => "1.14.4",
+        490 => "1.14.3",
+        485 => "1.14.2",
+        480 => "1.14.1",
+        477 => "1.14",
+        404 => "1.13.2",
+        401 => "1.13.1",
+        393 => "1.13",
+        340 => "1.12.2",
+        _ => "unknown",
+    }
+}
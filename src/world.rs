@@ -0,0 +1,566 @@
+use crate::error::{Error, ErrorKind};
+use crate::nbt::Tag;
+use crate::packet::{Field, Packet};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// A chunk section is 16x16x16 blocks, there are 16 of them on top of each other in a chunk.
+pub const SECTION_BLOCKS: usize = 4096;
+pub const SECTIONS: usize = 16;
+// Light is also sent for the sections right below and above the world, so there are two more.
+pub const LIGHT_SECTIONS: usize = SECTIONS + 2;
+// Half a byte of light per block.
+pub const LIGHT_ARRAY_LENGTH: usize = SECTION_BLOCKS / 2;
+// Up to this many bits per block the values point into the section's palette, above it they're
+// block state IDs. The client always uses at least 4 bits for a palette.
+pub const MAX_PALETTE_BITS: u8 = 8;
+pub const MIN_PALETTE_BITS: u8 = 4;
+// The bits a block state ID takes, what the server sends for sections without a palette in 1.16.
+pub const GLOBAL_BITS: u8 = 15;
+
+// One section in the paletted container format 1.16 uses. The values are packed into longs
+// from the lowest bit up, since 1.16 they don't cross from one long into the next.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ChunkSection {
+    // The number of blocks that aren't air, the client uses it to skip empty sections.
+    pub block_count: i16,
+    pub bits_per_block: u8,
+    // Empty when the values are block state IDs themselves.
+    pub palette: Vec<i32>,
+    pub data: Vec<i64>,
+}
+
+impl Default for ChunkSection {
+    // A section full of air.
+    fn default() -> Self {
+        Self {
+            block_count: 0,
+            bits_per_block: MIN_PALETTE_BITS,
+            palette: vec![0],
+            data: vec![0; data_length(MIN_PALETTE_BITS)],
+        }
+    }
+}
+
+fn data_length(bits: u8) -> usize {
+    let per_long = 64 / bits as usize;
+    SECTION_BLOCKS.div_ceil(per_long)
+}
+
+// The index of a block in a section (or a light array), x goes up first, then z, then y.
+fn block_index(x: usize, y: usize, z: usize) -> usize {
+    y << 8 | z << 4 | x
+}
+
+fn set_packed(data: &mut [i64], bits: u8, index: usize, value: u64) {
+    let bits = bits as usize;
+    let per_long = 64 / bits;
+    let shift = index % per_long * bits;
+    let mask = ((1u64 << bits) - 1) << shift;
+    let long = &mut data[index / per_long];
+    *long = ((*long as u64 & !mask) | (value << shift & mask)) as i64;
+}
+
+// Packs values into longs with the bits given, the way sections have them.
+pub fn pack(values: &[u64], bits: u8) -> Vec<i64> {
+    let mut data = vec![0; values.len().div_ceil(64 / bits as usize)];
+    for (index, value) in values.iter().enumerate() {
+        set_packed(&mut data, bits, index, *value);
+    }
+    data
+}
+
+impl ChunkSection {
+    fn has_palette(&self) -> bool {
+        self.bits_per_block <= MAX_PALETTE_BITS
+    }
+
+    // The bits the values actually take up.
+    fn bits(&self) -> u8 {
+        if self.has_palette() {
+            self.bits_per_block.max(MIN_PALETTE_BITS)
+        } else {
+            self.bits_per_block
+        }
+    }
+
+    fn value(&self, index: usize) -> u64 {
+        let bits = self.bits() as usize;
+        let per_long = 64 / bits;
+        let long = self.data[index / per_long] as u64;
+        let mask = (1u64 << bits) - 1;
+        long >> (index % per_long * bits) & mask
+    }
+
+    fn set_value(&mut self, index: usize, value: u64) {
+        let bits = self.bits();
+        set_packed(&mut self.data, bits, index, value);
+    }
+
+    // The block state ID at the position in the section. Values that point outside of the
+    // palette are air.
+    pub fn get(&self, x: usize, y: usize, z: usize) -> i32 {
+        let value = self.value(block_index(x, y, z));
+        match self.has_palette() {
+            true => self.palette.get(value as usize).copied().unwrap_or(0),
+            false => value as i32,
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, state: i32) {
+        let index = block_index(x, y, z);
+        // Cave and void air count as blocks here, the client itself knows better.
+        match (self.get(x, y, z) == 0, state == 0) {
+            (true, false) => self.block_count += 1,
+            (false, true) => self.block_count -= 1,
+            _ => {}
+        }
+        let value = match self.palette.iter().position(|entry| *entry == state) {
+            _ if !self.has_palette() => state as u64,
+            Some(value) => value as u64,
+            None => {
+                self.palette.push(state);
+                if self.palette.len() > 1 << self.bits() {
+                    self.resize();
+                }
+                self.value_of(state)
+            }
+        };
+        self.set_value(index, value);
+    }
+
+    // What's stored for a block state, it has to be in the palette if there is one.
+    fn value_of(&self, state: i32) -> u64 {
+        match self.has_palette() {
+            true => self
+                .palette
+                .iter()
+                .position(|entry| *entry == state)
+                .unwrap_or(0) as u64,
+            false => state as u64,
+        }
+    }
+
+    // Makes room for a palette that got too big, going without one after 8 bits. Values that
+    // pointed outside of the palette are air, which the server's palette doesn't always have,
+    // so the states that are missing are added.
+    fn resize(&mut self) {
+        let states = (0..SECTION_BLOCKS)
+            .map(|index| self.value(index))
+            .map(|value| self.palette.get(value as usize).copied().unwrap_or(0))
+            .collect::<Vec<i32>>();
+        let mut palette = self.palette.clone();
+        for state in &states {
+            if !palette.contains(state) {
+                palette.push(*state);
+            }
+        }
+        let mut bits = self.bits() + 1;
+        while bits <= MAX_PALETTE_BITS && palette.len() > 1 << bits {
+            bits += 1;
+        }
+        let mut resized = if bits > MAX_PALETTE_BITS {
+            Self {
+                block_count: self.block_count,
+                bits_per_block: GLOBAL_BITS,
+                palette: Vec::new(),
+                data: vec![0; data_length(GLOBAL_BITS)],
+            }
+        } else {
+            Self {
+                block_count: self.block_count,
+                bits_per_block: bits,
+                palette,
+                data: vec![0; data_length(bits)],
+            }
+        };
+        for (index, state) in states.into_iter().enumerate() {
+            let value = resized.value_of(state);
+            resized.set_value(index, value);
+        }
+        *self = resized;
+    }
+}
+
+impl Field for ChunkSection {
+    fn decode(packet: &mut Packet) -> Result<Self, Error> {
+        let block_count = packet.decode_short()?;
+        let bits_per_block = packet.decode_ubyte()?;
+        if !(1..=32).contains(&bits_per_block) {
+            return Err(packet.error(ErrorKind::UnknownEnumValue {
+                name: "bits per block",
+                value: bits_per_block as i64,
+            }));
+        }
+        let mut section = Self {
+            block_count,
+            bits_per_block,
+            palette: Vec::new(),
+            data: Vec::new(),
+        };
+        if section.has_palette() {
+            let count = packet.decode_count()?;
+            for _ in 0..count {
+                section.palette.push(packet.decode_varint()?);
+            }
+        }
+        // The length is sent, but it can only be one thing.
+        let expected = data_length(section.bits());
+        let length = packet.decode_varint()?;
+        if length as usize != expected {
+            return Err(packet.error(ErrorKind::InvalidLength {
+                length: length as i64,
+                max: expected,
+            }));
+        }
+        section.data = Vec::with_capacity(expected);
+        for _ in 0..expected {
+            section.data.push(packet.decode_long()?);
+        }
+        Ok(section)
+    }
+
+    fn encode(&self, packet: &mut Packet) {
+        packet.encode_short(self.block_count);
+        packet.encode_ubyte(self.bits_per_block);
+        if self.has_palette() {
+            packet.encode_varint(self.palette.len() as i32);
+            for state in &self.palette {
+                packet.encode_varint(*state);
+            }
+        }
+        packet.encode_varint(self.data.len() as i32);
+        for long in &self.data {
+            packet.encode_long(*long);
+        }
+    }
+
+    fn printable(&self) -> String {
+        format!(
+            "{} blocks, {} bits, {} in the palette",
+            self.block_count,
+            self.bits_per_block,
+            self.palette.len()
+        )
+    }
+}
+
+// The sky and block light of a chunk, from the section below the world up to the one above it.
+// A section that hasn't been sent is None.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Light {
+    pub sky: Vec<Option<Vec<u8>>>,
+    pub block: Vec<Option<Vec<u8>>>,
+}
+
+fn light_at(arrays: &[Option<Vec<u8>>], section: usize, index: usize) -> Option<u8> {
+    let array = arrays.get(section)?.as_ref()?;
+    let byte = *array.get(index / 2)?;
+    Some(if index.is_multiple_of(2) {
+        byte & 0x0f
+    } else {
+        byte >> 4
+    })
+}
+
+// The block entities of a chunk by their position.
+pub type BlockEntities = HashMap<(i64, i64, i64), Tag>;
+
+// The part of the world the proxy has seen, kept per connection. It's built from the chunk,
+// block change and light packets and forgotten when the player changes worlds.
+#[derive(Clone, Debug, Default)]
+pub struct World {
+    // The sections of the loaded chunks, from the bottom up. Sections that were empty are None.
+    pub chunks: HashMap<(i32, i32), Vec<Option<ChunkSection>>>,
+    // Light is sent before the chunk itself, so it's kept apart.
+    pub light: HashMap<(i32, i32), Light>,
+    // The biome of every 4x4x4 blocks, only sent with full chunks.
+    pub biomes: HashMap<(i32, i32), Vec<i32>>,
+    // The NBT of the block entities in a chunk by their position, with their id and position in it.
+    pub block_entities: HashMap<(i32, i32), BlockEntities>,
+    // The name of the dimension, like `minecraft:overworld`.
+    pub dimension: String,
+    // Where compasses point, the spawn of the world.
+    pub spawn: Option<(i64, i64, i64)>,
+}
+
+impl World {
+    // Takes out what's loaded, so it can be saved without the status locked. The dimension and
+    // the spawn stay, they're still the same world.
+    pub fn take(&mut self) -> World {
+        let world = std::mem::take(self);
+        self.dimension = world.dimension.clone();
+        self.spawn = world.spawn;
+        world
+    }
+
+    // Full chunks replace what was there, others only the sections in the mask. The sections
+    // are in the order of the mask, from the lowest bit up.
+    pub fn load_chunk(
+        &mut self,
+        chunk: (i32, i32),
+        full_chunk: bool,
+        mask: i32,
+        sections: &[ChunkSection],
+    ) {
+        let column = self
+            .chunks
+            .entry(chunk)
+            .or_insert_with(|| vec![None; SECTIONS]);
+        let mut sections = sections.iter();
+        for (y, section) in column.iter_mut().enumerate() {
+            if mask & 1 << y != 0 {
+                *section = sections.next().cloned();
+            } else if full_chunk {
+                *section = None;
+            }
+        }
+        if full_chunk {
+            self.block_entities.remove(&chunk);
+        }
+    }
+
+    pub fn unload_chunk(&mut self, chunk: (i32, i32)) {
+        self.chunks.remove(&chunk);
+        self.light.remove(&chunk);
+        self.biomes.remove(&chunk);
+        self.block_entities.remove(&chunk);
+    }
+
+    // Adds, replaces or (with None) removes the block entity at a position in a loaded chunk.
+    // The position in the NBT is set to where it is, so it can be saved as it is.
+    pub fn set_block_entity(&mut self, (x, y, z): (i64, i64, i64), tag: Option<Tag>) {
+        let chunk = ((x >> 4) as i32, (z >> 4) as i32);
+        if !self.chunks.contains_key(&chunk) {
+            return;
+        }
+        let block_entities = self.block_entities.entry(chunk).or_default();
+        match tag {
+            Some(mut tag) => {
+                tag.insert("x", Tag::Int(x as i32));
+                tag.insert("y", Tag::Int(y as i32));
+                tag.insert("z", Tag::Int(z as i32));
+                block_entities.insert((x, y, z), tag);
+            }
+            None => {
+                block_entities.remove(&(x, y, z));
+            }
+        }
+    }
+
+    // The block state ID at a position, None if that chunk isn't loaded or it's outside the world.
+    pub fn get_block(&self, (x, y, z): (i64, i64, i64)) -> Option<i32> {
+        if !(0..(SECTIONS * 16) as i64).contains(&y) {
+            return None;
+        }
+        let column = self.chunks.get(&((x >> 4) as i32, (z >> 4) as i32))?;
+        Some(match &column[(y >> 4) as usize] {
+            Some(section) => section.get((x & 15) as usize, (y & 15) as usize, (z & 15) as usize),
+            None => 0,
+        })
+    }
+
+    // Changes a block in a loaded chunk, the server only sends changes for those.
+    pub fn set_block(&mut self, (x, y, z): (i64, i64, i64), state: i32) {
+        if !(0..(SECTIONS * 16) as i64).contains(&y) {
+            return;
+        }
+        let column = match self.chunks.get_mut(&((x >> 4) as i32, (z >> 4) as i32)) {
+            Some(column) => column,
+            None => return,
+        };
+        // A block that's broken takes its block entity with it.
+        if state == 0 {
+            if let Some(block_entities) = self
+                .block_entities
+                .get_mut(&((x >> 4) as i32, (z >> 4) as i32))
+            {
+                block_entities.remove(&(x, y, z));
+            }
+        }
+        column[(y >> 4) as usize]
+            .get_or_insert_with(ChunkSection::default)
+            .set(
+                (x & 15) as usize,
+                (y & 15) as usize,
+                (z & 15) as usize,
+                state,
+            );
+    }
+
+    // Sets the light arrays for the bits in the masks, in the order of the bits. Sections in the
+    // empty masks are all dark.
+    pub fn update_light(
+        &mut self,
+        chunk: (i32, i32),
+        masks: (i32, i32),
+        empty_masks: (i32, i32),
+        arrays: (&[Vec<u8>], &[Vec<u8>]),
+    ) {
+        let light = self.light.entry(chunk).or_insert_with(|| Light {
+            sky: vec![None; LIGHT_SECTIONS],
+            block: vec![None; LIGHT_SECTIONS],
+        });
+        let update =
+            |sections: &mut Vec<Option<Vec<u8>>>, mask: i32, empty: i32, arrays: &[Vec<u8>]| {
+                let mut arrays = arrays.iter();
+                for (y, section) in sections.iter_mut().enumerate() {
+                    if mask & 1 << y != 0 {
+                        *section = arrays.next().cloned();
+                    } else if empty & 1 << y != 0 {
+                        *section = Some(vec![0; LIGHT_ARRAY_LENGTH]);
+                    }
+                }
+            };
+        update(&mut light.sky, masks.0, empty_masks.0, arrays.0);
+        update(&mut light.block, masks.1, empty_masks.1, arrays.1);
+    }
+
+    // The sky and block light at a position, None for the ones that haven't been sent.
+    pub fn get_light(&self, (x, y, z): (i64, i64, i64)) -> (Option<u8>, Option<u8>) {
+        let light = match self.light.get(&((x >> 4) as i32, (z >> 4) as i32)) {
+            Some(light) if (-16..(SECTIONS as i64 + 1) * 16).contains(&y) => light,
+            _ => return (None, None),
+        };
+        let section = ((y >> 4) + 1) as usize;
+        let index = block_index((x & 15) as usize, (y & 15) as usize, (z & 15) as usize);
+        (
+            light_at(&light.sky, section, index),
+            light_at(&light.block, section, index),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section() {
+        let mut section = ChunkSection::default();
+        assert_eq!(section.get(3, 4, 5), 0);
+        section.set(3, 4, 5, 1);
+        section.set(15, 15, 15, 9);
+        assert_eq!(section.get(3, 4, 5), 1);
+        assert_eq!(section.get(15, 15, 15), 9);
+        assert_eq!(section.block_count, 2);
+        assert_eq!(section.palette, vec![0, 1, 9]);
+
+        // 17 states don't fit in 4 bits, 257 not in a palette at all.
+        for state in 0..300 {
+            let index = state as usize;
+            section.set(index % 16, 8 + index / 256, index / 16 % 16, 100 + state);
+            if state == 15 {
+                assert_eq!(section.bits_per_block, 5);
+            }
+        }
+        assert_eq!(section.bits_per_block, GLOBAL_BITS);
+        assert!(section.palette.is_empty());
+        assert_eq!(section.data.len(), 1024);
+        assert_eq!(section.get(3, 4, 5), 1);
+        assert_eq!(section.get(15, 15, 15), 9);
+        assert_eq!(section.get(4, 8, 2), 136);
+        assert_eq!(section.block_count, 302);
+        section.set(3, 4, 5, 0);
+        assert_eq!(section.block_count, 301);
+
+        let mut packet = Packet::new();
+        section.encode(&mut packet);
+        assert_eq!(packet.get_slice()[..3], [0x01, 0x2d, GLOBAL_BITS]);
+        assert_eq!(ChunkSection::decode(&mut packet).unwrap(), section);
+        assert!(packet.is_empty());
+
+        // Values don't cross into the next long, with 5 bits there are 12 in each one.
+        let mut packet = Packet::new();
+        packet.encode_short(1);
+        packet.encode_ubyte(5);
+        packet.encode_varint(2);
+        packet.encode_varint(0);
+        packet.encode_varint(33);
+        packet.encode_varint(342);
+        packet.encode_long(0);
+        packet.encode_long(1);
+        for _ in 0..340 {
+            packet.encode_long(0);
+        }
+        let section = ChunkSection::decode(&mut packet).unwrap();
+        assert_eq!(section.get(12, 0, 0), 33);
+        assert_eq!(section.get(11, 0, 0), 0);
+        assert_eq!(section.get(13, 0, 0), 0);
+
+        for (bits, length) in &[(0, 0), (40, 0), (4, 255)] {
+            let mut packet = Packet::new();
+            packet.encode_short(0);
+            packet.encode_ubyte(*bits);
+            packet.encode_varint(0);
+            packet.encode_varint(*length);
+            assert!(ChunkSection::decode(&mut packet).is_err());
+        }
+    }
+
+    #[test]
+    fn test_resize_without_air() {
+        // Stone and dirt from the server, with a value that points outside of the palette.
+        let mut section = ChunkSection {
+            block_count: 4096,
+            bits_per_block: 4,
+            palette: vec![1, 10],
+            data: vec![0; 256],
+        };
+        section.set_value(1, 1);
+        section.set_value(2, 7);
+        section.resize();
+        assert_eq!(section.bits_per_block, 5);
+        assert_eq!(section.palette, vec![1, 10, 0]);
+        assert_eq!(section.get(0, 0, 0), 1);
+        assert_eq!(section.get(1, 0, 0), 10);
+        assert_eq!(section.get(2, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_world() {
+        let mut world = World::default();
+        let mut stone = ChunkSection::default();
+        stone.set(0, 0, 0, 1);
+        world.load_chunk((-1, 2), true, 0b10, &[stone.clone()]);
+        assert_eq!(world.get_block((-16, 16, 32)), Some(1));
+        assert_eq!(world.get_block((-15, 16, 32)), Some(0));
+        assert_eq!(world.get_block((-16, 0, 32)), Some(0));
+        assert_eq!(world.get_block((0, 16, 32)), None);
+        assert_eq!(world.get_block((-16, 256, 32)), None);
+
+        world.set_block((-1, 200, 47), 9);
+        assert_eq!(world.get_block((-1, 200, 47)), Some(9));
+        world.set_block((0, 16, 32), 9);
+        assert_eq!(world.get_block((0, 16, 32)), None);
+
+        // Only the sections in the mask change, unless it's a full chunk.
+        world.load_chunk((-1, 2), false, 0b1, &[stone.clone()]);
+        assert_eq!(world.get_block((-16, 0, 32)), Some(1));
+        assert_eq!(world.get_block((-16, 16, 32)), Some(1));
+        world.load_chunk((-1, 2), true, 0b1, &[stone]);
+        assert_eq!(world.get_block((-16, 16, 32)), Some(0));
+
+        let mut sky = vec![0xff; LIGHT_ARRAY_LENGTH];
+        sky[0] = 0x3f;
+        world.update_light((-1, 2), (0b1, 0), (0, 0b10), (&[sky], &[]));
+        assert_eq!(world.get_light((-16, -16, 32)), (Some(15), None));
+        assert_eq!(world.get_light((-15, -16, 32)), (Some(3), None));
+        assert_eq!(world.get_light((-16, 0, 32)), (None, Some(0)));
+        assert_eq!(world.get_light((-16, 300, 32)), (None, None));
+
+        let sign = Tag::Compound(vec![("id".into(), Tag::String("minecraft:sign".into()))]);
+        world.set_block_entity((-5, 3, 40), Some(sign.clone()));
+        world.set_block_entity((5, 3, 40), Some(sign));
+        let block_entities = &world.block_entities[&(-1, 2)];
+        assert_eq!(block_entities.len(), 1);
+        assert_eq!(block_entities[&(-5, 3, 40)].get("x"), Some(&Tag::Int(-5)));
+        assert!(!world.block_entities.contains_key(&(0, 2)));
+        world.set_block((-5, 3, 40), 0);
+        assert!(world.block_entities[&(-1, 2)].is_empty());
+
+        world.unload_chunk((-1, 2));
+        assert_eq!(world.get_block((-16, 0, 32)), None);
+        assert_eq!(world.get_light((-16, -16, 32)), (None, None));
+    }
+}